@@ -0,0 +1,26 @@
+// Generates the C header for the API in src/ffi into OUT_DIR. The copy in
+// include/chip8_emulator.h is only rewritten when CHIP8_WRITE_HEADER is set,
+// so that builds never touch the source tree:
+//
+//   CHIP8_WRITE_HEADER=1 cargo build --features ffi
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi/mod.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-env-changed=CHIP8_WRITE_HEADER");
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out = std::env::var("OUT_DIR").unwrap();
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", dir))
+            .expect("cbindgen.toml is valid");
+        let bindings = cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/ffi/mod.rs", dir))
+            .generate()
+            .expect("the C API can be expressed in C");
+        bindings.write_to_file(format!("{}/chip8_emulator.h", out));
+        if std::env::var_os("CHIP8_WRITE_HEADER").is_some() {
+            bindings.write_to_file(format!("{}/include/chip8_emulator.h", dir));
+        }
+    }
+}
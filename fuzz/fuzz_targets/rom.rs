@@ -0,0 +1,98 @@
+#![no_main]
+
+// Runs arbitrary ROMs with an arbitrary keypad sequence for a bounded number
+// of frames, on every platform and memory layout and with the debugging aids
+// switched on:
+//
+//   cargo fuzz run rom
+//
+// The input starts with the settings: a platform byte, a flags byte, a quirks
+// byte (taken over the platform's when its top bit is set), the stack depth,
+// the pages the program and the font start at, and the font. Then come four
+// bytes seeding CXKK, the number of keypad states, that many 16-bit keypad
+// states and finally the ROM itself.
+use chip8_emulator::emulator::{Emulator, Font, Platform, Quirks, Settings, MAX_STACK_DEPTH};
+use libfuzzer_sys::fuzz_target;
+
+const FRAMES: usize = 30;
+const PLATFORMS: [&str; 7] = [
+    "originalChip8",
+    "hybridVIP",
+    "modernChip8",
+    "chip48",
+    "superchip1",
+    "superchip",
+    "xochip",
+];
+
+fuzz_target!(|data: &[u8]| {
+    let [platform, flags, quirks, depth, program_start, font_start, font, data @ ..] = data else {
+        return;
+    };
+    let [a, b, c, d, keys, data @ ..] = data else {
+        return;
+    };
+    let seed = u32::from_be_bytes([*a, *b, *c, *d]);
+    let (keys, rom) = data.split_at((*keys as usize * 2).min(data.len()));
+    let keys: Vec<u16> = keys
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+
+    let platform = PLATFORMS[*platform as usize % PLATFORMS.len()];
+    let mut settings = Settings::for_platform(Platform::from_id(platform).unwrap());
+    if quirks & 0x80 != 0 {
+        settings.quirks = Quirks {
+            shift: quirks & 1 != 0,
+            memory_increment_by_x: quirks & 2 != 0,
+            memory_leave_i_unchanged: quirks & 4 != 0,
+            wrap: quirks & 8 != 0,
+            jump: quirks & 16 != 0,
+            vblank: quirks & 32 != 0,
+            logic: quirks & 64 != 0,
+        };
+    }
+    settings.stack_depth = 1 + *depth as usize % MAX_STACK_DEPTH;
+    settings.memory_stack = flags & 8 != 0;
+    settings.font = Font::builtin(Font::names()[*font as usize % Font::names().len()]).unwrap();
+    // anywhere in memory, as long as the whole font fits
+    settings.program_start = *program_start as usize * 16;
+    settings.font_start = (*font_start as usize * 16).min(0x1000 - settings.font.size());
+
+    let mut emulator = Emulator::new();
+    emulator.configure(settings);
+    emulator.seed(seed);
+    emulator.set_profiling(flags & 1 != 0);
+    emulator.set_coverage(flags & 2 != 0);
+    emulator.set_diagnostics(flags & 4 != 0);
+    if emulator.load_program(rom).is_err() {
+        return;
+    }
+
+    for frame in 0..FRAMES {
+        if let Some(keys) = keys.get(frame % keys.len().max(1)) {
+            emulator.keypad().set(*keys);
+        }
+        emulator.run_frame();
+        check(&emulator);
+    }
+    if let Some(coverage) = emulator.coverage() {
+        coverage.annotate(emulator.memory(), emulator.symbols());
+    }
+    if let Some(profiler) = emulator.profiler() {
+        profiler.report(10, emulator.symbols());
+    }
+});
+
+fn check(emulator: &Emulator) {
+    let registers = emulator.snapshot().registers;
+    assert!(registers.program_counter < 0x1000, "PC left memory");
+    assert!(
+        registers.stack_pointer <= emulator.settings().stack_depth,
+        "SP left the stack"
+    );
+    assert!(emulator
+        .display()
+        .iter()
+        .all(|row| row.iter().all(|pixel| *pixel <= 1)));
+}
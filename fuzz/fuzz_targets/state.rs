@@ -0,0 +1,38 @@
+#![no_main]
+
+// Feeds arbitrary bytes to load_state, which must either reject them or leave
+// a machine that runs and saves the same state back out:
+//
+//   cargo fuzz run state
+use chip8_emulator::emulator::Emulator;
+use libfuzzer_sys::fuzz_target;
+
+const FRAMES: usize = 5;
+// the tickrate follows the magic, version and quirk bytes
+const TICKRATE: usize = 6;
+const MAX_TICKRATE: u32 = 1000;
+
+fuzz_target!(|data: &[u8]| {
+    let mut emulator = Emulator::new();
+    if emulator.load_state(data).is_err() {
+        return;
+    }
+    let saved = emulator.save_state();
+    let mut copy = Emulator::new();
+    copy.load_state(&saved).expect("a saved state loads");
+    assert_eq!(copy.save_state(), saved);
+
+    // a state can ask for any tickrate, so only run the sane ones
+    let tickrate = u32::from_le_bytes(saved[TICKRATE..TICKRATE + 4].try_into().unwrap());
+    if tickrate <= MAX_TICKRATE {
+        for _ in 0..FRAMES {
+            emulator.run_frame();
+            let registers = emulator.snapshot().registers;
+            assert!(registers.program_counter < 0x1000, "PC left memory");
+            assert!(
+                registers.stack_pointer <= emulator.settings().stack_depth,
+                "SP left the stack"
+            );
+        }
+    }
+});
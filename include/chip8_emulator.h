@@ -0,0 +1,140 @@
+/* C API for the chip8_emulator core. Generated by cbindgen, do not edit. */
+
+#ifndef CHIP8_EMULATOR_H
+#define CHIP8_EMULATOR_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Bumped whenever a function's signature or behaviour changes.
+#define CHIP8_API_VERSION 1
+
+// Framebuffer width in pixels, also the length of a framebuffer row.
+#define CHIP8_WIDTH 128
+
+// Framebuffer height in pixels.
+#define CHIP8_HEIGHT 64
+
+typedef enum Chip8Result {
+  CHIP8_RESULT_OK = 0,
+  CHIP8_RESULT_NULL_POINTER,
+  CHIP8_RESULT_ROM_TOO_LARGE,
+  CHIP8_RESULT_BUFFER_TOO_SMALL,
+  CHIP8_RESULT_INVALID_STATE,
+} Chip8Result;
+
+// An emulator instance. Only ever handled through a pointer.
+typedef struct Chip8 Chip8;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns CHIP8_API_VERSION as compiled into the library.
+uint32_t chip8_api_version(void);
+
+// Creates an emulator with no program loaded. Free it with chip8_free.
+struct Chip8 *chip8_new(void);
+
+// Destroys an emulator created by chip8_new. Passing NULL does nothing.
+//
+// # Safety
+//
+// chip8 must be NULL or a pointer from chip8_new that hasn't been freed yet.
+// It, and any framebuffer pointer taken from it, is dangling afterwards.
+void chip8_free(struct Chip8 *chip8);
+
+// Loads a ROM image and resets the machine to run it.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call. data must be NULL or point to len readable
+// bytes, which are copied and needn't outlive the call.
+enum Chip8Result chip8_load_rom(struct Chip8 *chip8, const uint8_t *data, size_t len);
+
+// Restarts the loaded ROM from a fresh copy of memory.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call.
+enum Chip8Result chip8_reset(struct Chip8 *chip8);
+
+// Runs the given number of 60 Hz frames as fast as possible.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call.
+enum Chip8Result chip8_run_frames(struct Chip8 *chip8, uint32_t frames);
+
+// Whether the program has run into a 0000 instruction and stopped.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call.
+bool chip8_is_halted(const struct Chip8 *chip8);
+
+// Returns CHIP8_WIDTH * CHIP8_HEIGHT bytes, one per pixel and row by row,
+// each 0 or 1. The pointer is valid until the next call that mutates the
+// emulator.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new. The bytes returned
+// must not be written to, nor read once chip8 is freed or mutated.
+const uint8_t *chip8_framebuffer(const struct Chip8 *chip8);
+
+// Presses or releases one of the sixteen keypad keys (0x0 to 0xF).
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call.
+enum Chip8Result chip8_set_key(struct Chip8 *chip8, uint8_t key, bool down);
+
+// Sets the whole keypad at once, key n being bit 15 - n.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call.
+enum Chip8Result chip8_set_keys(struct Chip8 *chip8, uint16_t keys);
+
+// The number of bytes chip8_save_state needs for the current ROM.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call.
+size_t chip8_state_size(const struct Chip8 *chip8);
+
+// Writes a save state into buffer, which must hold chip8_state_size bytes.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call. buffer must be NULL or point to len writable
+// bytes that don't overlap the emulator.
+enum Chip8Result chip8_save_state(const struct Chip8 *chip8, uint8_t *buffer, size_t len);
+
+// Restores a state written by chip8_save_state. The emulator is left
+// untouched if the state is rejected.
+//
+// # Safety
+//
+// chip8 must be NULL or a live pointer from chip8_new that no other thread
+// is using during the call. data must be NULL or point to len readable
+// bytes, which are copied and needn't outlive the call.
+enum Chip8Result chip8_load_state(struct Chip8 *chip8, const uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CHIP8_EMULATOR_H */
@@ -0,0 +1,229 @@
+mod test;
+
+use crate::{
+    emulator::{Emulator, LoadError, Platform, Registers, Settings},
+    rom::Rom,
+};
+use serde_json::{json, Map, Value};
+use std::{
+    fmt,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+// One program run headless on an emulator of its own
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub name: String,
+    pub program: Vec<u8>,
+    pub settings: Settings,
+    // frames to run, unless the program halts first
+    pub frames: u32,
+    // keypad state from the given frame on, key n being bit 15 - n
+    pub inputs: Vec<(u32, u16)>,
+    // memory addresses whose final values are reported
+    pub watch: Vec<usize>,
+    // where CXKK's random numbers start, so that every run comes out the same
+    pub seed: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub name: String,
+    pub frames: u32,
+    pub halted: bool,
+    // SHA-1 of the final framebuffer, one byte per pixel, row by row
+    pub display: String,
+    pub ram: Vec<(usize, u8)>,
+    pub registers: Registers,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct JobError(String);
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid job: {}", self.0);
+    }
+}
+
+impl std::error::Error for JobError {}
+
+fn parse_address(value: &Value) -> Option<usize> {
+    if let Some(number) = value.as_u64() {
+        return Some(number as usize);
+    }
+    let text = value.as_str()?;
+    return match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    };
+}
+
+impl Job {
+    // One entry of a job file:
+    //
+    //   {"rom": "pong.ch8", "frames": 600, "platform": "superchip",
+    //    "tickrate": 30, "inputs": [[0, 256], [60, 0]], "watch": ["0x2F0"],
+    //    "seed": 7}
+    //
+    // The ROM path is relative to dir and names the job unless "name" does.
+    // Octo cartridges bring their own options, applied over the platform's.
+    pub fn parse(job: &Value, dir: &Path) -> Result<Self, JobError> {
+        let error = |message: &str| JobError(message.to_string());
+        let path = job["rom"]
+            .as_str()
+            .ok_or_else(|| error("'rom' must be a path"))?;
+        let rom =
+            Rom::open(&dir.join(path)).map_err(|err| JobError(format!("{}: {}", path, err)))?;
+        let frames = job["frames"]
+            .as_u64()
+            .and_then(|frames| u32::try_from(frames).ok())
+            .ok_or_else(|| error("'frames' must be a number of frames"))?;
+
+        let platform = match job["platform"].as_str() {
+            Some(id) => Platform::from_id(id)
+                .ok_or_else(|| JobError(format!("unknown platform '{}'", id)))?,
+            None => Platform::ModernChip8,
+        };
+        let mut settings = Settings::for_platform(platform);
+        if let Some(options) = &rom.cartridge {
+            options.apply(&mut settings);
+        }
+        if !job["tickrate"].is_null() {
+            settings.tickrate = job["tickrate"]
+                .as_u64()
+                .and_then(|tickrate| u32::try_from(tickrate).ok())
+                .ok_or_else(|| error("'tickrate' must be a number"))?;
+        }
+
+        let mut inputs = Vec::new();
+        for input in job["inputs"].as_array().into_iter().flatten() {
+            let frame = input[0]
+                .as_u64()
+                .and_then(|frame| u32::try_from(frame).ok());
+            let keys = input[1].as_u64().and_then(|keys| u16::try_from(keys).ok());
+            match (frame, keys) {
+                (Some(frame), Some(keys)) => inputs.push((frame, keys)),
+                _ => return Err(error("each input must be [frame, keys]")),
+            }
+        }
+        inputs.sort_by_key(|(frame, _)| *frame);
+
+        let mut watch = Vec::new();
+        for address in job["watch"].as_array().into_iter().flatten() {
+            match parse_address(address).filter(|address| *address < 0x1000) {
+                Some(address) => watch.push(address),
+                None => return Err(error("'watch' must list memory addresses")),
+            }
+        }
+
+        let seed = match &job["seed"] {
+            Value::Null => 0,
+            seed => seed
+                .as_u64()
+                .and_then(|seed| u32::try_from(seed).ok())
+                .ok_or_else(|| error("'seed' must be a 32-bit number"))?,
+        };
+
+        return Ok(Self {
+            name: job["name"].as_str().unwrap_or(path).to_string(),
+            program: rom.program,
+            settings,
+            frames,
+            inputs,
+            watch,
+            seed,
+        });
+    }
+
+    // A whole job file, which is a JSON array of jobs
+    pub fn parse_all(text: &str, dir: &Path) -> Result<Vec<Self>, JobError> {
+        let jobs: Value = serde_json::from_str(text).map_err(|err| JobError(err.to_string()))?;
+        let jobs = jobs
+            .as_array()
+            .ok_or_else(|| JobError(String::from("expected an array of jobs")))?;
+        return jobs.iter().map(|job| Self::parse(job, dir)).collect();
+    }
+
+    pub fn run(&self) -> Result<Outcome, LoadError> {
+        let mut emulator = Emulator::new();
+        emulator.configure(self.settings);
+        emulator.seed(self.seed);
+        emulator.load_program(&self.program)?;
+
+        let mut inputs = self.inputs.iter().peekable();
+        let mut frames = 0;
+        while frames < self.frames && !emulator.is_halted() {
+            while let Some((_, keys)) = inputs.next_if(|(frame, _)| *frame <= frames) {
+                emulator.keypad().set(*keys);
+            }
+            emulator.run_frame();
+            frames += 1;
+        }
+
+        let memory = emulator.memory();
+        return Ok(Outcome {
+            name: self.name.clone(),
+            frames,
+            halted: emulator.is_halted(),
+            display: Rom::hash(&emulator.display().concat()),
+            ram: self
+                .watch
+                .iter()
+                .map(|address| (*address, memory[*address]))
+                .collect(),
+            registers: emulator.snapshot().registers,
+        });
+    }
+}
+
+impl Outcome {
+    pub fn to_json(&self) -> Value {
+        let mut ram = Map::new();
+        for (address, value) in self.ram.iter() {
+            ram.insert(format!("0x{:03X}", address), json!(value));
+        }
+        let registers = &self.registers;
+        return json!({
+            "name": self.name,
+            "frames": self.frames,
+            "halted": self.halted,
+            "display": self.display,
+            "ram": ram,
+            "v": registers.v,
+            "i": registers.i,
+            "pc": registers.program_counter,
+        });
+    }
+}
+
+// Runs every job on a pool of threads, each taking the next job as soon as
+// it's done with the last. The results come back in the order of the jobs.
+pub fn run(jobs: &[Job], threads: usize) -> Vec<Result<Outcome, LoadError>> {
+    let next = AtomicUsize::new(0);
+    let workers = threads.clamp(1, jobs.len().max(1));
+    let mut finished: Vec<(usize, Result<Outcome, LoadError>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(index) else {
+                            return done;
+                        };
+                        done.push((index, job.run()));
+                    }
+                })
+            })
+            .collect();
+        return handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("batch worker panicked"))
+            .collect();
+    });
+    finished.sort_by_key(|(index, _)| *index);
+    return finished.into_iter().map(|(_, result)| result).collect();
+}
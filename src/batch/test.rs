@@ -0,0 +1,102 @@
+#[cfg(test)]
+use super::*;
+
+// LD V0, K; LD I, 0x300; LD [I], V0; JP 0x200: stores the key last pressed
+#[cfg(test)]
+const KEY: [u8; 8] = [0xF0, 0x0A, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+
+#[cfg(test)]
+fn job(name: &str, program: &[u8], inputs: Vec<(u32, u16)>) -> Job {
+    return Job {
+        name: name.to_string(),
+        program: program.to_vec(),
+        settings: Settings::default(),
+        frames: 30,
+        inputs,
+        watch: vec![0x300],
+        seed: 0,
+    };
+}
+
+#[test]
+fn test_batch_run() {
+    let mut jobs = Vec::new();
+    for key in 0..16 {
+        // pressed on frame 5 and released on frame 10
+        jobs.push(job(
+            &format!("key {}", key),
+            &KEY,
+            vec![(10, 0), (5, 1 << (15 - key))],
+        ));
+    }
+    jobs.push(job("halts", &[0x00, 0x00], Vec::new()));
+    jobs.push(job("too large", &[0; 0x1000], Vec::new()));
+
+    let results = run(&jobs, 4);
+    assert_eq!(results.len(), jobs.len());
+    for (key, result) in results[..16].iter().enumerate() {
+        let outcome = result.as_ref().unwrap();
+        assert_eq!(outcome.name, format!("key {}", key));
+        assert_eq!(outcome.frames, 30);
+        assert_eq!(outcome.ram, vec![(0x300, key as u8)]);
+    }
+    let halted = results[16].as_ref().unwrap();
+    assert!(halted.halted);
+    assert_eq!(halted.frames, 1);
+    assert!(results[17].is_err());
+
+    // the same job always ends up the same, whichever thread ran it
+    let again = run(&jobs[..1], 1);
+    assert_eq!(again[0].as_ref().unwrap(), results[0].as_ref().unwrap());
+    assert_eq!(results[0].as_ref().unwrap().to_json()["ram"]["0x300"], 0);
+}
+
+#[test]
+fn test_batch_seed() {
+    // RND V0, 0xFF; LD I, 0x300; LD [I], V0; JP 0x200
+    let random = [0xC0, 0xFF, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+    let mut jobs = Vec::new();
+    for seed in [1, 1, 2] {
+        let mut job = job("random", &random, Vec::new());
+        job.seed = seed;
+        jobs.push(job);
+    }
+    let results: Vec<Outcome> = run(&jobs, 3).into_iter().map(Result::unwrap).collect();
+    assert_eq!(results[0], results[1]);
+    assert_ne!(results[0].ram, results[2].ram);
+}
+
+#[test]
+fn test_job_parse() {
+    let dir = std::env::temp_dir().join(format!("chip8-batch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("key.ch8"), KEY).unwrap();
+
+    let jobs = Job::parse_all(
+        r#"[{"rom": "key.ch8", "frames": 60, "platform": "superchip", "tickrate": 20,
+             "inputs": [[30, 0], [0, 32768]], "watch": ["0x300", 769], "seed": 7},
+            {"name": "plain", "rom": "key.ch8", "frames": 1}]"#,
+        &dir,
+    )
+    .unwrap();
+    assert_eq!(jobs[0].name, "key.ch8");
+    assert_eq!(jobs[0].settings.quirks, Platform::SuperChip.quirks());
+    assert_eq!(jobs[0].settings.tickrate, 20);
+    assert_eq!(jobs[0].inputs, vec![(0, 0x8000), (30, 0)]);
+    assert_eq!(jobs[0].watch, vec![0x300, 0x301]);
+    assert_eq!(jobs[0].seed, 7);
+    assert_eq!(jobs[1].name, "plain");
+    assert_eq!(jobs[1].seed, 0);
+
+    let error = |job: &str| Job::parse_all(job, &dir).unwrap_err();
+    assert_eq!(
+        error(r#"[{"rom": "key.ch8"}]"#),
+        JobError(String::from("'frames' must be a number of frames"))
+    );
+    assert_eq!(
+        error(r#"[{"rom": "key.ch8", "frames": 1, "watch": ["0x1000"]}]"#),
+        JobError(String::from("'watch' must list memory addresses"))
+    );
+    assert!(error(r#"{"rom": "key.ch8"}"#).0.contains("array"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
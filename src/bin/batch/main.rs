@@ -0,0 +1,67 @@
+use chip8_emulator::batch::{self, Job};
+use std::{env, fs, path::Path, process, thread};
+
+// Runs every job in a JSON job file headless, spread over a pool of threads,
+// and prints one JSON line per job, in the order of the file:
+//
+//   [{"rom": "pong.ch8", "frames": 600, "inputs": [[0, 256]], "watch": [754]}]
+//
+// The pool is as large as the machine has cores, unless --threads says.
+fn usage() -> ! {
+    eprintln!("usage: chip8_batch [--threads <n>] <jobs.json>");
+    process::exit(2);
+}
+
+fn main() {
+    let mut threads = None;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threads" => match args.next().and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if n > 0 => threads = Some(n),
+                _ => usage(),
+            },
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => usage(),
+    };
+    let threads = match threads {
+        Some(threads) => threads,
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("could not read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+    let dir = Path::new(&path).parent().unwrap_or(Path::new(""));
+    let jobs = match Job::parse_all(&text, dir) {
+        Ok(jobs) => jobs,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let mut failed = false;
+    for (job, result) in jobs.iter().zip(batch::run(&jobs, threads)) {
+        match result {
+            Ok(outcome) => println!("{}", outcome.to_json()),
+            Err(err) => {
+                eprintln!("{}: {}", job.name, err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,25 @@
+use std::{env, path::Path, process};
+
+// Serves JSON-RPC on a Unix socket so scripts in any language can drive the
+// emulator, one request per line:
+//
+//   {"jsonrpc":"2.0","id":1,"method":"load","params":{"path":"pong.ch8"}}
+//   {"jsonrpc":"2.0","id":2,"method":"step","params":{"frames":60}}
+//
+// Methods: load, reset, step, press, release, registers, read_memory,
+// framebuffer, save_state, load_state, cheat_search_start, cheat_search,
+// cheats, set_cheats, load_cheats, save_cheats, profile, profile_report,
+// coverage, coverage_report, diagnostics, warnings and load_symbols.
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: chip8_rpc <socket>");
+            process::exit(2);
+        }
+    };
+    if let Err(err) = chip8_emulator::rpc::serve(Path::new(&path)) {
+        eprintln!("could not serve on {}: {}", path, err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,503 @@
+use chip8_emulator::{
+    config::Config,
+    database::Database,
+    emulator::{Control, Emulator, Event, Reset, SymbolError, Symbols},
+    filter::Filter,
+    keymap::Keymap,
+    palette::Palette,
+    rom::{Rom, Watcher},
+};
+use crossterm::{
+    cursor,
+    event::{
+        self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    queue,
+    style::{self, Color, Print},
+    terminal::{self, ClearType},
+};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+const FRAME: Duration = Duration::from_micros(16_667);
+// Most terminals only report key presses, so a key counts as held until its
+// autorepeat stops arriving.
+const HOLD: Duration = Duration::from_millis(250);
+// rows of each table in the profiling report
+const REPORT_ROWS: usize = 20;
+
+// Puts the terminal back the way we found it, even if we panic.
+struct Terminal {
+    enhanced: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        queue!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        let enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if enhanced {
+            queue!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        stdout.flush()?;
+        return Ok(Self { enhanced });
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.enhanced {
+            let _ = queue!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = queue!(
+            stdout,
+            style::ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = stdout.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Frontend {
+    title: String,
+    palette: Palette,
+    filter: Filter,
+    keymap: Keymap,
+    display: [[u8; 128]; 64],
+    // when each keypad key was last seen going down
+    held: [Option<Instant>; 16],
+    keys: u16,
+    paused: bool,
+    slow_motion: bool,
+    fast_forward: bool,
+    control: mpsc::Sender<Control>,
+    keyboard: mpsc::Sender<u16>,
+    // set with --watch, to reload the ROM whenever it is rebuilt
+    watcher: Option<Watcher>,
+    keep_registers: bool,
+    // reread along with the ROM, as the assembler rewrites them too
+    symbol_files: SymbolFiles,
+    // how the last reload went
+    notice: Option<String>,
+}
+
+impl Frontend {
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+        {
+            return false;
+        }
+        if key.kind != KeyEventKind::Release {
+            match key.code {
+                KeyCode::F(5) => {
+                    self.paused = !self.paused;
+                    self.send(if self.paused {
+                        Control::Pause
+                    } else {
+                        Control::Resume
+                    });
+                }
+                KeyCode::F(6) => {
+                    if !self.paused {
+                        self.paused = true;
+                        self.send(Control::Pause);
+                    }
+                    self.send(Control::FrameAdvance);
+                }
+                KeyCode::F(7) => self.send(Control::Reset(Reset::Soft)),
+                KeyCode::F(8) => self.send(Control::Reset(Reset::Hard)),
+                KeyCode::F(9) => {
+                    self.slow_motion = !self.slow_motion;
+                    self.fast_forward = false;
+                    self.send(Control::Speed(self.speed()));
+                }
+                KeyCode::F(10) => {
+                    self.fast_forward = !self.fast_forward;
+                    self.slow_motion = false;
+                    self.send(Control::Speed(self.speed()));
+                }
+                _ => {}
+            }
+        }
+
+        let name = match key.code {
+            KeyCode::Char(' ') => String::from("space"),
+            KeyCode::Char(c) => c.to_ascii_lowercase().to_string(),
+            KeyCode::Up => String::from("up"),
+            KeyCode::Down => String::from("down"),
+            KeyCode::Left => String::from("left"),
+            KeyCode::Right => String::from("right"),
+            KeyCode::Enter => String::from("enter"),
+            _ => return true,
+        };
+        let pad = Keymap::keycode(&name).and_then(|code| self.keymap.key(code));
+        if let Some(pad) = pad {
+            self.held[pad as usize] = match key.kind {
+                KeyEventKind::Release => None,
+                _ => Some(Instant::now()),
+            };
+            self.update_keys();
+        }
+        return true;
+    }
+
+    fn update_keys(&mut self) {
+        let mut keys = 0;
+        for (pad, held) in self.held.iter_mut().enumerate() {
+            if held.map_or(false, |since| since.elapsed() > HOLD) {
+                *held = None;
+            }
+            if held.is_some() {
+                keys |= 1 << (15 - pad);
+            }
+        }
+        if keys != self.keys {
+            self.keys = keys;
+            let _ = self.keyboard.send(keys);
+        }
+    }
+
+    fn send(&self, control: Control) {
+        // the emulator thread only goes away during shutdown
+        let _ = self.control.send(control);
+    }
+
+    fn reload(&mut self) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        if !watcher.changed() {
+            return;
+        }
+        self.notice = Some(match Rom::open(watcher.path()) {
+            Ok(rom) => {
+                let notice = match self.symbol_files.load(&rom) {
+                    Ok(symbols) => {
+                        self.send(Control::SetSymbols(symbols));
+                        String::from("reloaded")
+                    }
+                    Err(err) => format!("reloaded, but not the symbols: {}", err),
+                };
+                self.send(Control::Reload(rom.program, self.keep_registers));
+                notice
+            }
+            Err(err) => format!("reload failed: {}", err),
+        });
+    }
+
+    fn speed(&self) -> f32 {
+        if self.slow_motion {
+            return 0.25;
+        }
+        if self.fast_forward {
+            return 4.0;
+        }
+        return 1.0;
+    }
+
+    // Each character cell shows two pixels stacked on top of each other.
+    fn present(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let mut cells: Vec<(usize, usize)> = self
+            .filter
+            .take_dirty()
+            .into_iter()
+            .map(|(row, col)| (row / 2, col))
+            .collect();
+        cells.sort();
+        cells.dedup();
+        for (cell_row, col) in cells {
+            let top = self.filter.color(cell_row * 2, col, &self.palette);
+            let bottom = self.filter.color(cell_row * 2 + 1, col, &self.palette);
+            queue!(
+                out,
+                cursor::MoveTo(col as u16, cell_row as u16),
+                style::SetForegroundColor(Self::color(top)),
+                style::SetBackgroundColor(Self::color(bottom)),
+                Print('▀')
+            )?;
+        }
+
+        let mut status = self.title.clone();
+        if self.paused {
+            status.push_str(" [paused]");
+        } else if self.slow_motion {
+            status.push_str(" [slow motion]");
+        } else if self.fast_forward {
+            status.push_str(" [fast forward]");
+        }
+        if let Some(notice) = &self.notice {
+            status.push_str(&format!(" [{}]", notice));
+        }
+        queue!(
+            out,
+            style::ResetColor,
+            cursor::MoveTo(0, 32),
+            terminal::Clear(ClearType::CurrentLine),
+            Print(&status)
+        )?;
+        return out.flush();
+    }
+
+    fn color(color: chip8_emulator::palette::Color) -> Color {
+        return Color::Rgb {
+            r: color.0,
+            g: color.1,
+            b: color.2,
+        };
+    }
+}
+
+// Where to write reports once the emulator stops, if anywhere
+#[derive(Default)]
+struct Reports {
+    profile: Option<PathBuf>,
+    folded: Option<PathBuf>,
+    coverage: Option<PathBuf>,
+    disassembly: Option<PathBuf>,
+    diagnostics: Option<PathBuf>,
+}
+
+impl Reports {
+    fn profiling(&self) -> bool {
+        return self.profile.is_some() || self.folded.is_some();
+    }
+
+    fn covering(&self) -> bool {
+        return self.coverage.is_some() || self.disassembly.is_some();
+    }
+
+    fn write(&self, emulator: &Emulator) -> io::Result<()> {
+        let symbols = emulator.symbols();
+        if let Some(profiler) = emulator.profiler() {
+            if let Some(path) = &self.profile {
+                fs::write(path, profiler.report(REPORT_ROWS, symbols))?;
+            }
+            if let Some(path) = &self.folded {
+                fs::write(path, profiler.folded(symbols))?;
+            }
+        }
+        if let (Some(diagnostics), Some(path)) = (emulator.diagnostics(), &self.diagnostics) {
+            fs::write(path, diagnostics.report(symbols))?;
+        }
+        if let Some(coverage) = emulator.coverage() {
+            if let Some(path) = &self.coverage {
+                fs::write(path, coverage.map())?;
+            }
+            if let Some(path) = &self.disassembly {
+                fs::write(path, coverage.annotate(emulator.memory(), symbols))?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+fn run(rom: Rom, symbols: Symbols, reports: Reports, watch: Watch) -> io::Result<()> {
+    let config = Config::load();
+    let database =
+        Database::open(config.database().as_deref()).unwrap_or_else(|_| Database::bundled());
+    let setup = config.setup(&rom, Some(&database));
+
+    let (keyboard_sender, keyboard_receiver) = mpsc::channel::<u16>();
+    let (control_sender, control_receiver) = mpsc::channel::<Control>();
+    let (event_sender, event_receiver) = mpsc::channel::<Event>();
+
+    let (profiling, covering) = (reports.profiling(), reports.covering());
+    let diagnosing = reports.diagnostics.is_some();
+    let emulator = thread::spawn(move || {
+        let mut emulator = Emulator::new();
+        emulator.set_profiling(profiling);
+        emulator.set_coverage(covering);
+        emulator.set_diagnostics(diagnosing);
+        emulator.set_symbols(symbols);
+        emulator.start(keyboard_receiver, control_receiver, |event| {
+            if !matches!(event, Event::Snapshot(_)) {
+                let _ = event_sender.send(event);
+            }
+        });
+        return emulator;
+    });
+
+    let mut frontend = Frontend {
+        title: format!(
+            "CHIP-8 - {}  (F5 pause, F6 step, F7/F8 reset, Esc quit)",
+            setup.title
+        ),
+        palette: setup.palette,
+        filter: Filter::new(setup.filter),
+        keymap: setup.keymap,
+        display: [[0; 128]; 64],
+        held: [None; 16],
+        keys: 0,
+        paused: false,
+        slow_motion: false,
+        fast_forward: false,
+        control: control_sender,
+        keyboard: keyboard_sender,
+        watcher: watch.path.as_deref().map(Watcher::new),
+        keep_registers: watch.keep_registers,
+        symbol_files: watch.symbol_files,
+        notice: None,
+    };
+    frontend.send(Control::Configure(setup.settings));
+    frontend.send(Control::Load(rom.program));
+
+    let terminal = Terminal::enter()?;
+    let mut stdout = io::stdout();
+    queue!(
+        stdout,
+        style::SetBackgroundColor(Frontend::color(frontend.palette.color(0))),
+        terminal::Clear(ClearType::All)
+    )?;
+    frontend.filter.clear();
+    frontend.present(&mut stdout)?;
+
+    let mut next_frame = Instant::now() + FRAME;
+    'running: loop {
+        let timeout = next_frame.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            if let event::Event::Key(key) = event::read()? {
+                if !frontend.handle_key(key) {
+                    break 'running;
+                }
+            }
+            continue;
+        }
+        next_frame = Instant::now() + FRAME;
+
+        // the filter ages pixels by emulated frames rather than redraws
+        while let Ok(event) = event_receiver.try_recv() {
+            match event {
+                Event::Display(display) => {
+                    for row in 0..64 {
+                        for col in 0..128 {
+                            if display[row][col] != frontend.display[row][col] {
+                                frontend.filter.draw(row, col, display[row][col]);
+                            }
+                        }
+                    }
+                    frontend.display = *display;
+                }
+                Event::Frame => frontend.filter.end_frame(),
+                Event::Error(message) => frontend.notice = Some(message),
+                Event::Snapshot(_) => {}
+            }
+        }
+        if !terminal.enhanced {
+            frontend.update_keys();
+        }
+        frontend.reload();
+        frontend.present(&mut stdout)?;
+    }
+
+    frontend.send(Control::ShutDown);
+    drop(frontend);
+    drop(terminal);
+    match emulator.join() {
+        Ok(emulator) => {
+            if let Err(err) = reports.write(&emulator) {
+                eprintln!("failed to write reports: {}", err);
+            }
+        }
+        Err(_) => eprintln!("emulator thread panicked"),
+    }
+    return Ok(());
+}
+
+// Labels and source lines for the reports, on top of any the ROM came with
+#[derive(Default)]
+struct SymbolFiles {
+    symbols: Option<PathBuf>,
+    source_map: Option<PathBuf>,
+}
+
+impl SymbolFiles {
+    fn load(&self, rom: &Rom) -> Result<Symbols, SymbolError> {
+        return rom.symbols_with(self.symbols.as_deref(), self.source_map.as_deref());
+    }
+}
+
+// Whether to reload the ROM when it changes on disk, and how
+#[derive(Default)]
+struct Watch {
+    path: Option<PathBuf>,
+    keep_registers: bool,
+    symbol_files: SymbolFiles,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: chip8_tui [--profile <report>] [--folded <stacks>] \
+         [--coverage <map>] [--disassembly <listing>] [--diagnostics <warnings>] \
+         [--symbols <labels>] [--source-map <lines>] [--watch [--keep-registers]] <rom>"
+    );
+    process::exit(2);
+}
+
+fn path_argument(args: &mut impl Iterator<Item = String>) -> PathBuf {
+    return args.next().map(PathBuf::from).unwrap_or_else(|| usage());
+}
+
+fn main() {
+    let mut reports = Reports::default();
+    let mut watching = false;
+    let mut watch = Watch::default();
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => reports.profile = Some(path_argument(&mut args)),
+            "--folded" => reports.folded = Some(path_argument(&mut args)),
+            "--coverage" => reports.coverage = Some(path_argument(&mut args)),
+            "--disassembly" => reports.disassembly = Some(path_argument(&mut args)),
+            "--diagnostics" => reports.diagnostics = Some(path_argument(&mut args)),
+            "--symbols" => watch.symbol_files.symbols = Some(path_argument(&mut args)),
+            "--source-map" => watch.symbol_files.source_map = Some(path_argument(&mut args)),
+            "--watch" => watching = true,
+            "--keep-registers" => watch.keep_registers = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(path) = path else {
+        usage();
+    };
+    if watching {
+        watch.path = Some(PathBuf::from(&path));
+    } else if watch.keep_registers {
+        usage();
+    }
+    let rom = match Rom::open(Path::new(&path)) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("could not open {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+    let symbols = match watch.symbol_files.load(&rom) {
+        Ok(symbols) => symbols,
+        Err(err) => {
+            eprintln!("could not read symbols: {}", err);
+            process::exit(1);
+        }
+    };
+    if let Err(err) = run(rom, symbols, reports, watch) {
+        eprintln!("terminal error: {}", err);
+        process::exit(1);
+    }
+}
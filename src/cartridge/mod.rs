@@ -0,0 +1,339 @@
+mod test;
+
+use crate::{
+    emulator::{Font, Settings, Symbols},
+    octo::{self, OctoError},
+    palette::{Color, Palette},
+};
+use serde_json::Value;
+use std::fmt;
+
+// LZW codes never grow past twelve bits
+const MAX_CODES: usize = 4096;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    NotAGif,
+    // the image itself is damaged
+    Corrupt(&'static str),
+    // the image is fine but holds no Octo program
+    NoPayload,
+    Assemble(OctoError),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            CartridgeError::NotAGif => write!(f, "not a GIF image"),
+            CartridgeError::Corrupt(what) => write!(f, "damaged GIF image: {}", what),
+            CartridgeError::NoPayload => write!(f, "image is not an Octo cartridge"),
+            CartridgeError::Assemble(err) => write!(f, "cartridge program: {}", err),
+        };
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
+// The settings Octo saves alongside a program. Anything left out keeps
+// whatever the platform or database would have used.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    pub tickrate: Option<u32>,
+    pub shift: Option<bool>,
+    pub memory_leave_i_unchanged: Option<bool>,
+    pub wrap: Option<bool>,
+    pub jump: Option<bool>,
+    pub vblank: Option<bool>,
+    pub logic: Option<bool>,
+    // background, fill, second plane fill and the blend of both planes
+    pub colors: [Option<Color>; 4],
+    pub font: Option<Font>,
+}
+
+impl Options {
+    pub fn parse(options: &Value) -> Self {
+        let flag = |name: &str| options[name].as_bool();
+        let color = |name: &str| options[name].as_str().and_then(Color::parse);
+        // Octo's styles by the builtin font closest to each. Octo's own has
+        // the same small digits as ours, the ETI 660 used the VIP's and FISH
+        // 'N' CHIPS is a SUPER-CHIP interpreter.
+        let font = match options["fontStyle"].as_str() {
+            Some("octo") => Font::builtin("modern"),
+            Some("vip") | Some("eti660") => Font::builtin("vip"),
+            Some("dream6800") => Font::builtin("dream6800"),
+            Some("fish") | Some("schip") => Font::builtin("schip"),
+            _ => None,
+        };
+        return Self {
+            tickrate: options["tickrate"].as_u64().map(|t| t as u32),
+            shift: flag("shiftQuirks"),
+            memory_leave_i_unchanged: flag("loadStoreQuirks"),
+            // Octo's quirk is clipping, the opposite of wrapping
+            wrap: flag("clipQuirks").map(|clip| !clip),
+            jump: flag("jumpQuirks"),
+            vblank: flag("vBlankQuirks"),
+            logic: flag("logicQuirks"),
+            colors: [
+                color("backgroundColor"),
+                color("fillColor"),
+                color("fillColor2"),
+                color("blendColor"),
+            ],
+            font,
+        };
+    }
+
+    pub fn apply(&self, settings: &mut Settings) {
+        let quirks = &mut settings.quirks;
+        let overrides = [
+            (&mut quirks.shift, self.shift),
+            (
+                &mut quirks.memory_leave_i_unchanged,
+                self.memory_leave_i_unchanged,
+            ),
+            (&mut quirks.wrap, self.wrap),
+            (&mut quirks.jump, self.jump),
+            (&mut quirks.vblank, self.vblank),
+            (&mut quirks.logic, self.logic),
+        ];
+        for (quirk, value) in overrides {
+            if let Some(value) = value {
+                *quirk = value;
+            }
+        }
+        if self.memory_leave_i_unchanged == Some(true) {
+            quirks.memory_increment_by_x = false;
+        }
+        if let Some(tickrate) = self.tickrate {
+            settings.tickrate = tickrate;
+        }
+        if let Some(font) = self.font {
+            settings.font = font;
+        }
+    }
+
+    // Needs at least the background and fill colours
+    pub fn palette(&self, name: &str) -> Option<Palette> {
+        let background = self.colors[0]?;
+        let fill = self.colors[1]?;
+        return Some(Palette {
+            name: name.to_string(),
+            colors: [
+                background,
+                fill,
+                self.colors[2].unwrap_or(fill),
+                self.colors[3].unwrap_or(fill),
+            ],
+            bezel: Palette::classic().bezel,
+        });
+    }
+}
+
+// An Octo cartridge: a GIF that carries the source of a program and its
+// options hidden in its pixels, which is how much of the XO-CHIP and game
+// jam catalogue is shared.
+#[derive(Clone, Debug)]
+pub struct Cartridge {
+    pub source: String,
+    pub program: Vec<u8>,
+    pub options: Options,
+    // the labels and lines of the source, which is called "cartridge"
+    pub symbols: Symbols,
+}
+
+impl Cartridge {
+    pub fn is_cartridge(data: &[u8]) -> bool {
+        return data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a");
+    }
+
+    // Octo stores the payload in the low two bits of each pixel's colour
+    // index, frame after frame, four pixels to a byte with the high bits
+    // first. It starts with its length as a big endian u32 and is JSON with
+    // the source under "program" and the settings under "options".
+    pub fn parse(data: &[u8]) -> Result<Self, CartridgeError> {
+        let pixels = decode_gif(data)?;
+        let bytes: Vec<u8> = pixels
+            .chunks_exact(4)
+            .map(|p| (p[0] & 3) << 6 | (p[1] & 3) << 4 | (p[2] & 3) << 2 | (p[3] & 3))
+            .collect();
+        let Some((size, payload)) = bytes.split_first_chunk::<4>() else {
+            return Err(CartridgeError::NoPayload);
+        };
+        let size = u32::from_be_bytes(*size) as usize;
+        let payload = payload.get(..size).ok_or(CartridgeError::NoPayload)?;
+        let json: Value = serde_json::from_slice(payload).map_err(|_| CartridgeError::NoPayload)?;
+        let source = json["program"]
+            .as_str()
+            .ok_or(CartridgeError::NoPayload)?
+            .to_string();
+
+        let (program, symbols) =
+            octo::assemble_with_symbols(&source, "cartridge").map_err(CartridgeError::Assemble)?;
+        return Ok(Self {
+            source,
+            program,
+            options: Options::parse(&json["options"]),
+            symbols,
+        });
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CartridgeError> {
+        if self.data.len() < len {
+            return Err(CartridgeError::Corrupt("truncated"));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        return Ok(taken);
+    }
+
+    fn u8(&mut self) -> Result<u8, CartridgeError> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn u16(&mut self) -> Result<usize, CartridgeError> {
+        let bytes = self.take(2)?;
+        return Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize);
+    }
+
+    // a run of length prefixed sub-blocks, ending with an empty one
+    fn blocks(&mut self) -> Result<Vec<u8>, CartridgeError> {
+        let mut data = Vec::new();
+        loop {
+            let len = self.u8()? as usize;
+            if len == 0 {
+                return Ok(data);
+            }
+            data.extend_from_slice(self.take(len)?);
+        }
+    }
+}
+
+// The colour index of every pixel of every frame, in order
+fn decode_gif(data: &[u8]) -> Result<Vec<u8>, CartridgeError> {
+    if !Cartridge::is_cartridge(data) {
+        return Err(CartridgeError::NotAGif);
+    }
+    let mut reader = Reader { data: &data[6..] };
+    reader.take(4)?;
+    let flags = reader.u8()?;
+    reader.take(2)?;
+    if flags & 0x80 != 0 {
+        reader.take(3 << ((flags & 7) + 1))?;
+    }
+
+    let mut pixels = Vec::new();
+    loop {
+        match reader.u8()? {
+            // extensions: animation timing, comments and the like
+            0x21 => {
+                reader.u8()?;
+                reader.blocks()?;
+            }
+            0x2C => {
+                reader.take(4)?;
+                let width = reader.u16()?;
+                let height = reader.u16()?;
+                let flags = reader.u8()?;
+                if flags & 0x80 != 0 {
+                    reader.take(3 << ((flags & 7) + 1))?;
+                }
+                let min_code_size = reader.u8()?;
+                let frame = decode_lzw(min_code_size, &reader.blocks()?, width * height)?;
+                match flags & 0x40 != 0 {
+                    true => pixels.extend(deinterlace(&frame, width)),
+                    false => pixels.extend(frame),
+                }
+            }
+            0x3B => return Ok(pixels),
+            _ => return Err(CartridgeError::Corrupt("unknown block")),
+        }
+    }
+}
+
+fn decode_lzw(min_code_size: u8, data: &[u8], pixels: usize) -> Result<Vec<u8>, CartridgeError> {
+    if !(1..=11).contains(&min_code_size) {
+        return Err(CartridgeError::Corrupt("bad code size"));
+    }
+    let clear = 1 << min_code_size;
+    let end = clear + 1;
+    let reset = || -> Vec<Vec<u8>> {
+        let mut table: Vec<Vec<u8>> = (0..clear).map(|i| vec![i as u8]).collect();
+        // the clear and end codes have no string of their own
+        table.push(Vec::new());
+        table.push(Vec::new());
+        return table;
+    };
+
+    let mut table = reset();
+    let mut size = min_code_size as usize + 1;
+    let mut previous: Option<usize> = None;
+    // the header's size is only a claim, so memory follows the data actually there
+    let mut output = Vec::with_capacity(pixels.min(data.len() * 8));
+    let mut bit = 0;
+    while bit + size <= data.len() * 8 && output.len() < pixels {
+        let mut code = 0;
+        for n in 0..size {
+            let position = bit + n;
+            code |= ((data[position / 8] >> (position % 8) & 1) as usize) << n;
+        }
+        bit += size;
+
+        if code == clear {
+            table = reset();
+            size = min_code_size as usize + 1;
+            previous = None;
+            continue;
+        }
+        if code == end {
+            break;
+        }
+        let entry = match (table.get(code), previous) {
+            (Some(entry), _) => entry.clone(),
+            (None, Some(previous)) if code == table.len() => {
+                let mut entry = table[previous].clone();
+                entry.push(entry[0]);
+                entry
+            }
+            _ => return Err(CartridgeError::Corrupt("bad LZW code")),
+        };
+        output.extend_from_slice(&entry);
+        if let Some(previous) = previous {
+            if table.len() < MAX_CODES {
+                let mut added = table[previous].clone();
+                added.push(entry[0]);
+                table.push(added);
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+        }
+        previous = Some(code);
+    }
+    output.truncate(pixels);
+    return Ok(output);
+}
+
+// Interlaced frames store every eighth row, then the rows between those,
+// and so on
+fn deinterlace(frame: &[u8], width: usize) -> Vec<u8> {
+    if width == 0 {
+        return frame.to_vec();
+    }
+    let height = frame.len() / width;
+    let mut rows: Vec<usize> = Vec::with_capacity(height);
+    for (start, step) in [(0, 8), (4, 8), (2, 4), (1, 2)] {
+        rows.extend((start..height).step_by(step));
+    }
+    let mut pixels = vec![0; frame.len()];
+    for (stored, row) in rows.into_iter().enumerate() {
+        let from = stored * width;
+        pixels[row * width..(row + 1) * width].copy_from_slice(&frame[from..from + width]);
+    }
+    return pixels;
+}
@@ -0,0 +1,128 @@
+#[cfg(test)]
+use super::*;
+#[cfg(test)]
+use crate::emulator::Platform;
+
+// A GIF with one frame per slice of colour indices, using eight colours. The
+// LZW stream clears the table every few codes so they all stay four bits.
+#[cfg(test)]
+fn gif(frames: &[&[u8]]) -> Vec<u8> {
+    let mut data = b"GIF89a".to_vec();
+    data.extend_from_slice(&[1, 0, 1, 0, 0x82, 0, 0]);
+    data.extend_from_slice(&[0; 3 * 8]);
+    for frame in frames {
+        data.extend_from_slice(&[0x21, 0xF9, 4, 0, 10, 0, 0, 0]);
+        data.push(0x2C);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&(frame.len() as u16).to_le_bytes());
+        data.extend_from_slice(&[1, 0, 0]);
+        data.push(3);
+
+        let mut codes = Vec::new();
+        for chunk in frame.chunks(4) {
+            codes.push(8);
+            codes.extend(chunk.iter().map(|index| *index as u16));
+        }
+        codes.push(9);
+        let mut packed = vec![0u8; (codes.len() * 4).div_ceil(8)];
+        for (n, code) in codes.iter().enumerate() {
+            for bit in 0..4 {
+                let position = n * 4 + bit;
+                packed[position / 8] |= ((code >> bit & 1) as u8) << (position % 8);
+            }
+        }
+        for block in packed.chunks(255) {
+            data.push(block.len() as u8);
+            data.extend_from_slice(block);
+        }
+        data.push(0);
+    }
+    data.push(0x3B);
+    return data;
+}
+
+// Hides a payload the way Octo does, setting the third bit of some pixels as
+// the label artwork would
+#[cfg(test)]
+fn cartridge(payload: &str) -> Vec<u8> {
+    let mut bytes = (payload.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(payload.as_bytes());
+    let mut pixels = Vec::new();
+    for (n, byte) in bytes.iter().enumerate() {
+        for shift in [6, 4, 2, 0] {
+            pixels.push(byte >> shift & 3 | (n as u8 & 1) << 2);
+        }
+    }
+    let (first, second) = pixels.split_at(pixels.len() / 2);
+    return gif(&[first, second]);
+}
+
+#[test]
+fn test_decode_gif() {
+    let pixels: Vec<u8> = (0..40).map(|n| n % 8).collect();
+    assert_eq!(decode_gif(&gif(&[&pixels])).unwrap(), pixels);
+    assert!(matches!(decode_gif(b"PNG"), Err(CartridgeError::NotAGif)));
+    let truncated = gif(&[&pixels]);
+    assert!(matches!(
+        decode_gif(&truncated[..truncated.len() - 8]),
+        Err(CartridgeError::Corrupt(_))
+    ));
+    // a header claiming billions of pixels can't make us allocate them
+    assert_eq!(decode_lzw(2, &[0x44, 0x01], usize::MAX).unwrap(), vec![0]);
+}
+
+#[test]
+fn test_deinterlace() {
+    let rows: Vec<u8> = [0, 8, 4, 2, 6, 1, 3, 5, 7, 9].to_vec();
+    assert_eq!(deinterlace(&rows, 1), (0..10).collect::<Vec<u8>>());
+}
+
+#[test]
+fn test_parse_cartridge() {
+    let payload = r##"{
+        "program": ": main\n  v0 := 1\n  jump main\n",
+        "options": {
+            "tickrate": 20,
+            "backgroundColor": "#000000",
+            "fillColor": "#FF0000",
+            "shiftQuirks": true,
+            "loadStoreQuirks": true,
+            "clipQuirks": false,
+            "fontStyle": "vip"
+        }
+    }"##;
+    let cartridge = Cartridge::parse(&cartridge(payload)).unwrap();
+    assert_eq!(cartridge.program, vec![0x60, 0x01, 0x12, 0x00]);
+
+    let mut settings = Settings::for_platform(Platform::OriginalChip8);
+    cartridge.options.apply(&mut settings);
+    assert_eq!(settings.tickrate, 20);
+    assert!(settings.quirks.shift);
+    assert!(settings.quirks.memory_leave_i_unchanged);
+    assert!(settings.quirks.wrap);
+    assert!(settings.quirks.vblank);
+    assert_eq!(settings.font, Font::builtin("vip").unwrap());
+
+    let palette = cartridge.options.palette("cart").unwrap();
+    assert_eq!(palette.colors[1], Color(0xFF, 0, 0));
+    assert_eq!(palette.colors[3], Color(0xFF, 0, 0));
+
+    let font = |style: &str| Options::parse(&serde_json::json!({ "fontStyle": style })).font;
+    assert_eq!(font("eti660"), Font::builtin("vip"));
+    assert_eq!(font("fish"), Font::builtin("schip"));
+    assert_eq!(font("dream6800"), Font::builtin("dream6800"));
+    assert_eq!(font("octo"), Font::builtin("modern"));
+    assert_eq!(font("unknown"), None);
+}
+
+#[test]
+fn test_cartridge_errors() {
+    assert!(matches!(
+        Cartridge::parse(&cartridge("not json")),
+        Err(CartridgeError::NoPayload)
+    ));
+    assert!(matches!(
+        Cartridge::parse(&cartridge(r#"{"program": "jump nowhere"}"#)),
+        Err(CartridgeError::Assemble(_))
+    ));
+}
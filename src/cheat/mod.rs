@@ -0,0 +1,229 @@
+mod test;
+
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+const MEMORY_SIZE: usize = 0x1000;
+
+// How a byte compares with a constant or with its own earlier value, as
+// used by the cheat search and by the gym's predicates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    // compared with the byte's value before the step
+    Increased,
+    Decreased,
+    Changed,
+    Unchanged,
+}
+
+impl Comparison {
+    // "==", ">=" and so on, or "increased", "decreased", "changed" and
+    // "unchanged"
+    pub fn parse(text: &str) -> Option<Self> {
+        return match text {
+            "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            "increased" => Some(Comparison::Increased),
+            "decreased" => Some(Comparison::Decreased),
+            "changed" => Some(Comparison::Changed),
+            "unchanged" => Some(Comparison::Unchanged),
+            _ => None,
+        };
+    }
+
+    // whether the byte is compared with its old value rather than a constant
+    pub fn is_relative(&self) -> bool {
+        return matches!(
+            self,
+            Comparison::Increased
+                | Comparison::Decreased
+                | Comparison::Changed
+                | Comparison::Unchanged
+        );
+    }
+
+    pub fn holds(&self, old: u8, new: u8, value: u8) -> bool {
+        return match self {
+            Comparison::Equal => new == value,
+            Comparison::NotEqual => new != value,
+            Comparison::Less => new < value,
+            Comparison::LessOrEqual => new <= value,
+            Comparison::Greater => new > value,
+            Comparison::GreaterOrEqual => new >= value,
+            Comparison::Increased => new > old,
+            Comparison::Decreased => new < old,
+            Comparison::Changed => new != old,
+            Comparison::Unchanged => new == old,
+        };
+    }
+}
+
+// A byte written back into memory at the start of every frame, which either
+// freezes it at the value it had or overrides it with a new one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub address: usize,
+    pub value: u8,
+    pub enabled: bool,
+    pub description: String,
+}
+
+impl Cheat {
+    pub fn new(address: usize, value: u8, description: &str) -> Self {
+        return Self {
+            address,
+            value,
+            enabled: true,
+            description: description.to_string(),
+        };
+    }
+
+    // The current value of the byte, so that it stays that way
+    pub fn freeze(address: usize, memory: &[u8; MEMORY_SIZE], description: &str) -> Self {
+        return Self::new(address, memory[address], description);
+    }
+
+    // "2F0 03 lives", all in hex, the description being optional
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().splitn(3, char::is_whitespace);
+        let address = parse_hex(parts.next()?).filter(|address| *address < MEMORY_SIZE)?;
+        let value = u8::try_from(parse_hex(parts.next()?)?).ok()?;
+        return Some(Self::new(address, value, parts.next().unwrap_or("").trim()));
+    }
+
+    pub fn apply(&self, memory: &mut [u8; MEMORY_SIZE]) {
+        if self.enabled && self.address < MEMORY_SIZE {
+            memory[self.address] = self.value;
+        }
+    }
+}
+
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03X} {:02X}", self.address, self.value)?;
+        if !self.description.is_empty() {
+            write!(f, " {}", self.description)?;
+        }
+        return Ok(());
+    }
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    let text = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    return usize::from_str_radix(text, 16).ok();
+}
+
+// The cheats kept for one ROM, stored one per line as "2F0 03 lives" with
+// disabled cheats commented out with a '#'.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cheats {
+    pub cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn path(sha1: &str) -> Option<PathBuf> {
+        let base = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+        };
+        return Some(base.join("chip8_emulator").join("cheats").join(sha1));
+    }
+
+    // a ROM nobody has saved cheats for simply has none
+    pub fn load(sha1: &str) -> Self {
+        return Self::path(sha1)
+            .and_then(|path| Self::load_from(&path).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        return Ok(Self::parse(&fs::read_to_string(path)?));
+    }
+
+    pub fn save(&self, sha1: &str) -> io::Result<()> {
+        let path = Self::path(sha1)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        return fs::write(path, self.to_string());
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut cheats = Vec::new();
+        for line in text.lines() {
+            let (enabled, line) = match line.trim().strip_prefix('#') {
+                Some(line) => (false, line),
+                None => (true, line),
+            };
+            if let Some(mut cheat) = Cheat::parse(line) {
+                cheat.enabled = enabled;
+                cheats.push(cheat);
+            }
+        }
+        return Self { cheats };
+    }
+
+    pub fn apply(&self, memory: &mut [u8; MEMORY_SIZE]) {
+        for cheat in self.cheats.iter() {
+            cheat.apply(memory);
+        }
+    }
+}
+
+impl fmt::Display for Cheats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for cheat in self.cheats.iter() {
+            if !cheat.enabled {
+                write!(f, "#")?;
+            }
+            writeln!(f, "{}", cheat)?;
+        }
+        return Ok(());
+    }
+}
+
+// Narrows all of memory down to the few addresses that behave like, say, a
+// lives counter, by comparing each snapshot with the one before.
+#[derive(Clone, Debug)]
+pub struct Search {
+    candidates: Vec<usize>,
+    previous: [u8; MEMORY_SIZE],
+}
+
+impl Search {
+    pub fn new(memory: &[u8; MEMORY_SIZE]) -> Self {
+        return Self {
+            candidates: (0..MEMORY_SIZE).collect(),
+            previous: *memory,
+        };
+    }
+
+    // Keeps the candidates that pass, then makes this the snapshot the next
+    // filter compares against.
+    pub fn filter(&mut self, memory: &[u8; MEMORY_SIZE], comparison: Comparison, value: u8) {
+        let previous = &self.previous;
+        self.candidates
+            .retain(|address| comparison.holds(previous[*address], memory[*address], value));
+        self.previous = *memory;
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        return &self.candidates;
+    }
+}
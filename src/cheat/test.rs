@@ -0,0 +1,51 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_cheat_parse() {
+    assert_eq!(
+        Cheat::parse("2f0 03 infinite lives"),
+        Some(Cheat::new(0x2F0, 3, "infinite lives"))
+    );
+    assert_eq!(Cheat::parse("0x300 FF"), Some(Cheat::new(0x300, 0xFF, "")));
+    assert_eq!(Cheat::parse("2F0"), None);
+    assert_eq!(Cheat::parse("1000 01"), None);
+    assert_eq!(Cheat::parse("2F0 100"), None);
+}
+
+#[test]
+fn test_cheats_round_trip() {
+    let mut cheats = Cheats {
+        cheats: vec![Cheat::new(0x2F0, 3, "lives"), Cheat::new(0x2F1, 0x99, "")],
+    };
+    cheats.cheats[1].enabled = false;
+    let text = cheats.to_string();
+    assert_eq!(text, "2F0 03 lives\n#2F1 99\n");
+    assert_eq!(Cheats::parse(&text), cheats);
+
+    let mut memory = [0; 0x1000];
+    cheats.apply(&mut memory);
+    assert_eq!(memory[0x2F0], 3);
+    assert_eq!(memory[0x2F1], 0);
+}
+
+#[test]
+fn test_search() {
+    let mut memory = [0; 0x1000];
+    memory[0x300] = 3;
+    memory[0x301] = 3;
+    let mut search = Search::new(&memory);
+    search.filter(&memory, Comparison::Equal, 3);
+    assert_eq!(search.candidates(), &[0x300, 0x301]);
+
+    memory[0x300] = 2;
+    search.filter(&memory, Comparison::Decreased, 0);
+    assert_eq!(search.candidates(), &[0x300]);
+    search.filter(&memory, Comparison::Unchanged, 0);
+    assert_eq!(search.candidates(), &[0x300]);
+
+    let cheat = Cheat::freeze(0x300, &memory, "lives");
+    memory[0x300] = 1;
+    cheat.apply(&mut memory);
+    assert_eq!(memory[0x300], 2);
+}
@@ -0,0 +1,228 @@
+use crate::{Application, Message};
+use chip8_emulator::{
+    cheat::{Cheat, Cheats, Comparison, Search},
+    emulator::Snapshot,
+};
+use relm4::{
+    gtk::{
+        prelude::ComboBoxExtManual,
+        traits::{
+            BoxExt, ButtonExt, ComboBoxExt, EditableExt, EntryExt, GtkWindowExt, TextBufferExt,
+            TextViewExt, WidgetExt,
+        },
+        Box, Button, ComboBoxText, Entry, Label, Orientation, PolicyType, ScrolledWindow,
+        TextBuffer, TextView, Window,
+    },
+    ComponentSender, RelmWidgetExt,
+};
+
+// listing thousands of candidates is no help to anyone
+const MAX_LISTED: usize = 100;
+// while running, only redraw every few frames to keep the UI responsive
+const REFRESH_INTERVAL: u32 = 6;
+const COMPARISONS: [(&str, &str); 8] = [
+    ("Equal to", "=="),
+    ("Not equal to", "!="),
+    ("Greater than", ">"),
+    ("Less than", "<"),
+    ("Increased", "increased"),
+    ("Decreased", "decreased"),
+    ("Changed", "changed"),
+    ("Unchanged", "unchanged"),
+];
+
+pub struct CheatEditor {
+    window: Window,
+    summary: Label,
+    candidates: TextBuffer,
+    cheats: TextBuffer,
+    search: Option<Search>,
+    last: Option<Snapshot>,
+    frames: u32,
+}
+
+impl CheatEditor {
+    pub fn new(root: &Window, sender: ComponentSender<Application>) -> Self {
+        let window = Window::builder()
+            .title("Cheats")
+            .transient_for(root)
+            .destroy_with_parent(true)
+            .hide_on_close(true)
+            .default_width(420)
+            .default_height(560)
+            .build();
+
+        // searching: pick a comparison, then filter after each change in game
+        let comparison = ComboBoxText::new();
+        for (label, id) in COMPARISONS {
+            comparison.append(Some(id), label);
+        }
+        comparison.set_active(Some(0));
+        let value = Entry::builder()
+            .placeholder_text("value (hex)")
+            .width_chars(10)
+            .build();
+        let start = Button::with_label("New Search");
+        let filter = Button::with_label("Filter");
+
+        let search_sender = sender.clone();
+        start.connect_clicked(move |_| search_sender.input(Message::CheatSearch(None)));
+        let search_sender = sender.clone();
+        let search_value = value.clone();
+        filter.connect_clicked(move |_| {
+            let comparison = comparison
+                .active_id()
+                .and_then(|id| Comparison::parse(id.as_str()));
+            let Some(comparison) = comparison else {
+                return;
+            };
+            let text = search_value.text();
+            let value = match comparison.is_relative() {
+                true => Some(0),
+                false => u8::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok(),
+            };
+            match value {
+                Some(value) => search_sender.input(Message::CheatSearch(Some((comparison, value)))),
+                None => search_value.error_bell(),
+            }
+        });
+
+        let controls = Box::new(Orientation::Horizontal, 5);
+        controls.append(&start);
+        controls.append(&value);
+        controls.append(&filter);
+
+        let summary = Label::new(Some("No search running"));
+        let candidates = TextBuffer::new(None);
+        let candidate_view = TextView::with_buffer(&candidates);
+        candidate_view.set_monospace(true);
+        candidate_view.set_editable(false);
+        candidate_view.set_cursor_visible(false);
+        let candidate_list = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vexpand(true)
+            .child(&candidate_view)
+            .build();
+
+        // the cheat list is edited as text in the same format it's saved in
+        let freeze = Entry::builder()
+            .placeholder_text("address to freeze (hex)")
+            .build();
+        let freeze_sender = sender.clone();
+        freeze.connect_activate(move |entry| {
+            match usize::from_str_radix(entry.text().trim().trim_start_matches("0x"), 16) {
+                Ok(address) if address < 0x1000 => {
+                    freeze_sender.input(Message::FreezeCheat(address));
+                    entry.set_text("");
+                }
+                _ => entry.error_bell(),
+            }
+        });
+
+        let cheats = TextBuffer::new(None);
+        let cheat_view = TextView::with_buffer(&cheats);
+        cheat_view.set_monospace(true);
+        let cheat_list = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vexpand(true)
+            .child(&cheat_view)
+            .build();
+        let apply = Button::with_label("Apply and Save");
+        let cheat_text = cheats.clone();
+        apply.connect_clicked(move |_| {
+            let (start, end) = cheat_text.bounds();
+            let cheats = Cheats::parse(&cheat_text.text(&start, &end, false));
+            sender.input(Message::SetCheats(cheats));
+        });
+
+        let layout = Box::new(Orientation::Vertical, 5);
+        layout.set_margin_all(10);
+        layout.append(&controls);
+        layout.append(&summary);
+        layout.append(&candidate_list);
+        layout.append(&Label::new(Some(
+            "Cheats, one \"address value description\" per line, '#' to disable",
+        )));
+        layout.append(&cheat_list);
+        layout.append(&freeze);
+        layout.append(&apply);
+        window.set_child(Some(&layout));
+
+        return Self {
+            window,
+            summary,
+            candidates,
+            cheats,
+            search: None,
+            last: None,
+            frames: 0,
+        };
+    }
+
+    pub fn show(&mut self) {
+        self.window.present();
+        self.refresh();
+    }
+
+    pub fn update(&mut self, snapshot: Snapshot) {
+        let paused = snapshot.paused;
+        self.last = Some(snapshot);
+        self.frames = self.frames.wrapping_add(1);
+        if self.window.is_visible() && (paused || self.frames % REFRESH_INTERVAL == 0) {
+            self.refresh();
+        }
+    }
+
+    // None starts a new search over all of memory
+    pub fn search(&mut self, filter: Option<(Comparison, u8)>) {
+        let Some(snapshot) = &self.last else {
+            return;
+        };
+        match (filter, self.search.as_mut()) {
+            (Some((comparison, value)), Some(search)) => {
+                search.filter(&snapshot.memory, comparison, value);
+            }
+            _ => self.search = Some(Search::new(&snapshot.memory)),
+        }
+        self.refresh();
+    }
+
+    pub fn freeze(&mut self, address: usize) {
+        if let Some(snapshot) = &self.last {
+            let cheat = Cheat::freeze(address, &snapshot.memory, "");
+            let mut end = self.cheats.end_iter();
+            if end.line_offset() > 0 {
+                self.cheats.insert(&mut end, "\n");
+            }
+            self.cheats.insert(&mut end, &format!("{}\n", cheat));
+        }
+    }
+
+    // what's shown for a newly loaded ROM
+    pub fn set_cheats(&mut self, cheats: &Cheats) {
+        self.cheats.set_text(&cheats.to_string());
+        self.search = None;
+        self.refresh();
+    }
+
+    fn refresh(&self) {
+        let Some(search) = &self.search else {
+            self.summary.set_text("No search running");
+            self.candidates.set_text("");
+            return;
+        };
+        let candidates = search.candidates();
+        self.summary
+            .set_text(&format!("{} candidates", candidates.len()));
+        let mut text = String::new();
+        if let Some(snapshot) = &self.last {
+            for address in candidates.iter().take(MAX_LISTED) {
+                text.push_str(&format!(
+                    "{:03X}: {:02X}\n",
+                    address, snapshot.memory[*address]
+                ));
+            }
+        }
+        self.candidates.set_text(&text);
+    }
+}
@@ -0,0 +1,313 @@
+mod test;
+
+use crate::{
+    database::Database,
+    emulator::{Font, Platform, Settings, MAX_STACK_DEPTH},
+    filter::FilterMode,
+    keymap::Keymap,
+    palette::Palette,
+    rom::Rom,
+};
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+const GLOBAL: &str = "";
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    sections: Vec<Section>,
+}
+
+// Everything a frontend needs to run a particular ROM
+#[derive(Clone, Debug)]
+pub struct Setup {
+    pub title: String,
+    pub settings: Settings,
+    pub keymap: Keymap,
+    pub palette: Palette,
+    pub filter: FilterMode,
+}
+
+#[derive(Clone, Debug)]
+struct Section {
+    name: String,
+    entries: Vec<(String, String)>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        return Some(base.join("chip8_emulator").join("config"));
+    }
+
+    pub fn load() -> Self {
+        return match Self::path() {
+            Some(path) => Self::load_from(&path).unwrap_or_default(),
+            None => Self::default(),
+        };
+    }
+
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        return Ok(Self::parse(&fs::read_to_string(path)?));
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        return fs::write(path, self.to_string());
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut config = Self::default();
+        let mut section = String::from(GLOBAL);
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = String::from(line[1..line.len() - 1].trim());
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                config.set(&section, key.trim(), value.trim());
+            }
+        }
+        return config;
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        return self
+            .sections
+            .iter()
+            .find(|s| s.name == section)?
+            .entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str());
+    }
+
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        let index = match self.sections.iter().position(|s| s.name == section) {
+            Some(index) => index,
+            None => {
+                self.sections.push(Section {
+                    name: String::from(section),
+                    entries: Vec::new(),
+                });
+                self.sections.len() - 1
+            }
+        };
+        let entries = &mut self.sections[index].entries;
+        match entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = String::from(value),
+            None => entries.push((String::from(key), String::from(value))),
+        }
+    }
+
+    pub fn palettes(&self) -> Vec<Palette> {
+        let mut palettes = Palette::builtin();
+        if let Some(global) = self.sections.iter().find(|s| s.name == GLOBAL) {
+            for (key, value) in global.entries.iter() {
+                if let Some(name) = key.strip_prefix("palette.") {
+                    match Palette::parse(name, value) {
+                        Some(palette) => palettes.push(palette),
+                        None => eprintln!("ignoring invalid palette '{}'", name),
+                    }
+                }
+            }
+        }
+        return palettes;
+    }
+
+    pub fn palette(&self) -> Palette {
+        let name = self.get(GLOBAL, "palette").unwrap_or("classic");
+        return self
+            .palettes()
+            .into_iter()
+            .rev()
+            .find(|palette| palette.name == name)
+            .unwrap_or_else(Palette::classic);
+    }
+
+    pub fn rom_palette(&self, rom: &str) -> Option<Palette> {
+        let name = self.get(rom, "palette")?;
+        return self
+            .palettes()
+            .into_iter()
+            .rev()
+            .find(|palette| palette.name == name);
+    }
+
+    pub fn set_palette(&mut self, palette: &Palette) {
+        if !Palette::builtin().contains(palette) {
+            self.set(
+                GLOBAL,
+                &format!("palette.{}", palette.name),
+                &palette.to_spec(),
+            );
+        }
+        self.set(GLOBAL, "palette", &palette.name);
+    }
+
+    // Settings in a ROM's own section take precedence over the global ones.
+    fn get_for_rom(&self, rom: Option<&str>, key: &str) -> Option<&str> {
+        return rom
+            .and_then(|rom| self.get(rom, key))
+            .or_else(|| self.get(GLOBAL, key));
+    }
+
+    pub fn filter(&self, rom: Option<&str>) -> FilterMode {
+        return match self.get_for_rom(rom, "filter") {
+            Some(mode) => FilterMode::parse(mode).unwrap_or_else(|| {
+                eprintln!("ignoring invalid filter '{}'", mode);
+                FilterMode::Off
+            }),
+            None => FilterMode::Off,
+        };
+    }
+
+    pub fn database(&self) -> Option<PathBuf> {
+        return match self.get(GLOBAL, "database") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => Database::default_path(),
+        };
+    }
+
+    // Only looks at the given section, so callers can layer the global
+    // settings, a database match and the ROM's own section in that order.
+    pub fn apply_settings(&self, rom: Option<&str>, settings: &mut Settings) {
+        let section = rom.unwrap_or(GLOBAL);
+        if let Some(id) = self.get(section, "platform") {
+            match Platform::from_id(id) {
+                Some(platform) => *settings = Settings::for_platform(platform),
+                None => eprintln!("ignoring unknown platform '{}'", id),
+            }
+        }
+        if let Some(tickrate) = self.get(section, "tickrate") {
+            match tickrate.parse::<u32>() {
+                Ok(tickrate) => settings.tickrate = tickrate,
+                Err(_) => eprintln!("ignoring invalid tickrate '{}'", tickrate),
+            }
+        }
+        if let Some(depth) = self.get(section, "stack_depth") {
+            match depth.parse::<usize>() {
+                Ok(depth) if (1..=MAX_STACK_DEPTH).contains(&depth) => settings.stack_depth = depth,
+                _ => eprintln!("ignoring invalid stack_depth '{}'", depth),
+            }
+        }
+        if let Some(enabled) = self.get(section, "memory_stack") {
+            match enabled.parse::<bool>() {
+                Ok(enabled) => settings.memory_stack = enabled,
+                Err(_) => eprintln!("ignoring invalid memory_stack '{}'", enabled),
+            }
+        }
+        // addresses are in hex, as in "program_start = 0x600"
+        if let Some(address) = self.get(section, "program_start") {
+            match parse_address(address) {
+                Some(address) => settings.program_start = address,
+                None => eprintln!("ignoring invalid program_start '{}'", address),
+            }
+        }
+        if let Some(address) = self.get(section, "font_start") {
+            match parse_address(address) {
+                Some(address) => settings.font_start = address,
+                None => eprintln!("ignoring invalid font_start '{}'", address),
+            }
+        }
+        if let Some(font) = self.get(section, "font") {
+            match Font::parse(font) {
+                Ok(font) => settings.font = font,
+                Err(err) => eprintln!("ignoring font '{}': {}", font, err),
+            }
+        }
+        if settings.font_start + settings.font.size() > 0x1000 {
+            eprintln!("font does not fit at {:03X}", settings.font_start);
+            settings.font_start = Settings::default().font_start;
+        }
+    }
+
+    // Layers the defaults, the global settings, a database match, the options
+    // of an Octo cartridge and the ROM's own section, in that order.
+    pub fn setup(&self, rom: &Rom, database: Option<&Database>) -> Setup {
+        let entry = database.and_then(|database| database.lookup(&rom.sha1));
+
+        let mut settings = Settings::default();
+        self.apply_settings(None, &mut settings);
+        if let Some(entry) = entry {
+            entry.apply(&mut settings);
+        }
+        if let Some(options) = &rom.cartridge {
+            options.apply(&mut settings);
+        }
+        self.apply_settings(Some(&rom.name), &mut settings);
+
+        let mut keymap = self.keymap(Some(&rom.name));
+        if let Some(entry) = entry {
+            entry.bind_keys(&mut keymap);
+        }
+
+        let palette = self
+            .rom_palette(&rom.name)
+            .or_else(|| {
+                let options = rom.cartridge.as_ref()?;
+                return options.palette(&rom.name);
+            })
+            .or_else(|| entry.and_then(|entry| entry.palette()))
+            .unwrap_or_else(|| self.palette());
+        let title = match entry {
+            Some(entry) => entry.describe(),
+            None => rom.name.clone(),
+        };
+
+        return Setup {
+            title,
+            settings,
+            keymap,
+            palette,
+            filter: self.filter(Some(&rom.name)),
+        };
+    }
+
+    pub fn keymap(&self, rom: Option<&str>) -> Keymap {
+        return match self.get_for_rom(rom, "keymap") {
+            Some(spec) => Keymap::parse(spec).unwrap_or_else(|| {
+                eprintln!("ignoring invalid keymap '{}'", spec);
+                Keymap::cosmac()
+            }),
+            None => Keymap::cosmac(),
+        };
+    }
+}
+
+fn parse_address(text: &str) -> Option<usize> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    return usize::from_str_radix(digits, 16)
+        .ok()
+        .filter(|address| *address < 0x1000);
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the global section has no header, so it has to come first
+        let global = self.sections.iter().filter(|s| s.name == GLOBAL);
+        let named = self.sections.iter().filter(|s| s.name != GLOBAL);
+        for section in global.chain(named) {
+            if section.name != GLOBAL {
+                writeln!(f, "\n[{}]", section.name)?;
+            }
+            for (key, value) in section.entries.iter() {
+                writeln!(f, "{} = {}", key, value)?;
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_config_round_trip() {
+    let mut config = Config::parse("[pong.ch8]\nspeed = 10\n");
+    config.set("", "palette", "amber");
+    config.set("pong.ch8", "speed", "12");
+
+    let config = Config::parse(&config.to_string());
+    assert_eq!(config.get("", "palette"), Some("amber"));
+    assert_eq!(config.get("pong.ch8", "speed"), Some("12"));
+    assert_eq!(config.get("pong.ch8", "palette"), None);
+}
+
+#[test]
+fn test_config_palette() {
+    let mut config = Config::parse("# comment\npalette = green\n");
+    assert_eq!(config.palette(), Palette::green_phosphor());
+
+    let custom = Palette::parse("mine", "#000000 #FF0000; #202020").unwrap();
+    config.set_palette(&custom);
+    let config = Config::parse(&config.to_string());
+    assert_eq!(config.palette(), custom);
+    assert_eq!(config.palettes().len(), Palette::builtin().len() + 1);
+}
+
+#[test]
+fn test_config_rom_override() {
+    let config = Config::parse("filter = or-erase\n\n[blinky.ch8]\nfilter = decay:4\n");
+    assert_eq!(config.filter(None), FilterMode::OrErase);
+    assert_eq!(config.filter(Some("pong.ch8")), FilterMode::OrErase);
+    assert_eq!(config.filter(Some("blinky.ch8")), FilterMode::Decay(4));
+}
+
+#[test]
+fn test_config_setup() {
+    let config = Config::parse(
+        "platform = superchip\n\n[pong.ch8]\ntickrate = 8\nstack_depth = 24\nmemory_stack = true\n\
+         program_start = 0x600\nfont_start = 50\nfont = vip\n",
+    );
+    let rom = Rom {
+        name: String::from("pong.ch8"),
+        sha1: String::from("0000"),
+        program: vec![0x12, 0x00],
+        cartridge: None,
+        symbols: Default::default(),
+    };
+    let setup = config.setup(&rom, None);
+    assert_eq!(setup.title, "pong.ch8");
+    assert_eq!(setup.settings.quirks, Platform::SuperChip.quirks());
+    assert_eq!(setup.settings.tickrate, 8);
+    assert_eq!(setup.settings.stack_depth, 24);
+    assert!(setup.settings.memory_stack);
+    assert_eq!(setup.settings.program_start, 0x600);
+    assert_eq!(setup.settings.font_start, 0x050);
+    assert_eq!(setup.settings.font, Font::builtin("vip").unwrap());
+    assert_eq!(setup.keymap, Keymap::cosmac());
+}
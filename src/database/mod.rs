@@ -0,0 +1,199 @@
+mod test;
+
+use crate::{
+    emulator::{Platform, Quirks, Settings},
+    keymap::Keymap,
+    palette::{Color, Palette},
+};
+use serde_json::Value;
+use std::{collections::HashMap, env, fs, io, path::Path, path::PathBuf};
+
+// the copy of programs.json built into every binary, so a fresh install
+// recognises ROMs without downloading anything. data/programs.json is the
+// chip-8-database's database/programs.json, vendored along with its licence;
+// while it's an empty list only a downloaded copy recognises anything.
+const BUNDLED: &str = include_str!("../../data/programs.json");
+
+// An offline copy of the community chip-8-database (programs.json), keyed by
+// the SHA-1 of each ROM image.
+pub struct Database {
+    entries: HashMap<String, Entry>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub platform: Option<Platform>,
+    pub quirks: Option<Quirks>,
+    pub tickrate: Option<u32>,
+    pub keys: Vec<(String, u8)>,
+    pub colors: Vec<Color>,
+}
+
+impl Database {
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+        };
+        return Some(base.join("chip8_emulator").join("programs.json"));
+    }
+
+    pub fn bundled() -> Self {
+        return Self::parse(BUNDLED).expect("the bundled database is valid");
+    }
+
+    // A programs.json at the path, typically a newer download, overrides the
+    // bundled copy
+    pub fn open(path: Option<&Path>) -> io::Result<Self> {
+        return match path {
+            Some(path) if path.exists() => Self::load(path),
+            _ => Ok(Self::bundled()),
+        };
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        return Self::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+    }
+
+    pub fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        let programs: Vec<Value> = serde_json::from_str(text)?;
+        let mut entries = HashMap::new();
+        for program in programs.iter() {
+            let title = program["title"].as_str().unwrap_or("Unknown").to_string();
+            let authors: Vec<String> = program["authors"]
+                .as_array()
+                .map(|authors| {
+                    authors
+                        .iter()
+                        .filter_map(|a| a.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(roms) = program["roms"].as_object() {
+                for (hash, rom) in roms.iter() {
+                    let entry = Entry::parse(title.clone(), authors.clone(), rom);
+                    entries.insert(hash.to_lowercase(), entry);
+                }
+            }
+        }
+        return Ok(Self { entries });
+    }
+
+    pub fn lookup(&self, sha1: &str) -> Option<&Entry> {
+        return self.entries.get(&sha1.to_lowercase());
+    }
+}
+
+impl Entry {
+    fn parse(title: String, authors: Vec<String>, rom: &Value) -> Self {
+        let platform = rom["platforms"]
+            .as_array()
+            .and_then(|platforms| platforms.iter().filter_map(|p| p.as_str()).next())
+            .and_then(Platform::from_id);
+        let quirks = match (platform, rom["quirkyPlatforms"].as_object()) {
+            (Some(platform), Some(quirky)) => quirky
+                .get(platform.id())
+                .map(|overrides| Self::parse_quirks(platform.quirks(), overrides)),
+            _ => None,
+        };
+        let keys = rom["keys"]
+            .as_object()
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|(name, key)| Some((name.clone(), key.as_u64()? as u8)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let colors = rom["colors"]["pixels"]
+            .as_array()
+            .map(|pixels| {
+                pixels
+                    .iter()
+                    .filter_map(|c| c.as_str().and_then(Color::parse))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        return Self {
+            title,
+            authors,
+            platform,
+            quirks,
+            tickrate: rom["tickrate"].as_u64().map(|t| t as u32),
+            keys,
+            colors,
+        };
+    }
+
+    fn parse_quirks(defaults: Quirks, overrides: &Value) -> Quirks {
+        let flag = |name: &str, default: bool| overrides[name].as_bool().unwrap_or(default);
+        return Quirks {
+            shift: flag("shift", defaults.shift),
+            memory_increment_by_x: flag("memoryIncrementByX", defaults.memory_increment_by_x),
+            memory_leave_i_unchanged: flag(
+                "memoryLeaveIUnchanged",
+                defaults.memory_leave_i_unchanged,
+            ),
+            wrap: flag("wrap", defaults.wrap),
+            jump: flag("jump", defaults.jump),
+            vblank: flag("vblank", defaults.vblank),
+            logic: flag("logic", defaults.logic),
+        };
+    }
+
+    pub fn describe(&self) -> String {
+        if self.authors.is_empty() {
+            return self.title.clone();
+        }
+        return format!("{} by {}", self.title, self.authors.join(", "));
+    }
+
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(platform) = self.platform {
+            *settings = Settings::for_platform(platform);
+        }
+        if let Some(quirks) = self.quirks {
+            settings.quirks = quirks;
+        }
+        if let Some(tickrate) = self.tickrate {
+            settings.tickrate = tickrate;
+        }
+    }
+
+    // The database names keys by their role in the game, so bind those roles
+    // to the arrow keys on top of the regular keypad layout.
+    pub fn bind_keys(&self, keymap: &mut Keymap) {
+        for (name, key) in self.keys.iter() {
+            let keycode = match name.as_str() {
+                "up" => Keymap::keycode("up"),
+                "down" => Keymap::keycode("down"),
+                "left" => Keymap::keycode("left"),
+                "right" => Keymap::keycode("right"),
+                "a" => Keymap::keycode("space"),
+                "b" => Keymap::keycode("enter"),
+                _ => None,
+            };
+            if let Some(keycode) = keycode {
+                keymap.bind(keycode, *key);
+            }
+        }
+    }
+
+    pub fn palette(&self) -> Option<Palette> {
+        if self.colors.len() < 2 {
+            return None;
+        }
+        let mut colors = [self.colors[1]; 4];
+        for (i, color) in self.colors.iter().take(4).enumerate() {
+            colors[i] = *color;
+        }
+        return Some(Palette {
+            name: self.title.clone(),
+            colors,
+            bezel: Palette::classic().bezel,
+        });
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+use super::*;
+
+#[cfg(test)]
+const PROGRAMS: &str = r##"[
+    {
+        "title": "Paddles",
+        "authors": ["Someone", "Someone Else"],
+        "roms": {
+            "AABBCCDDEEFF00112233445566778899AABBCCDD": {
+                "platforms": ["superchip"],
+                "tickrate": 20,
+                "keys": { "up": 1, "down": 4, "a": 6 },
+                "colors": { "pixels": ["#000000", "#00ff00"] },
+                "quirkyPlatforms": { "superchip": { "wrap": true } }
+            }
+        }
+    },
+    { "title": "No roms" }
+]"##;
+
+#[test]
+fn test_lookup() {
+    let database = Database::parse(PROGRAMS).unwrap();
+    let entry = database
+        .lookup("aabbccddeeff00112233445566778899aabbccdd")
+        .unwrap();
+    assert_eq!(entry.describe(), "Paddles by Someone, Someone Else");
+    assert_eq!(entry.platform, Some(Platform::SuperChip));
+    assert_eq!(entry.palette().unwrap().color(1), Color(0, 0xFF, 0));
+
+    let mut settings = Settings::default();
+    entry.apply(&mut settings);
+    assert_eq!(settings.tickrate, 20);
+    assert!(settings.quirks.shift);
+    assert!(settings.quirks.wrap);
+
+    let mut keymap = Keymap::cosmac();
+    entry.bind_keys(&mut keymap);
+    assert_eq!(keymap.key(Keymap::keycode("up").unwrap()), Some(1));
+    assert_eq!(keymap.key(Keymap::keycode("space").unwrap()), Some(6));
+
+    assert!(database.lookup("0000").is_none());
+}
+
+#[test]
+fn test_lookup_rom() {
+    // LD V0, K; LD I, 0x300; LD [I], V0; JP 0x200
+    let program = [0xF0, 0x0A, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+    // the database lists hashes in upper case, ROM hashes come out in lower
+    let sha1 = crate::rom::Rom::hash(&program);
+    let programs = format!(
+        r##"[{{"title": "Key", "roms": {{"{}": {{
+            "platforms": ["originalChip8"], "tickrate": 15,
+            "keys": {{"a": 5}}, "colors": {{"pixels": ["#101010", "#ffb000"]}}
+        }}}}}}]"##,
+        sha1.to_uppercase()
+    );
+    let path = std::env::temp_dir().join(format!("chip8-rom-{}.json", std::process::id()));
+    std::fs::write(&path, programs).unwrap();
+    let database = Database::open(Some(&path)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let entry = database.lookup(&sha1).unwrap();
+    assert_eq!(entry.platform, Some(Platform::OriginalChip8));
+    assert_eq!(entry.tickrate, Some(15));
+    assert_eq!(entry.keys, vec![(String::from("a"), 5)]);
+    assert_eq!(
+        entry.colors,
+        vec![Color(0x10, 0x10, 0x10), Color(0xFF, 0xB0, 0x00)]
+    );
+}
+
+#[test]
+fn test_open() {
+    // without a file of its own the bundled copy is used
+    let missing = std::env::temp_dir().join("chip8-no-such-programs.json");
+    assert!(Database::open(Some(&missing)).is_ok());
+    assert!(Database::open(None).is_ok());
+
+    let path = std::env::temp_dir().join(format!("chip8-programs-{}.json", std::process::id()));
+    std::fs::write(&path, PROGRAMS).unwrap();
+    let database = Database::open(Some(&path)).unwrap();
+    assert!(database
+        .lookup("aabbccddeeff00112233445566778899aabbccdd")
+        .is_some());
+    std::fs::write(&path, "not json").unwrap();
+    assert!(Database::open(Some(&path)).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,158 @@
+mod test;
+
+use super::{disassembler::disassemble_with, Registers, Symbols};
+use std::{fmt::Write, ops::Range};
+
+const MEMORY_SIZE: usize = 0x1000;
+// bytes per line of the coverage map
+const MAP_ROW: usize = 32;
+
+pub const EXECUTED: u8 = 1;
+pub const READ: u8 = 2;
+pub const WRITTEN: u8 = 4;
+
+// How each byte of memory has been used: fetched as an instruction, read as
+// data by a sprite draw or FX65, or written by FX33 and FX55.
+#[derive(Clone, Debug)]
+pub struct Coverage {
+    program: Range<usize>,
+    flags: Vec<u8>,
+}
+
+impl Coverage {
+    // program is the range the ROM was loaded into, which the reports cover
+    pub fn new(program: Range<usize>) -> Self {
+        return Self {
+            program,
+            flags: vec![0; MEMORY_SIZE],
+        };
+    }
+
+    // Called with the registers as they are just before opcode executes
+    pub fn record(&mut self, registers: &Registers, opcode: u16) {
+        let pc = registers.program_counter;
+        self.mark(pc, 2, EXECUTED);
+
+        let i = registers.i;
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        match opcode & 0xF0FF {
+            0xF033 => self.mark(i, 3, WRITTEN),
+            0xF055 => self.mark(i, x + 1, WRITTEN),
+            0xF065 => self.mark(i, x + 1, READ),
+            _ if opcode & 0xF000 == 0xD000 => self.mark(i, (opcode & 0xF) as usize, READ),
+            _ => {}
+        }
+    }
+
+    fn mark(&mut self, start: usize, len: usize, flag: u8) {
+        for address in start..start + len {
+            self.flags[address % MEMORY_SIZE] |= flag;
+        }
+    }
+
+    pub fn flags(&self, address: usize) -> u8 {
+        return self.flags.get(address).copied().unwrap_or(0);
+    }
+
+    // Bytes of the program that were executed, read, written, and never
+    // touched at all
+    pub fn summary(&self) -> (usize, usize, usize, usize) {
+        let flags = &self.flags[self.program.clone()];
+        let count = |flag: u8| flags.iter().filter(|f| *f & flag != 0).count();
+        let untouched = flags.iter().filter(|f| **f == 0).count();
+        return (count(EXECUTED), count(READ), count(WRITTEN), untouched);
+    }
+
+    fn marker(flags: u8) -> char {
+        return match flags {
+            0 => '.',
+            EXECUTED => 'x',
+            READ => 'r',
+            WRITTEN => 'w',
+            _ => '*',
+        };
+    }
+
+    // One character per program byte: 'x' code, 'r' data read, 'w' written,
+    // '*' more than one of those and '.' untouched.
+    pub fn map(&self) -> String {
+        let (executed, read, written, untouched) = self.summary();
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "; {} bytes: {} executed, {} read, {} written, {} untouched",
+            self.program.len(),
+            executed,
+            read,
+            written,
+            untouched
+        );
+        let mut address = self.program.start;
+        while address < self.program.end {
+            let end = (address + MAP_ROW).min(self.program.end);
+            let row: String = self.flags[address..end]
+                .iter()
+                .map(|flags| Self::marker(*flags))
+                .collect();
+            let _ = writeln!(text, "{:03X}: {}", address, row);
+            address = end;
+        }
+        return text;
+    }
+
+    // The program as code where it ran and as bytes where it was only used
+    // as data. Whatever was never touched is disassembled too, flagged so
+    // that unreached code paths stand out. Labels head the lines they name
+    // and instructions note the source line they came from.
+    pub fn annotate(&self, memory: &[u8; MEMORY_SIZE], symbols: &Symbols) -> String {
+        let mut text = String::new();
+        let mut address = self.program.start;
+        while address < self.program.end {
+            let flags = self.flags[address];
+            let next = self.flags.get(address + 1).copied().unwrap_or(0);
+            let whole = address + 1 < self.program.end;
+            let opcode = (memory[address] as u16) << 8 | memory[(address + 1) % MEMORY_SIZE] as u16;
+            let marker = format!(
+                "{}{}{}",
+                if flags & EXECUTED != 0 { 'x' } else { '-' },
+                if flags & READ != 0 { 'r' } else { '-' },
+                if flags & WRITTEN != 0 { 'w' } else { '-' },
+            );
+            if let Some(label) = symbols.label(address) {
+                let _ = writeln!(text, "{}:", label);
+            }
+            let mut notes = Vec::new();
+            if flags & EXECUTED == 0 {
+                notes.push(String::from("never reached"));
+            }
+            notes.extend(symbols.line(address));
+            if (flags & EXECUTED != 0 || (flags == 0 && next == 0)) && whole {
+                let instruction = disassemble_with(opcode, symbols);
+                let line = match notes.is_empty() {
+                    true => format!(
+                        "{} {:03X}: {:04X}  {}",
+                        marker, address, opcode, instruction
+                    ),
+                    false => format!(
+                        "{} {:03X}: {:04X}  {:<16} ; {}",
+                        marker,
+                        address,
+                        opcode,
+                        instruction,
+                        notes.join(", ")
+                    ),
+                };
+                let _ = writeln!(text, "{}", line);
+                address += 2;
+            } else {
+                let _ = writeln!(
+                    text,
+                    "{} {:03X}: {:02X}    DB {:02X}",
+                    marker, address, memory[address], memory[address]
+                );
+                address += 1;
+            }
+        }
+        return text;
+    }
+}
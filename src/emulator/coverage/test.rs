@@ -0,0 +1,51 @@
+#[cfg(test)]
+use super::*;
+#[cfg(test)]
+use crate::emulator::Emulator;
+
+// LD I, 0x20A; DRW V0, V0, 2; JP 0x208; JP 0x206; sprite; LD [I], V0
+#[cfg(test)]
+const PROGRAM: [u8; 14] = [
+    0xA2, 0x0A, 0xD0, 0x02, 0x12, 0x08, 0x12, 0x06, 0x12, 0x08, 0xF0, 0x90, 0xF0, 0x55,
+];
+
+#[test]
+fn test_coverage() {
+    let mut emulator = Emulator::new();
+    emulator.set_coverage(true);
+    emulator.load_program(&PROGRAM).unwrap();
+    emulator.run_frame();
+    let coverage = emulator.coverage().unwrap();
+
+    assert_eq!(coverage.flags(0x200), EXECUTED);
+    assert_eq!(coverage.flags(0x20A), READ);
+    assert_eq!(coverage.flags(0x20B), READ);
+    assert_eq!(coverage.flags(0x206), 0);
+    // LD I, 0x20A; DRW; JP 0x208 and the jump it lands on, the sprite, and
+    // the unreached jump plus the trailing LD [I], V0
+    assert_eq!(coverage.summary(), (8, 2, 0, 4));
+    assert_eq!(
+        coverage.map(),
+        "; 14 bytes: 8 executed, 2 read, 0 written, 4 untouched\n200: xxxxxx..xxrr..\n"
+    );
+
+    let listing = coverage.annotate(emulator.memory(), &Symbols::default());
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines[0], "x-- 200: A20A  LD I, 20A");
+    assert_eq!(lines[3], "--- 206: 1206  JP 206           ; never reached");
+    assert_eq!(lines[5], "-r- 20A: F0    DB F0");
+
+    let mut symbols = Symbols::parse("main 0x200\nsprite 0x20A").unwrap();
+    symbols.add_source_map("0x200 game.8o:1").unwrap();
+    let listing = coverage.annotate(emulator.memory(), &symbols);
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines[0], "main:");
+    assert_eq!(lines[1], "x-- 200: A20A  LD I, sprite     ; game.8o:1");
+    assert_eq!(lines[6], "sprite:");
+
+    // a hard reset adds to the same coverage, a new program starts over
+    emulator.reset(crate::emulator::Reset::Hard);
+    assert_eq!(emulator.coverage().unwrap().flags(0x200), EXECUTED);
+    emulator.load_program(&[0x12, 0x00]).unwrap();
+    assert_eq!(emulator.coverage().unwrap().flags(0x200), 0);
+}
@@ -1,11 +1,31 @@
 mod test;
 
-use crate::Message;
-use std::{
-    sync::mpsc::Receiver,
-    thread,
-    time::{Duration, SystemTime},
+use super::{
+    keypad::Keypad,
+    quirks::{Platform, Quirks},
 };
+use std::time::SystemTime;
+
+const MEMORY_SIZE: usize = 0x1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Continue,
+    // the instruction has to wait for the next frame to start
+    Vblank,
+    Halt,
+}
+
+// A copy of the CPU state for the debugger, the call stack itself lives in RAM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Registers {
+    pub v: [u8; 16],
+    pub program_counter: usize,
+    pub i: usize,
+    pub stack_pointer: usize,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct CPU {
@@ -15,6 +35,11 @@ pub struct CPU {
     stack_pointer: usize,
     delay_timer: u8,
     sound_timer: u8,
+    quirks: Quirks,
+    // where FX29 finds the digits
+    font_start: usize,
+    // the generator behind CXKK, so a seed replays the same numbers
+    random: u32,
 }
 
 impl CPU {
@@ -26,176 +51,245 @@ impl CPU {
             stack_pointer: 0,
             delay_timer: 0,
             sound_timer: 0,
+            quirks: Platform::ModernChip8.quirks(),
+            font_start: 0,
+            // unless seeded, every run is different
+            random: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |time| time.as_micros() as u32),
         };
     }
-    pub fn run(
-        &mut self,
-        memory: &mut [u8; 4096],
-        stack: &mut [u16; 16],
-        display: &mut [[u8; 128]; 64],
-        keyboard_receiver: &Receiver<u16>,
-        sender: &relm4::Sender<Message>,
-    ) {
-        loop {
-            let op_byte1 = memory[self.program_counter] as u16;
-            let op_byte2 = memory[self.program_counter + 1] as u16;
-            let opcode = op_byte1 << 8 | op_byte2;
 
-            self.program_counter += 2;
+    pub fn registers(&self) -> Registers {
+        return Registers {
+            v: self.registers,
+            program_counter: self.program_counter,
+            i: self.i,
+            stack_pointer: self.stack_pointer,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+        };
+    }
+
+    pub fn set_registers(&mut self, registers: Registers) {
+        self.registers = registers.v;
+        self.program_counter = registers.program_counter;
+        self.i = registers.i;
+        self.stack_pointer = registers.stack_pointer;
+        self.delay_timer = registers.delay_timer;
+        self.sound_timer = registers.sound_timer;
+    }
 
-            let x = ((opcode & 0x0F00) >> 8) as u8;
-            let y = ((opcode & 0x00F0) >> 4) as u8;
-            let n = (opcode & 0x000F) as u8;
-            let kk = (opcode & 0x00FF) as u8;
-            let nnn = opcode & 0x0FFF;
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn set_font_start(&mut self, font_start: usize) {
+        self.font_start = font_start;
+    }
+
+    pub fn random_state(&self) -> u32 {
+        return self.random;
+    }
+
+    pub fn set_random_state(&mut self, random: u32) {
+        self.random = random;
+    }
 
-            match opcode {
-                0x0000 => {
-                    sender.emit(Message::ShutDown);
-                    return;
+    pub fn is_beeping(&self) -> bool {
+        return self.sound_timer > 0;
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    pub fn step(
+        &mut self,
+        memory: &mut [u8; 4096],
+        stack: &mut [u16],
+        display: &mut [[u8; 128]; 64],
+        keypad: &mut Keypad,
+    ) -> Step {
+        // the program counter wraps around the top of memory, as does every
+        // other address
+        self.program_counter %= MEMORY_SIZE;
+        let op_byte1 = memory[self.program_counter] as u16;
+        let op_byte2 = memory[(self.program_counter + 1) % MEMORY_SIZE] as u16;
+        let opcode = op_byte1 << 8 | op_byte2;
+
+        self.program_counter = (self.program_counter + 2) % MEMORY_SIZE;
+
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        let n = (opcode & 0x000F) as u8;
+        let kk = (opcode & 0x00FF) as u8;
+        let nnn = opcode & 0x0FFF;
+
+        match opcode {
+            0x0000 => {
+                return Step::Halt;
+            }
+            0x00E0 => {
+                Self::clr(display);
+            }
+            // the guards below carry out the instruction, and when it succeeds
+            // the opcode falls through to an arm that does nothing
+            0x00EE if !self.ret(stack) => {
+                return Step::Halt;
+            }
+            0x1000..=0x1FFF => {
+                self.jmp(nnn);
+            }
+            0x2000..=0x2FFF if !self.call(nnn, stack) => {
+                return Step::Halt;
+            }
+            0x3000..=0x3FFF => {
+                self.se(x, kk);
+            }
+            0x4000..=0x4FFF => {
+                self.sne(x, kk);
+            }
+            0x5000..=0x5FFF => {
+                self.se(x, self.registers[y as usize]);
+            }
+            0x6000..=0x6FFF => {
+                self.ld(x, kk);
+            }
+            0x7000..=0x7FFF => {
+                self.add(x, kk);
+            }
+            0x8000..=0x8FFF => match opcode & 0x000F {
+                0x0 => {
+                    self.ld(x, self.registers[y as usize]);
                 }
-                0x00E0 => {
-                    Self::clr(display, sender);
+                0x1 => {
+                    self.or_xy(x, y);
+                    self.reset_vf();
                 }
-                0x00EE => {
-                    self.ret(stack);
+                0x2 => {
+                    self.and_xy(x, y);
+                    self.reset_vf();
                 }
-                0x1000..=0x1FFF => {
-                    self.jmp(nnn);
+                0x3 => {
+                    self.xor_xy(x, y);
+                    self.reset_vf();
                 }
-                0x2000..=0x2FFF => {
-                    self.call(nnn, stack);
+                0x4 => {
+                    self.add_xy(x, y);
                 }
-                0x3000..=0x3FFF => {
-                    self.se(x, kk);
+                0x5 => {
+                    self.sub_xy(x, y);
                 }
-                0x4000..=0x4FFF => {
-                    self.sne(x, kk);
+                0x6 => {
+                    self.shr(x, y);
                 }
-                0x5000..=0x5FFF => {
-                    self.se(x, self.registers[y as usize]);
+                0x7 => {
+                    self.subb(x, y);
                 }
-                0x6000..=0x6FFF => {
-                    self.ld(x, kk);
+                0xE => {
+                    self.shl(x, y);
                 }
-                0x7000..=0x7FFF => {
-                    self.add(x, kk);
+                _ => {}
+            },
+            0x9000..=0x9FF0 => {
+                self.sne(x, self.registers[y as usize]);
+            }
+            0xA000..=0xAFFF => {
+                self.ld_i(nnn);
+            }
+            0xB000..=0xBFFF => {
+                self.jmp_0(x, nnn);
+            }
+            0xC000..=0xCFFF => {
+                self.rnd(x, kk);
+            }
+            0xD000..=0xDFFF => {
+                self.drw(n, x, y, memory, display);
+                if self.quirks.vblank {
+                    return Step::Vblank;
                 }
-                0x8000..=0x8FFF => match opcode & 0x000F {
-                    0x0 => {
-                        self.ld(x, self.registers[y as usize]);
-                    }
-                    0x1 => {
-                        self.or_xy(x, y);
-                    }
-                    0x2 => {
-                        self.and_xy(x, y);
-                    }
-                    0x3 => {
-                        self.xor_xy(x, y);
-                    }
-                    0x4 => {
-                        self.add_xy(x, y);
-                    }
-                    0x5 => {
-                        self.sub_xy(x, y);
-                    }
-                    0x6 => {
-                        self.shr(x);
-                    }
-                    0x7 => {
-                        self.subb(x, y);
+            }
+            0xE09E..=0xEFA1 => {
+                match kk {
+                    0x9E => {
+                        self.skp(x, keypad);
                     }
-                    0xE => {
-                        self.shl(x);
+                    0xA1 => {
+                        self.sknp(x, keypad);
                     }
-                    _ => {}
-                },
-                0x9000..=0x9FF0 => {
-                    self.sne(x, self.registers[y as usize]);
+                    _ => { /* invalid */ }
+                };
+            }
+            0xF007..=0xFF65 => match kk {
+                0x07 => {
+                    self.ld_x_dt(x);
                 }
-                0xA000..=0xAFFF => {
-                    self.ld_i(nnn);
+                0x0A if !self.ld_k_x(x, keypad) => {
+                    return Step::Vblank;
                 }
-                0xB000..=0xBFFF => {
-                    self.jmp_0(nnn);
+                0x15 => {
+                    self.ld_dt_x(x);
                 }
-                0xC000..=0xCFFF => {
-                    self.rnd(x, kk);
+                0x18 => {
+                    self.ld_st_x(x);
                 }
-                // 0xD000..=0xDFFF => self.drw(n, x, y, memory, display, sender),
-                0xD000..=0xDFFF => self.drw(n, x, y, memory, display, sender),
-                0xE09E..=0xEFA1 => {
-                    match kk {
-                        0x9E => { /* skip if key stored in x is pressed */ }
-                        0xA1 => { /* skip if key stored in x is NOT pressed */ }
-                        _ => { /* invalid */ }
-                    };
+                0x1E => {
+                    self.add_i(x);
+                }
+                0x29 => {
+                    self.ld_f_x(x);
+                }
+                0x33 => {
+                    self.ld_b_x(x, memory);
+                }
+                0x55 => {
+                    self.ld_0_x_i(x, memory);
+                }
+                0x65 => {
+                    self.ld_i_0_x(x, memory);
                 }
-                0xF007..=0xFF65 => match kk {
-                    0x07 => {
-                        self.ld_x_dt(x);
-                    }
-                    0x0A => {
-                        self.ld_k_x(x, keyboard_receiver);
-                    }
-                    0x15 => {
-                        self.ld_dt_x(x);
-                    }
-                    0x18 => {
-                        self.ld_st_x(x);
-                    }
-                    0x1E => {
-                        self.add_i(x);
-                    }
-                    0x29 => {
-                        self.ld_f_x(x);
-                    }
-                    0x33 => {
-                        self.ld_b_x(x, memory);
-                    }
-                    0x55 => {
-                        self.ld_0_x_i(x, memory);
-                    }
-                    0x65 => {
-                        self.ld_i_0_x(x, memory);
-                    }
-                    _ => { /* invalid */ }
-                },
                 _ => { /* invalid */ }
-            }
+            },
+            _ => { /* invalid */ }
         }
+        // a skip can step past the top of memory
+        self.program_counter %= MEMORY_SIZE;
+        return Step::Continue;
     }
 
-    fn clr(display: &mut [[u8; 128]; 64], sender: &relm4::Sender<Message>) {
-        thread::sleep(Duration::from_secs(1));
+    fn clr(display: &mut [[u8; 128]; 64]) {
         *display = [[0; 128]; 64];
-        sender.emit(Message::Clr);
     }
 
-    fn ret(&mut self, stack: &[u16; 16]) {
-        if self.stack_pointer == 0 {
-            panic!("stack underflow!")
+    // Returns false on a stack underflow, which stops the machine
+    fn ret(&mut self, stack: &[u16]) -> bool {
+        if self.stack_pointer == 0 || self.stack_pointer > stack.len() {
+            return false;
         }
         self.stack_pointer -= 1;
         let call_nnn = stack[self.stack_pointer];
-        self.program_counter = call_nnn as usize;
+        self.program_counter = call_nnn as usize % MEMORY_SIZE;
+        return true;
     }
 
     fn jmp(&mut self, nnn: u16) {
         self.program_counter = nnn as usize;
     }
 
-    fn call(&mut self, nnn: u16, stack: &mut [u16; 16]) {
+    // Returns false on a stack overflow, which stops the machine
+    fn call(&mut self, nnn: u16, stack: &mut [u16]) -> bool {
         let sp = self.stack_pointer;
 
-        if sp > stack.len() {
-            panic!("stack overflow!");
+        if sp >= stack.len() {
+            return false;
         }
 
         stack[sp] = self.program_counter as u16;
         self.stack_pointer += 1;
         self.program_counter = nnn as usize;
+        return true;
     }
 
     fn se(&mut self, x: u8, kk: u8) {
@@ -273,8 +367,18 @@ impl CPU {
         }
     }
 
-    fn shr(&mut self, x: u8) {
-        let arg = self.registers[x as usize];
+    fn reset_vf(&mut self) {
+        if self.quirks.logic {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    fn shr(&mut self, x: u8, y: u8) {
+        let arg = if self.quirks.shift {
+            self.registers[x as usize]
+        } else {
+            self.registers[y as usize]
+        };
 
         let lsb = arg & 0x1;
 
@@ -302,12 +406,16 @@ impl CPU {
         self.registers[x as usize] = val;
     }
 
-    fn shl(&mut self, x: u8) {
-        let arg = self.registers[x as usize];
+    fn shl(&mut self, x: u8, y: u8) {
+        let arg = if self.quirks.shift {
+            self.registers[x as usize]
+        } else {
+            self.registers[y as usize]
+        };
 
         let msb = arg & 0x80;
 
-        if msb == 1 {
+        if msb != 0 {
             self.registers[0xF] = 1;
         } else {
             self.registers[0xF] = 0;
@@ -320,75 +428,83 @@ impl CPU {
         self.i = nnn as usize;
     }
 
-    fn jmp_0(&mut self, nnn: u16) {
-        let v0 = self.registers[0] as usize;
-        self.i = (nnn as usize) + v0;
+    fn jmp_0(&mut self, x: u8, nnn: u16) {
+        let offset = if self.quirks.jump {
+            self.registers[x as usize] as usize
+        } else {
+            self.registers[0] as usize
+        };
+        self.program_counter = (nnn as usize + offset) % MEMORY_SIZE;
     }
 
+    // a linear congruential generator, whose high bits are the random ones
     fn rnd(&mut self, x: u8, kk: u8) {
-        let seed = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("failed to get time")
-            .as_micros();
-
-        self.registers[x as usize] = (seed as u8) & kk;
+        self.random = self
+            .random
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        self.registers[x as usize] = (self.random >> 24) as u8 & kk;
     }
 
-    fn drw(
-        &mut self,
-        n: u8,
-        x: u8,
-        y: u8,
-        memory: &[u8; 0x1000],
-        display: &mut [[u8; 128]; 64],
-        sender: &relm4::Sender<Message>,
-    ) {
-        let arg1 = self.registers[x as usize];
-        let arg2 = self.registers[y as usize];
-        let bytes = &memory[self.i..self.i + n as usize];
-        let collision = false;
-        for c in 0..n {
-            let (mut row, _) = arg2.overflowing_add(c);
+    fn drw(&mut self, n: u8, x: u8, y: u8, memory: &[u8; 0x1000], display: &mut [[u8; 128]; 64]) {
+        let origin_col = self.registers[x as usize] as usize % 128;
+        let origin_row = self.registers[y as usize] as usize % 64;
+        let mut collision = false;
+        for c in 0..n as usize {
+            let mut row = origin_row + c;
             if row >= 64 {
-                self.registers[0xF] = 1;
-                row = row % 64;
+                if !self.quirks.wrap {
+                    break;
+                }
+                row %= 64;
             }
+            let byte = memory[(self.i + c) % memory.len()];
             for pl in 0..8 {
-                let (mut col, _) = arg1.overflowing_add(pl);
+                let mut col = origin_col + pl;
                 if col >= 128 {
-                    self.registers[0xF] = 1;
-                    col = col % 128
+                    if !self.quirks.wrap {
+                        break;
+                    }
+                    col %= 128;
                 }
-                let bit = (bytes[c as usize] >> (7 - pl)) & 0x1;
-                let curr = &mut display[row as usize][col as usize];
+                let bit = (byte >> (7 - pl)) & 0x1;
+                let curr = &mut display[row][col];
                 if *curr & bit == 1 {
-                    self.registers[0xF] = 1;
+                    collision = true;
                 }
-                *curr = *curr ^ bit;
-                sender.emit(Message::Drw(row, col, *curr));
+                *curr ^= bit;
             }
         }
-        if !collision {
-            self.registers[0xF] = 0;
-        }
+        self.registers[0xF] = collision as u8;
     }
 
     fn ld_x_dt(&mut self, x: u8) {
         self.registers[x as usize] = self.delay_timer;
     }
 
-    fn ld_k_x(&mut self, x: u8, keyboard_receiver: &Receiver<u16>) {
-        let keys = keyboard_receiver.recv().unwrap();
-        if keys != 0 {
-            for val in 0..16 {
-                if keys >> (15 - val) & 1 == 1 {
-                    self.registers[x as usize] = val;
-                    break;
-                }
-            }
+    fn skp(&mut self, x: u8, keypad: &Keypad) {
+        if keypad.is_held(self.registers[x as usize]) {
+            self.program_counter += 2;
         }
     }
 
+    fn sknp(&mut self, x: u8, keypad: &Keypad) {
+        if !keypad.is_held(self.registers[x as usize]) {
+            self.program_counter += 2;
+        }
+    }
+
+    // Returns false while no key has been pressed yet, in which case the
+    // instruction is repeated instead of blocking the emulator thread.
+    fn ld_k_x(&mut self, x: u8, keypad: &mut Keypad) -> bool {
+        if let Some(key) = keypad.take_pressed() {
+            self.registers[x as usize] = key;
+            return true;
+        }
+        self.program_counter = (self.program_counter + MEMORY_SIZE - 2) % MEMORY_SIZE;
+        return false;
+    }
+
     fn ld_dt_x(&mut self, x: u8) {
         self.delay_timer = self.registers[x as usize];
     }
@@ -407,28 +523,42 @@ impl CPU {
         self.i = result;
     }
 
+    // only the low nibble picks a digit, as on the VIP
     fn ld_f_x(&mut self, x: u8) {
-        self.i = (self.registers[x as usize] * 5) as usize;
+        self.i = (self.font_start + (self.registers[x as usize] & 0xF) as usize * 5) % MEMORY_SIZE;
     }
 
     fn ld_b_x(&mut self, x: u8, memory: &mut [u8]) {
         let mut arg = self.registers[x as usize];
         for pl in 0..3 {
             let mag = u8::pow(10, 2 - pl as u32);
-            memory[self.i + pl] = arg / mag;
+            memory[(self.i + pl) % MEMORY_SIZE] = arg / mag;
             arg %= mag;
         }
     }
 
     fn ld_0_x_i(&mut self, x: u8, memory: &mut [u8]) {
         for reg in 0..=x {
-            memory[self.i + reg as usize] = self.registers[reg as usize];
+            memory[(self.i + reg as usize) % MEMORY_SIZE] = self.registers[reg as usize];
         }
+        self.advance_i(x);
     }
 
     fn ld_i_0_x(&mut self, x: u8, memory: &mut [u8]) {
         for reg in 0..=x {
-            self.registers[reg as usize] = memory[self.i + reg as usize];
+            self.registers[reg as usize] = memory[(self.i + reg as usize) % MEMORY_SIZE];
+        }
+        self.advance_i(x);
+    }
+
+    fn advance_i(&mut self, x: u8) {
+        if self.quirks.memory_leave_i_unchanged {
+            return;
+        }
+        if self.quirks.memory_increment_by_x {
+            self.i += x as usize;
+        } else {
+            self.i += x as usize + 1;
         }
     }
 }
@@ -34,3 +34,196 @@ fn test_add_xy() {
     assert_eq!(cpu.registers[0], 254);
     assert_eq!(cpu.registers[0xF], 1);
 }
+
+#[test]
+fn test_shift_quirk() {
+    let mut cpu = CPU::new();
+
+    cpu.registers[0] = 0;
+    cpu.registers[1] = 0b11;
+    cpu.shr(0, 1);
+    assert_eq!(cpu.registers[0], 0b1);
+    assert_eq!(cpu.registers[0xF], 1);
+
+    cpu.set_quirks(Platform::SuperChip.quirks());
+    cpu.registers[0] = 0x80;
+    cpu.shl(0, 1);
+    assert_eq!(cpu.registers[0], 0);
+    assert_eq!(cpu.registers[0xF], 1);
+}
+
+#[test]
+fn test_jump_quirk() {
+    let mut cpu = CPU::new();
+
+    cpu.registers[0] = 2;
+    cpu.registers[3] = 4;
+    cpu.jmp_0(3, 0x300);
+    assert_eq!(cpu.program_counter, 0x302);
+
+    cpu.set_quirks(Platform::Chip48.quirks());
+    cpu.jmp_0(3, 0x300);
+    assert_eq!(cpu.program_counter, 0x304);
+}
+
+#[test]
+fn test_ld_k_x_waits_for_key() {
+    let mut cpu = CPU::new();
+    let mut keypad = Keypad::new();
+
+    cpu.program_counter = 0x202;
+    assert!(!cpu.ld_k_x(0, &mut keypad));
+    assert_eq!(cpu.program_counter, 0x200);
+
+    keypad.set_key(0xA, true);
+    assert!(cpu.ld_k_x(0, &mut keypad));
+    assert_eq!(cpu.registers[0], 0xA);
+}
+
+#[test]
+fn test_skp() {
+    let mut cpu = CPU::new();
+    let mut keypad = Keypad::new();
+    cpu.registers[1] = 0x5;
+
+    cpu.skp(1, &keypad);
+    assert_eq!(cpu.program_counter, 0x200);
+    cpu.sknp(1, &keypad);
+    assert_eq!(cpu.program_counter, 0x202);
+
+    keypad.set_key(0x5, true);
+    cpu.skp(1, &keypad);
+    assert_eq!(cpu.program_counter, 0x204);
+}
+
+#[test]
+fn test_registers() {
+    let mut cpu = CPU::new();
+    cpu.registers[0xA] = 0x42;
+    cpu.delay_timer = 3;
+
+    let mut registers = cpu.registers();
+    assert_eq!(registers.v[0xA], 0x42);
+    assert_eq!(registers.program_counter, 0x200);
+    assert_eq!(registers.delay_timer, 3);
+
+    registers.i = 0x300;
+    registers.stack_pointer = 2;
+    cpu.set_registers(registers);
+    assert_eq!(cpu.i, 0x300);
+    assert_eq!(cpu.stack_pointer, 2);
+}
+
+#[cfg(test)]
+fn step_at(cpu: &mut CPU, memory: &mut [u8; 4096], opcode: u16) -> Step {
+    let pc = cpu.program_counter;
+    memory[pc] = (opcode >> 8) as u8;
+    memory[(pc + 1) % 4096] = opcode as u8;
+    return cpu.step(
+        memory,
+        &mut [0; 16],
+        &mut [[0; 128]; 64],
+        &mut Keypad::new(),
+    );
+}
+
+#[test]
+fn test_stack_faults_halt() {
+    let mut cpu = CPU::new();
+    let mut memory = [0; 4096];
+    assert_eq!(step_at(&mut cpu, &mut memory, 0x00EE), Step::Halt);
+
+    let mut stack = [0; 16];
+    memory[0x200] = 0x22;
+    memory[0x201] = 0x00;
+    for _ in 0..16 {
+        cpu.program_counter = 0x200;
+        let step = cpu.step(
+            &mut memory,
+            &mut stack,
+            &mut [[0; 128]; 64],
+            &mut Keypad::new(),
+        );
+        assert_eq!(step, Step::Continue);
+    }
+    let step = cpu.step(
+        &mut memory,
+        &mut stack,
+        &mut [[0; 128]; 64],
+        &mut Keypad::new(),
+    );
+    assert_eq!(step, Step::Halt);
+    assert_eq!(cpu.stack_pointer, 16);
+
+    // the VIP only has room for 12
+    let mut cpu = CPU::new();
+    let mut stack = [0; 12];
+    for _ in 0..12 {
+        cpu.program_counter = 0x200;
+        let step = cpu.step(
+            &mut memory,
+            &mut stack,
+            &mut [[0; 128]; 64],
+            &mut Keypad::new(),
+        );
+        assert_eq!(step, Step::Continue);
+    }
+    cpu.program_counter = 0x200;
+    let step = cpu.step(
+        &mut memory,
+        &mut stack,
+        &mut [[0; 128]; 64],
+        &mut Keypad::new(),
+    );
+    assert_eq!(step, Step::Halt);
+}
+
+#[test]
+fn test_addresses_wrap() {
+    let mut cpu = CPU::new();
+    let mut memory = [0; 4096];
+
+    // an instruction straddling the top of memory
+    cpu.program_counter = 0xFFF;
+    memory[0] = 0x05;
+    memory[0xFFF] = 0x60;
+    cpu.step(
+        &mut memory,
+        &mut [0; 16],
+        &mut [[0; 128]; 64],
+        &mut Keypad::new(),
+    );
+    assert_eq!(cpu.registers[0], 0x05);
+    assert_eq!(cpu.program_counter, 0x001);
+
+    cpu.program_counter = 0x200;
+    cpu.i = 0xFFE;
+    cpu.registers[1] = 123;
+    step_at(&mut cpu, &mut memory, 0xF133);
+    assert_eq!(memory[0xFFE..], [1, 2]);
+    assert_eq!(memory[0], 3);
+
+    cpu.registers[2] = 0xFF;
+    step_at(&mut cpu, &mut memory, 0xF229);
+    assert_eq!(cpu.i, 0xF * 5);
+
+    cpu.registers[0] = 0xFF;
+    cpu.program_counter = 0x200;
+    step_at(&mut cpu, &mut memory, 0xBF02);
+    assert_eq!(cpu.program_counter, 0x001);
+}
+
+#[test]
+fn test_rnd_seed() {
+    let mut first = CPU::new();
+    let mut second = CPU::new();
+    first.set_random_state(42);
+    second.set_random_state(42);
+    for _ in 0..8 {
+        first.rnd(0, 0xFF);
+        second.rnd(0, 0xFF);
+        assert_eq!(first.registers[0], second.registers[0]);
+    }
+    first.rnd(1, 0x0F);
+    assert_eq!(first.registers[1] & 0xF0, 0);
+}
@@ -0,0 +1,177 @@
+mod test;
+
+use super::{Registers, Symbols};
+use std::{collections::HashMap, fmt, ops::Range};
+
+const MEMORY_SIZE: usize = 0x1000;
+// past this, new kinds of warning are only counted, not kept
+const MAX_WARNINGS: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Problem {
+    // a write to a byte that has already run as an instruction
+    SelfModifyingCode { address: usize },
+    FontWrite { address: usize },
+    // the instruction itself lies outside the loaded program
+    OutsideProgram,
+    // addresses past the end of memory, as I + n can easily produce
+    ReadOutOfBounds { address: usize },
+    WriteOutOfBounds { address: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Warning {
+    // the instruction that caused it
+    pub pc: usize,
+    pub problem: Problem,
+    // how many times this instruction did this
+    pub count: u64,
+}
+
+impl Warning {
+    // Like its Display, but with the instruction's label and source line and
+    // the label of any address it touched
+    pub fn describe(&self, symbols: &Symbols) -> String {
+        let at = |address: usize| -> String {
+            let name = symbols.name(address);
+            return match name == format!("{:03X}", address) {
+                true => name,
+                false => format!("{:03X} ({})", address, name),
+            };
+        };
+        let mut text = at(self.pc);
+        if let Some(line) = symbols.line(self.pc) {
+            text.push_str(&format!(" [{}]", line));
+        }
+        text.push_str(": ");
+        text.push_str(&match self.problem {
+            Problem::SelfModifyingCode { address } => {
+                format!("wrote to {}, which has run as code", at(address))
+            }
+            Problem::FontWrite { address } => format!("wrote to {} in the font", at(address)),
+            Problem::OutsideProgram => String::from("executed outside the loaded program"),
+            Problem::ReadOutOfBounds { address } => {
+                format!("read from {:X}, past the end of memory", address)
+            }
+            Problem::WriteOutOfBounds { address } => {
+                format!("wrote to {:X}, past the end of memory", address)
+            }
+        });
+        if self.count > 1 {
+            text.push_str(&format!(" ({} times)", self.count));
+        }
+        return text;
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.describe(&Symbols::default()));
+    }
+}
+
+// Watches memory accesses for the silent corruption real hardware allows:
+// self-modifying code, a trampled font, runaway jumps and I pointing off the
+// end of memory.
+#[derive(Clone, Debug)]
+pub struct Diagnostics {
+    program: Range<usize>,
+    font: Range<usize>,
+    executed: Vec<bool>,
+    warnings: Vec<Warning>,
+    // where each distinct warning sits in the list
+    seen: HashMap<(usize, Problem), usize>,
+    dropped: u64,
+}
+
+impl Diagnostics {
+    pub fn new(program: Range<usize>, font: Range<usize>) -> Self {
+        return Self {
+            program,
+            font,
+            executed: vec![false; MEMORY_SIZE],
+            warnings: Vec::new(),
+            seen: HashMap::new(),
+            dropped: 0,
+        };
+    }
+
+    // Called with the registers as they are just before opcode executes
+    pub fn record(&mut self, registers: &Registers, opcode: u16) {
+        let pc = registers.program_counter;
+        if !self.program.contains(&pc) || !self.program.contains(&(pc + 1)) {
+            self.warn(pc, Problem::OutsideProgram);
+        }
+        if pc + 1 >= MEMORY_SIZE {
+            self.warn(pc, Problem::ReadOutOfBounds { address: pc + 1 });
+        }
+        for address in pc..(pc + 2).min(MEMORY_SIZE) {
+            self.executed[address] = true;
+        }
+
+        let i = registers.i;
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        match opcode & 0xF0FF {
+            0xF033 => self.write(pc, i..i + 3),
+            0xF055 => self.write(pc, i..i + x + 1),
+            0xF065 => self.read(pc, i..i + x + 1),
+            _ if opcode & 0xF000 == 0xD000 => self.read(pc, i..i + (opcode & 0xF) as usize),
+            _ => {}
+        }
+    }
+
+    fn read(&mut self, pc: usize, range: Range<usize>) {
+        if range.end > MEMORY_SIZE {
+            let address = range.start.max(MEMORY_SIZE);
+            self.warn(pc, Problem::ReadOutOfBounds { address });
+        }
+    }
+
+    fn write(&mut self, pc: usize, range: Range<usize>) {
+        for address in range {
+            if address >= MEMORY_SIZE {
+                self.warn(pc, Problem::WriteOutOfBounds { address });
+                break;
+            }
+            if self.font.contains(&address) {
+                self.warn(pc, Problem::FontWrite { address });
+            } else if self.executed[address] {
+                self.warn(pc, Problem::SelfModifyingCode { address });
+            }
+        }
+    }
+
+    fn warn(&mut self, pc: usize, problem: Problem) {
+        if let Some(index) = self.seen.get(&(pc, problem)) {
+            self.warnings[*index].count += 1;
+            return;
+        }
+        if self.warnings.len() >= MAX_WARNINGS {
+            self.dropped += 1;
+            return;
+        }
+        self.seen.insert((pc, problem), self.warnings.len());
+        self.warnings.push(Warning {
+            pc,
+            problem,
+            count: 1,
+        });
+    }
+
+    // in the order they first happened
+    pub fn warnings(&self) -> &[Warning] {
+        return &self.warnings;
+    }
+
+    // Everything found so far, one warning per line
+    pub fn report(&self, symbols: &Symbols) -> String {
+        let mut text = String::new();
+        for warning in self.warnings.iter() {
+            text.push_str(&format!("{}\n", warning.describe(symbols)));
+        }
+        if self.dropped > 0 {
+            text.push_str(&format!("{} more warnings not listed\n", self.dropped));
+        }
+        return text;
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+use super::*;
+#[cfg(test)]
+use crate::emulator::Emulator;
+
+#[test]
+fn test_diagnostics() {
+    // LD I, 0x200; LD [I], V0; LD I, 0x010; LD B, V0; LD I, 0xFFE;
+    // DRW V0, V0, 5; JP 0x300, where there's nothing but a 0000 to halt on
+    let program = [
+        0xA2, 0x00, 0xF0, 0x55, 0xA0, 0x10, 0xF0, 0x33, 0xAF, 0xFE, 0xD0, 0x05, 0x13, 0x00,
+    ];
+    let mut emulator = Emulator::new();
+    emulator.set_diagnostics(true);
+    emulator.load_program(&program).unwrap();
+    emulator.run_frame();
+    assert!(emulator.is_halted());
+    let warnings = emulator.diagnostics().unwrap().warnings();
+    let problems: Vec<(usize, Problem)> = warnings.iter().map(|w| (w.pc, w.problem)).collect();
+    assert_eq!(
+        problems,
+        vec![
+            (0x202, Problem::SelfModifyingCode { address: 0x200 }),
+            (0x206, Problem::FontWrite { address: 0x10 }),
+            (0x206, Problem::FontWrite { address: 0x11 }),
+            (0x206, Problem::FontWrite { address: 0x12 }),
+            (0x20A, Problem::ReadOutOfBounds { address: 0x1000 }),
+            (0x300, Problem::OutsideProgram),
+        ]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "202: wrote to 200, which has run as code"
+    );
+
+    let mut symbols = Symbols::parse("main 0x200").unwrap();
+    symbols.add_source_map("0x202 game.8o:2").unwrap();
+    assert_eq!(
+        warnings[0].describe(&symbols),
+        "202 (main+2) [game.8o:2]: wrote to 200 (main), which has run as code"
+    );
+    // the font lies before any label
+    assert_eq!(
+        warnings[1].describe(&symbols),
+        "206 (main+6): wrote to 010 in the font"
+    );
+}
@@ -0,0 +1,69 @@
+mod test;
+
+use super::Symbols;
+
+// Cowgod's mnemonics for an opcode, e.g. 0xD125 is "DRW V1, V2, 5". Opcodes
+// the CPU doesn't implement come out as raw data.
+pub fn disassemble(opcode: u16) -> String {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let kk = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+
+    return match opcode {
+        0x0000 => String::from("HALT"),
+        0x00E0 => String::from("CLS"),
+        0x00EE => String::from("RET"),
+        0x1000..=0x1FFF => format!("JP {:03X}", nnn),
+        0x2000..=0x2FFF => format!("CALL {:03X}", nnn),
+        0x3000..=0x3FFF => format!("SE V{:X}, {:02X}", x, kk),
+        0x4000..=0x4FFF => format!("SNE V{:X}, {:02X}", x, kk),
+        0x5000..=0x5FFF if n == 0 => format!("SE V{:X}, V{:X}", x, y),
+        0x6000..=0x6FFF => format!("LD V{:X}, {:02X}", x, kk),
+        0x7000..=0x7FFF => format!("ADD V{:X}, {:02X}", x, kk),
+        0x8000..=0x8FFF => match n {
+            0x0 => format!("LD V{:X}, V{:X}", x, y),
+            0x1 => format!("OR V{:X}, V{:X}", x, y),
+            0x2 => format!("AND V{:X}, V{:X}", x, y),
+            0x3 => format!("XOR V{:X}, V{:X}", x, y),
+            0x4 => format!("ADD V{:X}, V{:X}", x, y),
+            0x5 => format!("SUB V{:X}, V{:X}", x, y),
+            0x6 => format!("SHR V{:X}, V{:X}", x, y),
+            0x7 => format!("SUBN V{:X}, V{:X}", x, y),
+            0xE => format!("SHL V{:X}, V{:X}", x, y),
+            _ => format!("DW {:04X}", opcode),
+        },
+        0x9000..=0x9FFF if n == 0 => format!("SNE V{:X}, V{:X}", x, y),
+        0xA000..=0xAFFF => format!("LD I, {:03X}", nnn),
+        0xB000..=0xBFFF => format!("JP V0, {:03X}", nnn),
+        0xC000..=0xCFFF => format!("RND V{:X}, {:02X}", x, kk),
+        0xD000..=0xDFFF => format!("DRW V{:X}, V{:X}, {:X}", x, y, n),
+        0xE000..=0xEFFF if kk == 0x9E => format!("SKP V{:X}", x),
+        0xE000..=0xEFFF if kk == 0xA1 => format!("SKNP V{:X}", x),
+        0xF000..=0xFFFF => match kk {
+            0x07 => format!("LD V{:X}, DT", x),
+            0x0A => format!("LD V{:X}, K", x),
+            0x15 => format!("LD DT, V{:X}", x),
+            0x18 => format!("LD ST, V{:X}", x),
+            0x1E => format!("ADD I, V{:X}", x),
+            0x29 => format!("LD F, V{:X}", x),
+            0x33 => format!("LD B, V{:X}", x),
+            0x55 => format!("LD [I], V{:X}", x),
+            0x65 => format!("LD V{:X}, [I]", x),
+            _ => format!("DW {:04X}", opcode),
+        },
+        _ => format!("DW {:04X}", opcode),
+    };
+}
+
+// The same, with the targets of jumps, calls and LD I given by their label
+// where the symbols have one
+pub fn disassemble_with(opcode: u16, symbols: &Symbols) -> String {
+    let text = disassemble(opcode);
+    let addressed = matches!(opcode & 0xF000, 0x1000 | 0x2000 | 0xA000 | 0xB000);
+    return match symbols.label((opcode & 0x0FFF) as usize) {
+        Some(label) if addressed => format!("{}{}", &text[..text.len() - 3], label),
+        _ => text,
+    };
+}
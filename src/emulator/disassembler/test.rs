@@ -0,0 +1,26 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_disassemble() {
+    assert_eq!(disassemble(0x00E0), "CLS");
+    assert_eq!(disassemble(0x2ABC), "CALL ABC");
+    assert_eq!(disassemble(0x6A05), "LD VA, 05");
+    assert_eq!(disassemble(0x8126), "SHR V1, V2");
+    assert_eq!(disassemble(0xD125), "DRW V1, V2, 5");
+    assert_eq!(disassemble(0xE19E), "SKP V1");
+    assert_eq!(disassemble(0xF365), "LD V3, [I]");
+    assert_eq!(disassemble(0x5121), "DW 5121");
+    assert_eq!(disassemble(0xE1FF), "DW E1FF");
+}
+
+#[test]
+fn test_disassemble_with() {
+    let symbols = Symbols::parse("loop 0x2A4\nsprite 0x300").unwrap();
+    assert_eq!(disassemble_with(0x12A4, &symbols), "JP loop");
+    assert_eq!(disassemble_with(0xA300, &symbols), "LD I, sprite");
+    assert_eq!(disassemble_with(0xB2A4, &symbols), "JP V0, loop");
+    assert_eq!(disassemble_with(0x22A6, &symbols), "CALL 2A6");
+    // an immediate that happens to match a label is left alone
+    assert_eq!(disassemble_with(0x6300, &symbols), "LD V3, 00");
+}
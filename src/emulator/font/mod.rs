@@ -0,0 +1,181 @@
+mod test;
+
+use std::{fmt, fs, io, path::Path};
+
+// sixteen 4x5 hex digits, the font FX29 points I at
+pub const SMALL_SIZE: usize = 0x50;
+// ten 8x10 decimal digits, as SCHIP added for its high resolution mode
+pub const BIG_SIZE: usize = 100;
+
+const MODERN: [u8; SMALL_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// as stored in the COSMAC VIP's interpreter ROM
+const VIP: [u8; SMALL_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0x70, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// the DREAM 6800's narrower three pixel wide digits
+const DREAM_6800: [u8; SMALL_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+const SCHIP_BIG: [u8; BIG_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+];
+
+const BUILTIN: [&str; 4] = ["modern", "vip", "dream6800", "schip"];
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    // a font file is the small digits, optionally followed by the big ones
+    WrongSize(usize),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            FontError::Io(err) => write!(f, "{}", err),
+            FontError::WrongSize(size) => write!(
+                f,
+                "font is {} bytes, expected {} or {}",
+                size,
+                SMALL_SIZE,
+                SMALL_SIZE + BIG_SIZE
+            ),
+        };
+    }
+}
+
+impl std::error::Error for FontError {}
+
+// The digit sprites loaded into memory below the program. The big digits, if
+// any, follow straight after the small ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Font {
+    pub small: [u8; SMALL_SIZE],
+    pub big: Option<[u8; BIG_SIZE]>,
+}
+
+impl Font {
+    pub fn builtin(name: &str) -> Option<Self> {
+        return match name {
+            "modern" => Some(Self::new(MODERN, None)),
+            "vip" => Some(Self::new(VIP, None)),
+            "dream6800" => Some(Self::new(DREAM_6800, None)),
+            "schip" => Some(Self::new(MODERN, Some(SCHIP_BIG))),
+            _ => None,
+        };
+    }
+
+    pub fn names() -> &'static [&'static str] {
+        return &BUILTIN;
+    }
+
+    fn new(small: [u8; SMALL_SIZE], big: Option<[u8; BIG_SIZE]>) -> Self {
+        return Self { small, big };
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FontError> {
+        let mut small = [0; SMALL_SIZE];
+        return match bytes.len() {
+            SMALL_SIZE => {
+                small.copy_from_slice(bytes);
+                Ok(Self::new(small, None))
+            }
+            size if size == SMALL_SIZE + BIG_SIZE => {
+                let mut big = [0; BIG_SIZE];
+                small.copy_from_slice(&bytes[..SMALL_SIZE]);
+                big.copy_from_slice(&bytes[SMALL_SIZE..]);
+                Ok(Self::new(small, Some(big)))
+            }
+            size => Err(FontError::WrongSize(size)),
+        };
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FontError> {
+        return Self::from_bytes(&fs::read(path).map_err(FontError::Io)?);
+    }
+
+    // A builtin font's name, or else the path of a font file
+    pub fn parse(spec: &str) -> Result<Self, FontError> {
+        return match Self::builtin(spec) {
+            Some(font) => Ok(font),
+            None => Self::load(Path::new(spec)),
+        };
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut bytes = self.small.to_vec();
+        if let Some(big) = self.big {
+            bytes.extend_from_slice(&big);
+        }
+        return bytes;
+    }
+
+    pub fn size(&self) -> usize {
+        return SMALL_SIZE + self.big.map_or(0, |_| BIG_SIZE);
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        return Self::new(MODERN, None);
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_builtin_fonts() {
+    for name in Font::names() {
+        let font = Font::builtin(name).unwrap();
+        assert_eq!(font.bytes().len(), font.size());
+    }
+    assert_eq!(Font::builtin("modern"), Some(Font::default()));
+    assert_eq!(
+        Font::builtin("schip").unwrap().size(),
+        SMALL_SIZE + BIG_SIZE
+    );
+    assert_eq!(Font::builtin("vip").unwrap().small[5], 0x60);
+    assert!(Font::builtin("eti660").is_none());
+}
+
+#[test]
+fn test_font_from_bytes() {
+    let font = Font::from_bytes(&[0xAA; SMALL_SIZE]).unwrap();
+    assert_eq!(font.big, None);
+    assert_eq!(font.small[SMALL_SIZE - 1], 0xAA);
+
+    let font = Font::from_bytes(&[0x55; SMALL_SIZE + BIG_SIZE]).unwrap();
+    assert_eq!(font.big, Some([0x55; BIG_SIZE]));
+    assert_eq!(font.bytes(), vec![0x55; SMALL_SIZE + BIG_SIZE]);
+
+    assert!(matches!(
+        Font::from_bytes(&[0; 10]),
+        Err(FontError::WrongSize(10))
+    ));
+    assert!(matches!(
+        Font::parse("/nonexistent/font.bin"),
+        Err(FontError::Io(_))
+    ));
+}
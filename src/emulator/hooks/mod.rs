@@ -0,0 +1,176 @@
+mod test;
+
+use super::{Display, Registers};
+use std::{fmt, ops::Range};
+
+const MEMORY_SIZE: usize = 0x1000;
+
+// What a hook gets to look at. Hooks only watch, changing the machine is up
+// to whoever owns the emulator.
+pub struct Machine<'a> {
+    pub registers: Registers,
+    pub stack: &'a [u16],
+    pub memory: &'a [u8; MEMORY_SIZE],
+    pub display: &'a Display,
+}
+
+// handed out when a hook is added, to take it away again
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HookId(usize);
+
+type Execute = Box<dyn FnMut(&Machine, u16) + Send>;
+type Access = Box<dyn FnMut(&Machine, usize, u8) + Send>;
+type Sound = Box<dyn FnMut(&Machine, bool) + Send>;
+type Frame = Box<dyn FnMut(&Machine) + Send>;
+
+enum Hook {
+    // with the opcode about to run, before it runs
+    Execute(Execute),
+    // the address and the byte there, before the instruction reads it
+    Read(Access),
+    // the address and the byte now there, after the instruction wrote it
+    Write(Access),
+    // after CLS or DRW, with its opcode
+    Draw(Execute),
+    // true when the sound timer starts the beep, false when it runs out
+    Sound(Sound),
+    Frame(Frame),
+}
+
+// The memory an instruction is about to read or write, worked out from the
+// registers just before it runs. Addresses past the end of memory wrap.
+fn accesses(registers: &Registers, opcode: u16) -> (Range<usize>, Range<usize>) {
+    let i = registers.i;
+    let x = ((opcode & 0x0F00) >> 8) as usize;
+    return match opcode & 0xF0FF {
+        0xF033 => (0..0, i..i + 3),
+        0xF055 => (0..0, i..i + x + 1),
+        0xF065 => (i..i + x + 1, 0..0),
+        _ if opcode & 0xF000 == 0xD000 => (i..i + (opcode & 0xF) as usize, 0..0),
+        _ => (0..0, 0..0),
+    };
+}
+
+fn draws(opcode: u16) -> bool {
+    return opcode == 0x00E0 || opcode & 0xF000 == 0xD000;
+}
+
+// Callbacks for tooling that lives outside the core: achievements, analytics,
+// overlays and the like. A clone of the emulator starts without any.
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<(HookId, Hook)>,
+    next: usize,
+}
+
+impl Clone for Hooks {
+    fn clone(&self) -> Self {
+        return Self::default();
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "Hooks({})", self.hooks.len());
+    }
+}
+
+impl Hooks {
+    fn add(&mut self, hook: Hook) -> HookId {
+        let id = HookId(self.next);
+        self.next += 1;
+        self.hooks.push((id, hook));
+        return id;
+    }
+
+    pub fn on_execute(&mut self, hook: impl FnMut(&Machine, u16) + Send + 'static) -> HookId {
+        return self.add(Hook::Execute(Box::new(hook)));
+    }
+
+    pub fn on_read(&mut self, hook: impl FnMut(&Machine, usize, u8) + Send + 'static) -> HookId {
+        return self.add(Hook::Read(Box::new(hook)));
+    }
+
+    pub fn on_write(&mut self, hook: impl FnMut(&Machine, usize, u8) + Send + 'static) -> HookId {
+        return self.add(Hook::Write(Box::new(hook)));
+    }
+
+    pub fn on_draw(&mut self, hook: impl FnMut(&Machine, u16) + Send + 'static) -> HookId {
+        return self.add(Hook::Draw(Box::new(hook)));
+    }
+
+    pub fn on_sound(&mut self, hook: impl FnMut(&Machine, bool) + Send + 'static) -> HookId {
+        return self.add(Hook::Sound(Box::new(hook)));
+    }
+
+    pub fn on_frame(&mut self, hook: impl FnMut(&Machine) + Send + 'static) -> HookId {
+        return self.add(Hook::Frame(Box::new(hook)));
+    }
+
+    // false if it had already gone
+    pub fn remove(&mut self, id: HookId) -> bool {
+        let count = self.hooks.len();
+        self.hooks.retain(|(hook, _)| *hook != id);
+        return self.hooks.len() != count;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.hooks.is_empty();
+    }
+
+    // Everything due before an instruction runs. Returns the memory it is
+    // about to write, which only the registers as they are now can tell.
+    pub(super) fn before(&mut self, machine: &Machine, opcode: u16) -> Range<usize> {
+        let (reads, writes) = accesses(&machine.registers, opcode);
+        for (_, hook) in self.hooks.iter_mut() {
+            match hook {
+                Hook::Execute(hook) => hook(machine, opcode),
+                Hook::Read(hook) => {
+                    for address in reads.clone() {
+                        let address = address % MEMORY_SIZE;
+                        hook(machine, address, machine.memory[address]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        return writes;
+    }
+
+    // Everything due after it has, given the writes it was going to make
+    // and whether the beep was on before it
+    pub(super) fn after(
+        &mut self,
+        machine: &Machine,
+        opcode: u16,
+        writes: Range<usize>,
+        beeping: bool,
+    ) {
+        let now = machine.registers.sound_timer > 0;
+        for (_, hook) in self.hooks.iter_mut() {
+            match hook {
+                Hook::Write(hook) => {
+                    for address in writes.clone() {
+                        let address = address % MEMORY_SIZE;
+                        hook(machine, address, machine.memory[address]);
+                    }
+                }
+                Hook::Draw(hook) if draws(opcode) => hook(machine, opcode),
+                Hook::Sound(hook) if now != beeping => hook(machine, now),
+                _ => {}
+            }
+        }
+    }
+
+    // At the end of every frame, once the timers have ticked
+    pub(super) fn frame(&mut self, machine: &Machine, beeping: bool) {
+        let now = machine.registers.sound_timer > 0;
+        for (_, hook) in self.hooks.iter_mut() {
+            match hook {
+                Hook::Sound(hook) if now != beeping => hook(machine, now),
+                Hook::Frame(hook) => hook(machine),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+use super::*;
+#[cfg(test)]
+use crate::emulator::Emulator;
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_hooks() {
+    // LD V0, 3; LD ST, V0; LD I, 0x300; LD [I], V0; LD V1, [I]; DRW V0, V0, 1;
+    // JP 0x20C
+    let program = [
+        0x60, 0x03, 0xF0, 0x18, 0xA3, 0x00, 0xF0, 0x55, 0xF1, 0x65, 0xD0, 0x01, 0x12, 0x0C,
+    ];
+    let mut emulator = Emulator::new();
+    emulator.load_program(&program).unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let log = |events: &Arc<Mutex<Vec<String>>>| {
+        let events = events.clone();
+        return move |event: String| events.lock().unwrap().push(event);
+    };
+    let push = log(&events);
+    let execute = emulator.hooks().on_execute(move |machine, opcode| {
+        push(format!(
+            "{:03X} {:04X}",
+            machine.registers.program_counter, opcode
+        ))
+    });
+    let push = log(&events);
+    emulator
+        .hooks()
+        .on_read(move |_, address, value| push(format!("read {:03X} {:02X}", address, value)));
+    let push = log(&events);
+    emulator
+        .hooks()
+        .on_write(move |_, address, value| push(format!("write {:03X} {:02X}", address, value)));
+    let push = log(&events);
+    emulator
+        .hooks()
+        .on_draw(move |machine, _| push(format!("draw {}", machine.display[3][3])));
+    let push = log(&events);
+    emulator
+        .hooks()
+        .on_sound(move |_, on| push(format!("sound {}", on)));
+    let push = log(&events);
+    emulator
+        .hooks()
+        .on_frame(move |machine| push(format!("frame {}", machine.registers.sound_timer)));
+
+    emulator.run_frame();
+    let frame: Vec<String> = events.lock().unwrap().drain(..).collect();
+    assert_eq!(
+        frame[..14],
+        [
+            "200 6003",
+            "202 F018",
+            "sound true",
+            "204 A300",
+            "206 F055",
+            "write 300 03",
+            "208 F165",
+            // I has moved on past what FX55 and FX65 touched
+            "read 301 00",
+            "read 302 00",
+            "20A D001",
+            "read 303 00",
+            "draw 0",
+            "20C 120C",
+            "20C 120C",
+        ]
+    );
+    assert_eq!(frame.last().unwrap(), "frame 2");
+
+    // the beep runs out at the end of the third frame
+    assert!(emulator.hooks().remove(execute));
+    assert!(!emulator.hooks().remove(execute));
+    emulator.run_frame();
+    emulator.run_frame();
+    let frames: Vec<String> = events.lock().unwrap().drain(..).collect();
+    assert_eq!(frames, ["frame 1", "sound false", "frame 0"]);
+}
+
+#[test]
+fn test_accesses() {
+    let mut emulator = Emulator::new();
+    emulator.load_program(&[0x12, 0x00]).unwrap();
+    let mut registers = emulator.snapshot().registers;
+    registers.i = 0xFFE;
+    assert_eq!(accesses(&registers, 0xF355), (0..0, 0xFFE..0x1002));
+    assert_eq!(accesses(&registers, 0xD125), (0xFFE..0x1003, 0..0));
+    assert_eq!(accesses(&registers, 0x00E0), (0..0, 0..0));
+    assert!(draws(0x00E0) && draws(0xD125) && !draws(0x00EE));
+}
@@ -0,0 +1,52 @@
+mod test;
+
+// The hexadecimal keypad as the frontend last reported it. Key n is bit
+// 15 - n, the same layout the keyboard has always sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keypad {
+    held: u16,
+    // keys that went down since the end of the last frame, for FX0A
+    pressed: u16,
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn set(&mut self, keys: u16) {
+        self.pressed |= keys & !self.held;
+        self.held = keys;
+    }
+
+    pub fn set_key(&mut self, key: u8, down: bool) {
+        let bit = 1 << (15 - (key & 0xF));
+        if down {
+            self.set(self.held | bit);
+        } else {
+            self.set(self.held & !bit);
+        }
+    }
+
+    pub fn keys(&self) -> u16 {
+        return self.held;
+    }
+
+    pub fn is_held(&self, key: u8) -> bool {
+        return self.held >> (15 - (key & 0xF)) & 1 == 1;
+    }
+
+    pub fn take_pressed(&mut self) -> Option<u8> {
+        for key in 0..16 {
+            if self.pressed >> (15 - key) & 1 == 1 {
+                self.pressed = 0;
+                return Some(key);
+            }
+        }
+        return None;
+    }
+
+    pub fn end_frame(&mut self) {
+        self.pressed = 0;
+    }
+}
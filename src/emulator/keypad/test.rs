@@ -0,0 +1,24 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_keypad() {
+    let mut keypad = Keypad::new();
+    assert_eq!(keypad.take_pressed(), None);
+
+    keypad.set_key(0xA, true);
+    assert!(keypad.is_held(0xA));
+    assert_eq!(keypad.keys(), 1 << (15 - 0xA));
+    assert_eq!(keypad.take_pressed(), Some(0xA));
+    assert_eq!(keypad.take_pressed(), None);
+
+    // holding a key down doesn't count as another press
+    keypad.set(keypad.keys());
+    assert_eq!(keypad.take_pressed(), None);
+
+    keypad.set_key(0xA, false);
+    keypad.set_key(0x3, true);
+    keypad.end_frame();
+    assert!(keypad.is_held(0x3));
+    assert_eq!(keypad.take_pressed(), None);
+}
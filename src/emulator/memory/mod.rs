@@ -1,48 +1,163 @@
-const FONT: [u8; 0x50] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
+mod test;
+
+use super::{Font, MAX_STACK_DEPTH};
+use std::{fmt, ops::Range};
+
+pub const PROGRAM_START: usize = 0x200;
+pub const FONT_START: usize = 0x000;
+// where the VIP interpreter keeps V0-VF, just above its work area
+pub const VARIABLES: usize = 0xEF0;
+// the VIP stack grows down from here, two bytes an entry, high byte first
+const STACK_TOP: usize = 0xED0;
+// the VIP keeps its stack, work area, V0-VF and display from here up
+pub const RESERVED_START: usize = 0xEA0;
+// the most the stack and V0-VF take up, with the deepest stack there is
+pub const RESERVED_SIZE: usize = VARIABLES + 16 - (STACK_TOP - 2 * MAX_STACK_DEPTH);
+
+#[derive(Debug)]
+pub enum LoadError {
+    TooLarge { size: usize, available: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            LoadError::TooLarge { size, available } => write!(
+                f,
+                "program is {} bytes but only {} bytes of memory are available",
+                size, available
+            ),
+        };
+    }
+}
+
+impl std::error::Error for LoadError {}
 
 #[derive(Clone)]
 pub struct RAM {
     pub memory: [u8; 0x1000],
-    pub stack: [u16; 16],
-    last_program_end: usize,
+    pub stack: Vec<u16>,
+    program_start: usize,
+    font_start: usize,
     last_sprite_end: usize,
+    program_end: usize,
 }
 
 impl RAM {
     pub fn new() -> Self {
+        return Self::with_layout(PROGRAM_START, FONT_START, &Font::default());
+    }
+
+    // Memory with the font loaded at font_start, ready for a program at
+    // program_start
+    pub fn with_layout(program_start: usize, font_start: usize, font: &Font) -> Self {
         let mut memory = Self {
             memory: [0; 0x1000],
-            stack: [0; 16],
-            last_program_end: 0x200,
-            last_sprite_end: 0,
+            stack: vec![0; 16],
+            program_start,
+            font_start,
+            last_sprite_end: font_start,
+            program_end: program_start,
         };
-        memory.load_font();
+        memory.load_font(font);
         return memory;
     }
 
-    pub fn load_program(&mut self, program: Vec<u8>) {
+    // Whether the program fits between program_start and end
+    pub fn check_program(
+        program: &[u8],
+        program_start: usize,
+        end: usize,
+    ) -> Result<(), LoadError> {
+        let available = end.saturating_sub(program_start);
+        if program.len() > available {
+            return Err(LoadError::TooLarge {
+                size: program.len(),
+                available,
+            });
+        }
+        return Ok(());
+    }
+
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), LoadError> {
+        Self::check_program(program, self.program_start, 0x1000)?;
         let len = program.len();
-        for i in 0..len {
-            self.memory[i + self.last_program_end] = program[i];
+        self.memory[self.program_start..self.program_start + len].copy_from_slice(program);
+        self.program_end = self.program_start + len;
+        return Ok(());
+    }
+
+    pub fn program(&self) -> Range<usize> {
+        return self.program_start..self.program_end;
+    }
+
+    pub fn font(&self) -> Range<usize> {
+        return self.font_start..self.last_sprite_end;
+    }
+
+    // Whatever no longer fits is dropped
+    pub fn set_stack_depth(&mut self, depth: usize) {
+        self.stack.resize(depth, 0);
+    }
+
+    fn stack_address(n: usize) -> usize {
+        return STACK_TOP - 2 * (n + 1);
+    }
+
+    // The bytes the VIP layout keeps the stack and V0-VF in. A stack deeper
+    // than the VIP's reaches down past 0xEA0 into program memory.
+    pub fn reserved(&self) -> Range<usize> {
+        return Self::stack_address(self.stack.len().max(1) - 1)..VARIABLES + 16;
+    }
+
+    // Where programs have to end when V0-VF and a stack this deep are kept
+    // in memory
+    pub fn reserved_start(depth: usize) -> usize {
+        return RESERVED_START.min(Self::stack_address(depth.max(1) - 1));
+    }
+
+    // The reserved area as it is now, copied without allocating since that
+    // happens before every instruction
+    pub fn copy_reserved(&self) -> [u8; RESERVED_SIZE] {
+        let reserved = self.reserved();
+        let mut copy = [0; RESERVED_SIZE];
+        copy[..reserved.len()].copy_from_slice(&self.memory[reserved]);
+        return copy;
+    }
+
+    // Reads V0-VF and the stack back out of the reserved area
+    pub fn fetch_reserved(&mut self, v: &mut [u8; 16]) {
+        v.copy_from_slice(&self.memory[VARIABLES..VARIABLES + 16]);
+        for (n, entry) in self.stack.iter_mut().enumerate() {
+            let address = Self::stack_address(n);
+            *entry = u16::from_be_bytes([self.memory[address], self.memory[address + 1]]);
+        }
+    }
+
+    // Writes V0-VF and the stack into the reserved area. Bytes that differ
+    // from before, a copy of the area taken earlier, were written there
+    // directly in the meantime and are left alone.
+    pub fn store_reserved(&mut self, v: &[u8; 16], before: Option<&[u8]>) {
+        let start = self.reserved().start;
+        let variables = v
+            .iter()
+            .enumerate()
+            .map(|(n, value)| (VARIABLES + n, *value));
+        let stack = self.stack.iter().enumerate().flat_map(|(n, entry)| {
+            let [high, low] = entry.to_be_bytes();
+            let address = Self::stack_address(n);
+            return [(address, high), (address + 1, low)];
+        });
+        let memory = &mut self.memory;
+        for (address, value) in variables.chain(stack) {
+            let untouched = match before {
+                Some(before) => before[address - start] == memory[address],
+                None => true,
+            };
+            if untouched {
+                memory[address] = value;
+            }
         }
-        self.last_program_end = self.last_program_end + len;
     }
 
     // pub fn load_sprite(&mut self, sprite: Vec<u8>) {
@@ -51,11 +166,13 @@ impl RAM {
     //     }
     // }
 
-    fn load_font(&mut self) {
-        let len = FONT.len();
-        for i in 0..len {
-            self.memory[i] = FONT[i];
+    // a font that runs off the top of memory is cut short
+    fn load_font(&mut self, font: &Font) {
+        for (i, byte) in font.bytes().into_iter().enumerate() {
+            if let Some(slot) = self.memory.get_mut(self.font_start + i) {
+                *slot = byte;
+                self.last_sprite_end = self.font_start + i + 1;
+            }
         }
-        self.last_sprite_end = len;
     }
 }
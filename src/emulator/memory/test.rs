@@ -0,0 +1,84 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_load_program() {
+    let mut ram = RAM::new();
+
+    ram.load_program(&[0x12, 0x00]).unwrap();
+    assert_eq!(ram.memory[PROGRAM_START], 0x12);
+    assert_eq!(ram.memory[0], Font::default().small[0]);
+
+    let largest = vec![0xAA; 0x1000 - PROGRAM_START];
+    assert!(ram.load_program(&largest).is_ok());
+
+    let too_large = vec![0xAA; 0x1000 - PROGRAM_START + 1];
+    assert!(matches!(
+        ram.load_program(&too_large),
+        Err(LoadError::TooLarge {
+            size: 0xE01,
+            available: 0xE00
+        })
+    ));
+}
+
+#[test]
+fn test_regions() {
+    let mut ram = RAM::new();
+    assert_eq!(ram.font(), FONT_START..FONT_START + 0x50);
+    assert!(ram.program().is_empty());
+
+    ram.load_program(&[0x12, 0x00, 0x00]).unwrap();
+    assert_eq!(ram.program(), PROGRAM_START..PROGRAM_START + 3);
+}
+
+#[test]
+fn test_layout() {
+    let font = Font::builtin("schip").unwrap();
+    let mut ram = RAM::with_layout(0x600, 0x050, &font);
+    assert_eq!(ram.font(), 0x050..0x050 + font.size());
+    assert_eq!(ram.memory[0x050 + 0x50], font.big.unwrap()[0]);
+
+    ram.load_program(&[0x12, 0x00]).unwrap();
+    assert_eq!(ram.program(), 0x600..0x602);
+    assert_eq!(ram.memory[0x600], 0x12);
+    assert!(matches!(
+        ram.load_program(&vec![0; 0xA01]),
+        Err(LoadError::TooLarge {
+            size: 0xA01,
+            available: 0xA00
+        })
+    ));
+}
+
+#[test]
+fn test_reserved_area() {
+    let mut ram = RAM::new();
+    ram.set_stack_depth(12);
+    assert_eq!(ram.reserved(), 0xEB8..0xF00);
+
+    let mut v = [0; 16];
+    v[3] = 0x42;
+    ram.stack[0] = 0x0246;
+    ram.store_reserved(&v, None);
+    assert_eq!(ram.memory[VARIABLES + 3], 0x42);
+    assert_eq!(&ram.memory[0xECE..0xED0], &[0x02, 0x46]);
+
+    // a byte the program wrote itself wins over the register
+    let before = ram.copy_reserved();
+    ram.memory[VARIABLES + 3] = 0x99;
+    v[3] = 0x43;
+    v[4] = 0x44;
+    ram.store_reserved(&v, Some(&before));
+    let mut fetched = [0; 16];
+    ram.stack[0] = 0;
+    ram.fetch_reserved(&mut fetched);
+    assert_eq!(fetched[3], 0x99);
+    assert_eq!(fetched[4], 0x44);
+    assert_eq!(ram.stack[0], 0x0246);
+
+    // the copy has room for the deepest stack
+    ram.set_stack_depth(MAX_STACK_DEPTH);
+    assert_eq!(ram.reserved().len(), RESERVED_SIZE);
+    assert_eq!(ram.copy_reserved()[RESERVED_SIZE - 16 + 3], 0x99);
+}
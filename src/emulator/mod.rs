@@ -1,39 +1,674 @@
+pub mod coverage;
 mod cpu;
+mod diagnostics;
+mod disassembler;
+mod font;
+mod hooks;
+mod keypad;
 mod memory;
+mod profiler;
+mod quirks;
+mod state;
+mod symbols;
+mod test;
 
-use crate::Message;
-use std::sync::mpsc::Receiver;
+pub use coverage::Coverage;
+pub use cpu::Registers;
+pub use diagnostics::{Diagnostics, Problem, Warning};
+pub use disassembler::{disassemble, disassemble_with};
+pub use font::{Font, FontError};
+pub use hooks::{HookId, Hooks, Machine};
+pub use keypad::Keypad;
+pub use memory::LoadError;
+pub use profiler::Profiler;
+pub use quirks::{Platform, Quirks};
+pub use state::StateError;
+pub use symbols::{SymbolError, Symbols};
+
+use crate::cheat::Cheats;
+use cpu::Step;
+use std::{
+    ops::Range,
+    sync::mpsc::{Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+const FRAME: Duration = Duration::from_micros(16_667);
+// the range Control::Speed is held to, which keeps the frame time sensible
+const MIN_SPEED: f32 = 0.01;
+const MAX_SPEED: f32 = 100.0;
+// the most return addresses any configuration can hold
+pub const MAX_STACK_DEPTH: usize = 64;
+
+pub type Display = [[u8; 128]; 64];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub quirks: Quirks,
+    // instructions executed per 60 Hz frame
+    pub tickrate: u32,
+    // return addresses the stack holds before a call halts the machine
+    pub stack_depth: usize,
+    // keep V0-VF and the stack in memory at 0xEA0-0xEFF, as the VIP did
+    pub memory_stack: bool,
+    // the memory map only changes with the next program loaded
+    pub program_start: usize,
+    pub font_start: usize,
+    pub font: Font,
+}
+
+impl Settings {
+    pub fn for_platform(platform: Platform) -> Self {
+        return Self {
+            quirks: platform.quirks(),
+            tickrate: platform.tickrate(),
+            stack_depth: platform.stack_depth(),
+            memory_stack: false,
+            program_start: memory::PROGRAM_START,
+            font_start: memory::FONT_START,
+            font: platform.font(),
+        };
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        return Self::for_platform(Platform::ModernChip8);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reset {
+    // restart the program but keep whatever it left in memory
+    Soft,
+    // reload the program into freshly initialised memory
+    Hard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(usize),
+    I,
+    ProgramCounter,
+    StackPointer,
+    DelayTimer,
+    SoundTimer,
+    Stack(usize),
+}
+
+impl Register {
+    pub fn max(&self) -> u16 {
+        return match self {
+            Register::V(_) | Register::DelayTimer | Register::SoundTimer => 0xFF,
+            Register::I => 0xFFF,
+            // the opcode at PC is two bytes long
+            Register::ProgramCounter => 0xFFE,
+            Register::StackPointer => MAX_STACK_DEPTH as u16,
+            Register::Stack(_) => 0xFFE,
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum Control {
+    Load(Vec<u8>),
+    // a new build of the running program, keeping the registers if asked
+    Reload(Vec<u8>, bool),
+    Configure(Settings),
+    Pause,
+    Resume,
+    FrameAdvance,
+    Reset(Reset),
+    // multiplier applied to the 60 Hz frame rate, clamped to 0.01-100 with
+    // NaN ignored
+    Speed(f32),
+    // only honoured while paused
+    Poke(usize, u8),
+    SetRegister(Register, u16),
+    SetCheats(Cheats),
+    SetSymbols(Symbols),
+    ShutDown,
+}
+
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub memory: [u8; 0x1000],
+    pub registers: Registers,
+    pub stack: Vec<u16>,
+    pub program: Range<usize>,
+    pub font: Range<usize>,
+    pub paused: bool,
+}
+
+// What the emulator thread reports back to whichever frontend is running it
+#[derive(Debug)]
+pub enum Event {
+    Display(Box<Display>),
+    Snapshot(Box<Snapshot>),
+    // a 60 Hz frame has been emulated, after its Display if it drew anything
+    Frame,
+    // a control couldn't be carried out, such as a program too large to load
+    Error(String),
+}
 
 #[derive(Clone)]
 pub struct Emulator {
     cpu: cpu::CPU,
     memory: memory::RAM,
+    display: Display,
+    keypad: Keypad,
+    settings: Settings,
+    program: Vec<u8>,
+    paused: bool,
+    halted: bool,
+    speed: f32,
+    cheats: Cheats,
+    profiler: Option<Box<Profiler>>,
+    coverage: Option<Box<Coverage>>,
+    diagnostics: Option<Box<Diagnostics>>,
+    // labels and source lines of the loaded program, for the reports
+    symbols: Symbols,
+    // callbacks from tooling outside the core, which a clone doesn't take along
+    hooks: Hooks,
 }
 
+// the batch runner moves emulators onto worker threads, so this must hold
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Emulator>();
+};
+
 impl Emulator {
     pub fn new() -> Self {
         return Self {
             cpu: cpu::CPU::new(),
             memory: memory::RAM::new(),
+            display: [[0; 128]; 64],
+            keypad: Keypad::new(),
+            settings: Settings::default(),
+            program: Vec::new(),
+            paused: false,
+            halted: false,
+            speed: 1.0,
+            cheats: Cheats::default(),
+            profiler: None,
+            coverage: None,
+            diagnostics: None,
+            symbols: Symbols::default(),
+            hooks: Hooks::default(),
+        };
+    }
+
+    pub fn configure(&mut self, settings: Settings) {
+        self.settings = settings;
+        self.cpu.set_quirks(settings.quirks);
+        self.cpu.set_font_start(settings.font_start);
+        self.memory.set_stack_depth(settings.stack_depth);
+        if settings.memory_stack {
+            self.store_reserved();
+        }
+    }
+
+    pub fn settings(&self) -> Settings {
+        return self.settings;
+    }
+
+    // whether the program fits the usual memory map, loaded at 0x200
+    pub fn check_program(program: &[u8]) -> Result<(), LoadError> {
+        return memory::RAM::check_program(program, memory::PROGRAM_START, 0x1000);
+    }
+
+    // Programs can't reach into the interpreter's own memory when the stack
+    // lives there
+    fn program_end(&self) -> usize {
+        if self.settings.memory_stack {
+            return memory::RAM::reserved_start(self.settings.stack_depth);
+        }
+        return 0x1000;
+    }
+
+    // Memory laid out as the settings ask, with nothing loaded yet
+    fn fresh_memory(&self) -> memory::RAM {
+        let settings = &self.settings;
+        let mut memory =
+            memory::RAM::with_layout(settings.program_start, settings.font_start, &settings.font);
+        memory.set_stack_depth(settings.stack_depth);
+        return memory;
+    }
+
+    // The CPU as it is at power on, about to run the program
+    fn fresh_cpu(&self) -> cpu::CPU {
+        let mut cpu = cpu::CPU::new();
+        // the random numbers carry on rather than start over
+        cpu.set_random_state(self.cpu.random_state());
+        cpu.set_quirks(self.settings.quirks);
+        cpu.set_font_start(self.settings.font_start);
+        let mut registers = cpu.registers();
+        registers.program_counter = self.settings.program_start;
+        cpu.set_registers(registers);
+        return cpu;
+    }
+
+    // Makes CXKK repeatable, the same seed giving the same numbers from here
+    pub fn seed(&mut self, seed: u32) {
+        self.cpu.set_random_state(seed);
+    }
+
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), LoadError> {
+        memory::RAM::check_program(program, self.settings.program_start, self.program_end())?;
+        self.cpu = self.fresh_cpu();
+        self.memory = self.fresh_memory();
+        self.memory.load_program(program)?;
+        self.display = [[0; 128]; 64];
+        // a hard reset of the same program keeps adding to its coverage
+        if self.coverage.is_some() && program != self.program {
+            self.coverage = Some(Box::new(Coverage::new(self.memory.program())));
+        }
+        if self.diagnostics.is_some() && program != self.program {
+            self.set_diagnostics(true);
+        }
+        self.program = program.to_vec();
+        self.halted = false;
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.restart();
+        }
+        return Ok(());
+    }
+
+    // Swaps in a new build of the running program. Memory always starts
+    // over, but the registers, stack and screen can carry on from where the
+    // old build left them.
+    pub fn reload_program(
+        &mut self,
+        program: &[u8],
+        keep_registers: bool,
+    ) -> Result<(), LoadError> {
+        if !keep_registers {
+            return self.load_program(program);
+        }
+        let registers = self.cpu.registers();
+        let stack = self.memory.stack.clone();
+        let display = self.display;
+        self.load_program(program)?;
+        self.cpu.set_registers(registers);
+        self.memory.stack = stack;
+        self.display = display;
+        if self.settings.memory_stack {
+            self.store_reserved();
+        }
+        return Ok(());
+    }
+
+    pub fn display(&self) -> &Display {
+        return &self.display;
+    }
+
+    pub fn keypad(&mut self) -> &mut Keypad {
+        return &mut self.keypad;
+    }
+
+    // where tooling outside the core registers its callbacks
+    pub fn hooks(&mut self) -> &mut Hooks {
+        return &mut self.hooks;
+    }
+
+    pub fn memory(&self) -> &[u8; 0x1000] {
+        return &self.memory.memory;
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8; 0x1000] {
+        return &mut self.memory.memory;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        return self.halted;
+    }
+
+    // the buzzer sounds for as long as the sound timer is non-zero
+    pub fn is_beeping(&self) -> bool {
+        return self.cpu.is_beeping();
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        return Snapshot {
+            memory: self.memory.memory,
+            registers: self.cpu.registers(),
+            stack: self.memory.stack.clone(),
+            program: self.memory.program(),
+            font: self.memory.font(),
+            paused: self.paused,
+        };
+    }
+
+    pub fn poke(&mut self, address: usize, value: u8) {
+        if let Some(byte) = self.memory.memory.get_mut(address) {
+            *byte = value;
+        }
+        if self.settings.memory_stack {
+            self.fetch_reserved();
+        }
+    }
+
+    pub fn set_register(&mut self, register: Register, value: u16) {
+        if value > register.max() {
+            return;
+        }
+        let mut registers = self.cpu.registers();
+        match register {
+            Register::V(x) => registers.v[x & 0xF] = value as u8,
+            Register::I => registers.i = value as usize,
+            Register::ProgramCounter => registers.program_counter = value as usize,
+            Register::StackPointer => registers.stack_pointer = value as usize,
+            Register::DelayTimer => registers.delay_timer = value as u8,
+            Register::SoundTimer => registers.sound_timer = value as u8,
+            Register::Stack(n) => {
+                if let Some(entry) = self.memory.stack.get_mut(n) {
+                    *entry = value;
+                }
+            }
+        }
+        self.cpu.set_registers(registers);
+        if self.settings.memory_stack {
+            self.store_reserved();
+        }
+    }
+
+    // applied at the start of every frame until replaced
+    pub fn set_cheats(&mut self, cheats: Cheats) {
+        self.cheats = cheats;
+    }
+
+    pub fn cheats(&self) -> &Cheats {
+        return &self.cheats;
+    }
+
+    // Starts counting every instruction from here on, or stops and throws the
+    // counts away.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler = match enabled {
+            true => Some(Box::new(Profiler::new(self.memory.program().start))),
+            false => None,
+        };
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        return self.profiler.as_deref();
+    }
+
+    // Starts tracking how the loaded program's bytes get used, or stops and
+    // throws the results away.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.coverage = match enabled {
+            true => Some(Box::new(Coverage::new(self.memory.program()))),
+            false => None,
+        };
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        return self.coverage.as_deref();
+    }
+
+    // Starts watching for suspicious memory accesses, or stops and forgets
+    // what was found.
+    pub fn set_diagnostics(&mut self, enabled: bool) {
+        self.diagnostics = match enabled {
+            true => Some(Box::new(Diagnostics::new(
+                self.memory.program(),
+                self.memory.font(),
+            ))),
+            false => None,
         };
     }
 
-    pub fn load_program(&mut self, program: Vec<u8>) {
-        self.memory.load_program(program);
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        return self.diagnostics.as_deref();
+    }
+
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        return &self.symbols;
+    }
+
+    pub fn reset(&mut self, reset: Reset) {
+        match reset {
+            Reset::Soft => {
+                self.cpu = self.fresh_cpu();
+                self.display = [[0; 128]; 64];
+                self.halted = false;
+                if let Some(profiler) = self.profiler.as_mut() {
+                    profiler.restart();
+                }
+            }
+            Reset::Hard => {
+                // the program may no longer fit if the memory map changed
+                let program = self.program.clone();
+                if self.load_program(&program).is_err() {
+                    self.halted = true;
+                }
+            }
+        }
     }
 
     pub fn start(
         &mut self,
-        display: &mut [[u8; 128]; 64],
         keyboard_receiver: Receiver<u16>,
-        sender: &relm4::Sender<Message>,
+        control_receiver: Receiver<Control>,
+        mut emit: impl FnMut(Event),
     ) {
-        self.cpu.run(
+        loop {
+            let frame_start = Instant::now();
+
+            // a paused or halted machine has nothing to do until it is told otherwise
+            let control = if self.paused || self.halted {
+                match control_receiver.recv() {
+                    Ok(control) => Some(control),
+                    Err(_) => return,
+                }
+            } else {
+                match control_receiver.try_recv() {
+                    Ok(control) => Some(control),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            };
+            while let Ok(keys) = keyboard_receiver.try_recv() {
+                self.keypad.set(keys);
+            }
+
+            let shown = self.display;
+            let controlled = control.is_some();
+            let ran = matches!(control, None | Some(Control::FrameAdvance));
+            match control {
+                Some(Control::Load(program)) => {
+                    if let Err(err) = self.load_program(&program) {
+                        emit(Event::Error(format!("failed to load program: {}", err)));
+                        continue;
+                    }
+                }
+                Some(Control::Reload(program, keep_registers)) => {
+                    if let Err(err) = self.reload_program(&program, keep_registers) {
+                        emit(Event::Error(format!("failed to reload program: {}", err)));
+                        continue;
+                    }
+                }
+                Some(Control::Configure(settings)) => {
+                    self.configure(settings);
+                }
+                Some(Control::Pause) => {
+                    self.paused = true;
+                }
+                Some(Control::Resume) => {
+                    self.paused = false;
+                }
+                Some(Control::FrameAdvance) => {
+                    self.run_frame();
+                }
+                Some(Control::Reset(reset)) => {
+                    self.reset(reset);
+                }
+                Some(Control::Speed(speed)) => {
+                    if !speed.is_nan() {
+                        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+                    }
+                }
+                Some(Control::Poke(address, value)) => {
+                    if self.paused {
+                        self.poke(address, value);
+                    }
+                }
+                Some(Control::SetRegister(register, value)) => {
+                    if self.paused {
+                        self.set_register(register, value);
+                    }
+                }
+                Some(Control::SetCheats(cheats)) => {
+                    self.set_cheats(cheats);
+                }
+                Some(Control::SetSymbols(symbols)) => {
+                    self.set_symbols(symbols);
+                }
+                Some(Control::ShutDown) => {
+                    return;
+                }
+                None => {
+                    self.run_frame();
+                }
+            }
+
+            if self.display != shown {
+                emit(Event::Display(Box::new(self.display)));
+            }
+            if ran {
+                emit(Event::Frame);
+            }
+            emit(Event::Snapshot(Box::new(self.snapshot())));
+
+            if !controlled {
+                let frame = FRAME.div_f32(self.speed);
+                if let Some(rest) = frame.checked_sub(frame_start.elapsed()) {
+                    thread::sleep(rest);
+                }
+            }
+        }
+    }
+
+    // Shows the instruction about to run to whichever of the profiler,
+    // coverage tracker and diagnostics are switched on
+    fn trace(&mut self) {
+        if self.profiler.is_none() && self.coverage.is_none() && self.diagnostics.is_none() {
+            return;
+        }
+        let registers = self.cpu.registers();
+        let opcode = self.opcode();
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.record(&registers, opcode, self.memory.stack.len());
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.record(&registers, opcode);
+        }
+        if let Some(diagnostics) = self.diagnostics.as_mut() {
+            diagnostics.record(&registers, opcode);
+        }
+    }
+
+    // the instruction at the program counter
+    fn opcode(&self) -> u16 {
+        let address = self.cpu.registers().program_counter;
+        let memory = &self.memory.memory;
+        return (memory[address % 0x1000] as u16) << 8 | memory[(address + 1) % 0x1000] as u16;
+    }
+
+    // What hooks get to see
+    fn machine(&self) -> Machine<'_> {
+        return Machine {
+            registers: self.cpu.registers(),
+            stack: &self.memory.stack,
+            memory: &self.memory.memory,
+            display: &self.display,
+        };
+    }
+
+    // A step with the hooks told what happens around it
+    fn hooked_step(&mut self) -> Step {
+        let opcode = self.opcode();
+        let beeping = self.cpu.is_beeping();
+        let mut hooks = std::mem::take(&mut self.hooks);
+        let writes = hooks.before(&self.machine(), opcode);
+        let step = self.step();
+        hooks.after(&self.machine(), opcode, writes, beeping);
+        self.hooks = hooks;
+        return step;
+    }
+
+    // With the VIP memory layout the registers live in memory, where the
+    // program can read and overwrite them between instructions. Whatever an
+    // instruction writes there itself takes precedence over the registers.
+    fn step(&mut self) -> Step {
+        if !self.settings.memory_stack {
+            return self.cpu.step(
+                &mut self.memory.memory,
+                &mut self.memory.stack,
+                &mut self.display,
+                &mut self.keypad,
+            );
+        }
+        self.fetch_reserved();
+        let before = self.memory.copy_reserved();
+        let step = self.cpu.step(
             &mut self.memory.memory,
             &mut self.memory.stack,
-            display,
-            &keyboard_receiver,
-            sender,
+            &mut self.display,
+            &mut self.keypad,
         );
+        let registers = self.cpu.registers();
+        self.memory.store_reserved(&registers.v, Some(&before));
+        self.fetch_reserved();
+        return step;
+    }
+
+    fn fetch_reserved(&mut self) {
+        let mut registers = self.cpu.registers();
+        self.memory.fetch_reserved(&mut registers.v);
+        self.cpu.set_registers(registers);
+    }
+
+    fn store_reserved(&mut self) {
+        let registers = self.cpu.registers();
+        self.memory.store_reserved(&registers.v, None);
+    }
+
+    // Runs one 60 Hz frame worth of instructions and ticks the timers
+    pub fn run_frame(&mut self) {
+        if self.halted {
+            return;
+        }
+        self.cheats.apply(&mut self.memory.memory);
+        for _ in 0..self.settings.tickrate {
+            self.trace();
+            let step = match self.hooks.is_empty() {
+                true => self.step(),
+                false => self.hooked_step(),
+            };
+            match step {
+                Step::Continue => {}
+                Step::Vblank => break,
+                Step::Halt => {
+                    self.halted = true;
+                    return;
+                }
+            }
+        }
+        let beeping = self.cpu.is_beeping();
+        self.cpu.tick_timers();
+        self.keypad.end_frame();
+        if !self.hooks.is_empty() {
+            let mut hooks = std::mem::take(&mut self.hooks);
+            hooks.frame(&self.machine(), beeping);
+            self.hooks = hooks;
+        }
     }
 }
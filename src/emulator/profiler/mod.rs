@@ -0,0 +1,246 @@
+mod test;
+
+use super::{Registers, Symbols};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+const MEMORY_SIZE: usize = 0x1000;
+// deeper than the hardware stack allows, so only runaway recursion is cut off
+const MAX_DEPTH: usize = 64;
+
+// The instruction pattern an opcode matches, e.g. 0xD125 is "DXYN". Anything
+// the CPU doesn't implement comes out as "????".
+pub fn class(opcode: u16) -> &'static str {
+    return match opcode {
+        0x0000 => "0000",
+        0x00E0 => "00E0",
+        0x00EE => "00EE",
+        0x1000..=0x1FFF => "1NNN",
+        0x2000..=0x2FFF => "2NNN",
+        0x3000..=0x3FFF => "3XNN",
+        0x4000..=0x4FFF => "4XNN",
+        0x5000..=0x5FFF if opcode & 0xF == 0 => "5XY0",
+        0x6000..=0x6FFF => "6XNN",
+        0x7000..=0x7FFF => "7XNN",
+        0x8000..=0x8FFF => match opcode & 0xF {
+            0x0 => "8XY0",
+            0x1 => "8XY1",
+            0x2 => "8XY2",
+            0x3 => "8XY3",
+            0x4 => "8XY4",
+            0x5 => "8XY5",
+            0x6 => "8XY6",
+            0x7 => "8XY7",
+            0xE => "8XYE",
+            _ => "????",
+        },
+        0x9000..=0x9FFF if opcode & 0xF == 0 => "9XY0",
+        0xA000..=0xAFFF => "ANNN",
+        0xB000..=0xBFFF => "BNNN",
+        0xC000..=0xCFFF => "CXNN",
+        0xD000..=0xDFFF => "DXYN",
+        0xE000..=0xEFFF => match opcode & 0xFF {
+            0x9E => "EX9E",
+            0xA1 => "EXA1",
+            _ => "????",
+        },
+        0xF000..=0xFFFF => match opcode & 0xFF {
+            0x07 => "FX07",
+            0x0A => "FX0A",
+            0x15 => "FX15",
+            0x18 => "FX18",
+            0x1E => "FX1E",
+            0x29 => "FX29",
+            0x33 => "FX33",
+            0x55 => "FX55",
+            0x65 => "FX65",
+            _ => "????",
+        },
+        _ => "????",
+    };
+}
+
+// Counts every instruction executed, by address, by class and by the chain of
+// subroutines that led to it. Each instruction counts as one cycle.
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    entry: usize,
+    counts: Vec<u64>,
+    // the opcode last seen at each address, for the report
+    opcodes: Vec<u16>,
+    classes: BTreeMap<&'static str, u64>,
+    // entry points of the subroutines we're currently inside
+    calls: Vec<usize>,
+    // instructions executed with exactly this call chain
+    stacks: HashMap<Vec<usize>, u64>,
+    total: u64,
+}
+
+impl Profiler {
+    // entry is where the program starts, the root of every call chain
+    pub fn new(entry: usize) -> Self {
+        return Self {
+            entry,
+            counts: vec![0; MEMORY_SIZE],
+            opcodes: vec![0; MEMORY_SIZE],
+            classes: BTreeMap::new(),
+            calls: Vec::new(),
+            stacks: HashMap::new(),
+            total: 0,
+        };
+    }
+
+    // Called with the registers as they are just before opcode executes. A
+    // call with all stack_depth entries in use halts rather than enters.
+    pub fn record(&mut self, registers: &Registers, opcode: u16, stack_depth: usize) {
+        let address = registers.program_counter % MEMORY_SIZE;
+        self.counts[address] += 1;
+        self.opcodes[address] = opcode;
+        *self.classes.entry(class(opcode)).or_default() += 1;
+        // the chain is only copied the first time it's seen
+        match self.stacks.get_mut(&self.calls[..]) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.calls.clone(), 1);
+            }
+        }
+        self.total += 1;
+
+        let overflows = registers.stack_pointer >= stack_depth;
+        match opcode {
+            0x2000..=0x2FFF if !overflows && self.calls.len() < MAX_DEPTH => {
+                self.calls.push((opcode & 0xFFF) as usize);
+            }
+            0x00EE => {
+                self.calls.pop();
+            }
+            _ => {}
+        }
+    }
+
+    // a reset starts over from the top, but keeps the counts
+    pub fn restart(&mut self) {
+        self.calls.clear();
+    }
+
+    pub fn total(&self) -> u64 {
+        return self.total;
+    }
+
+    pub fn count(&self, address: usize) -> u64 {
+        return self.counts.get(address).copied().unwrap_or(0);
+    }
+
+    pub fn class_count(&self, class: &str) -> u64 {
+        return self.classes.get(class).copied().unwrap_or(0);
+    }
+
+    // Instructions spent in each subroutine itself and including whatever it
+    // called, keyed by entry point. Recursion only counts once towards the
+    // inclusive figure.
+    pub fn subroutines(&self) -> BTreeMap<usize, (u64, u64)> {
+        let mut subroutines: BTreeMap<usize, (u64, u64)> = BTreeMap::new();
+        for (stack, count) in self.stacks.iter() {
+            let mut seen: Vec<usize> = Vec::new();
+            for entry in stack.iter() {
+                if !seen.contains(entry) {
+                    subroutines.entry(*entry).or_default().1 += count;
+                    seen.push(*entry);
+                }
+            }
+            if let Some(entry) = stack.last() {
+                subroutines.entry(*entry).or_default().0 += count;
+            }
+        }
+        return subroutines;
+    }
+
+    // One "main_200;sub_2A0;sub_2F4 1234" line per call chain, the format
+    // flamegraph.pl and inferno read. Labelled entry points go by their label.
+    pub fn folded(&self, symbols: &Symbols) -> String {
+        let frame = |prefix: &str, entry: usize| -> String {
+            return match symbols.label(entry) {
+                Some(label) => label.to_string(),
+                None => format!("{}_{:03X}", prefix, entry),
+            };
+        };
+        let mut lines: Vec<String> = self
+            .stacks
+            .iter()
+            .map(|(stack, count)| {
+                let mut line = frame("main", self.entry);
+                for entry in stack.iter() {
+                    line.push(';');
+                    line.push_str(&frame("sub", *entry));
+                }
+                return format!("{} {}", line, count);
+            })
+            .collect();
+        lines.sort();
+        let mut text = lines.join("\n");
+        text.push('\n');
+        return text;
+    }
+
+    // The busiest addresses, opcode classes and subroutines, most first.
+    // With symbols, addresses are followed by their label and source line.
+    pub fn report(&self, top: usize, symbols: &Symbols) -> String {
+        let share = |count: u64| count as f64 * 100.0 / self.total.max(1) as f64;
+        let place = |address: usize| -> String {
+            if symbols.is_empty() {
+                return String::new();
+            }
+            return match symbols.line(address) {
+                Some(line) => format!("  {} ({})", symbols.name(address), line),
+                None => format!("  {}", symbols.name(address)),
+            };
+        };
+        let mut text = String::new();
+        let _ = writeln!(text, "{} instructions executed", self.total);
+
+        let mut hotspots: Vec<usize> = (0..MEMORY_SIZE).filter(|a| self.counts[*a] > 0).collect();
+        hotspots.sort_by(|a, b| self.counts[*b].cmp(&self.counts[*a]).then(a.cmp(b)));
+        let _ = writeln!(text, "\nHotspots\n  address  opcode       count  share");
+        for address in hotspots.iter().take(top) {
+            let count = self.counts[*address];
+            let _ = writeln!(
+                text,
+                "  {:03X}      {:04X}  {:>12}  {:5.1}%{}",
+                address,
+                self.opcodes[*address],
+                count,
+                share(count),
+                place(*address)
+            );
+        }
+
+        let mut classes: Vec<(&&str, &u64)> = self.classes.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let _ = writeln!(text, "\nOpcode classes\n  class          count  share");
+        for (class, count) in classes.iter().take(top) {
+            let _ = writeln!(text, "  {}  {:>13}  {:5.1}%", class, count, share(**count));
+        }
+
+        let mut subroutines: Vec<(usize, (u64, u64))> = self.subroutines().into_iter().collect();
+        subroutines.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(&b.0)));
+        let _ = writeln!(
+            text,
+            "\nSubroutines\n  entry           self   share      inclusive   share"
+        );
+        for (entry, (own, inclusive)) in subroutines.iter().take(top) {
+            let _ = writeln!(
+                text,
+                "  {:03X}    {:>12}  {:5.1}%   {:>12}  {:5.1}%{}",
+                entry,
+                own,
+                share(*own),
+                inclusive,
+                share(*inclusive),
+                place(*entry)
+            );
+        }
+        return text;
+    }
+}
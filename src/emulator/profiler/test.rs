@@ -0,0 +1,75 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_class() {
+    assert_eq!(class(0xD125), "DXYN");
+    assert_eq!(class(0x8AB6), "8XY6");
+    assert_eq!(class(0x8AB9), "????");
+    assert_eq!(class(0xF265), "FX65");
+    assert_eq!(class(0x5121), "????");
+}
+
+// records opcode at address with a return address on the stack for every
+// call the profiler is inside
+#[cfg(test)]
+fn record(profiler: &mut Profiler, address: usize, opcode: u16, stack_depth: usize) {
+    let registers = Registers {
+        v: [0; 16],
+        program_counter: address,
+        i: 0,
+        stack_pointer: profiler.calls.len(),
+        delay_timer: 0,
+        sound_timer: 0,
+    };
+    profiler.record(&registers, opcode, stack_depth);
+}
+
+#[test]
+fn test_profiler_call_graph() {
+    let mut profiler = Profiler::new(0x200);
+    // main calls 0x300 twice, which calls 0x400 once in between
+    record(&mut profiler, 0x200, 0x2300, 16);
+    record(&mut profiler, 0x300, 0x6001, 16);
+    record(&mut profiler, 0x302, 0x2400, 16);
+    record(&mut profiler, 0x400, 0x00EE, 16);
+    record(&mut profiler, 0x304, 0x00EE, 16);
+    record(&mut profiler, 0x202, 0x2300, 16);
+    record(&mut profiler, 0x300, 0x6001, 16);
+
+    assert_eq!(profiler.total(), 7);
+    assert_eq!(profiler.count(0x300), 2);
+    assert_eq!(profiler.class_count("2NNN"), 3);
+    let subroutines = profiler.subroutines();
+    assert_eq!(subroutines[&0x300], (4, 5));
+    assert_eq!(subroutines[&0x400], (1, 1));
+    assert_eq!(
+        profiler.folded(&Symbols::default()),
+        "main_200 2\nmain_200;sub_300 4\nmain_200;sub_300;sub_400 1\n"
+    );
+    assert!(profiler
+        .report(5, &Symbols::default())
+        .contains("7 instructions executed"));
+
+    let mut symbols = Symbols::parse("start 0x200\ndraw 0x300").unwrap();
+    symbols.add_source_map("0x302 game.8o:7").unwrap();
+    assert_eq!(
+        profiler.folded(&symbols),
+        "start 2\nstart;draw 4\nstart;draw;sub_400 1\n"
+    );
+    let report = profiler.report(5, &symbols);
+    assert!(report.contains("  302      2400             1   14.3%  draw+2 (game.8o:7)"));
+}
+
+#[test]
+fn test_profiler_stack_overflow() {
+    let mut profiler = Profiler::new(0x200);
+    // the second call finds the only stack entry taken and halts
+    record(&mut profiler, 0x200, 0x2300, 1);
+    record(&mut profiler, 0x300, 0x2400, 1);
+    record(&mut profiler, 0x302, 0x6001, 1);
+
+    let subroutines = profiler.subroutines();
+    assert_eq!(subroutines[&0x300], (2, 2));
+    assert!(!subroutines.contains_key(&0x400));
+}
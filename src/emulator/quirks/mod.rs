@@ -0,0 +1,127 @@
+use super::Font;
+
+// Quirk names follow the community chip-8-database so its profiles apply as-is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6 / 8XYE shift VX in place instead of shifting VY into VX
+    pub shift: bool,
+    // FX55 / FX65 advance I by X instead of X + 1
+    pub memory_increment_by_x: bool,
+    // FX55 / FX65 leave I untouched
+    pub memory_leave_i_unchanged: bool,
+    // sprites wrap around the screen edges instead of being clipped
+    pub wrap: bool,
+    // BNNN jumps to XNN + VX instead of NNN + V0
+    pub jump: bool,
+    // DXYN waits for the next frame before drawing
+    pub vblank: bool,
+    // 8XY1 / 8XY2 / 8XY3 reset VF
+    pub logic: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    OriginalChip8,
+    HybridVip,
+    ModernChip8,
+    Chip48,
+    SuperChip1,
+    SuperChip,
+    XoChip,
+}
+
+const PLATFORMS: [Platform; 7] = [
+    Platform::OriginalChip8,
+    Platform::HybridVip,
+    Platform::ModernChip8,
+    Platform::Chip48,
+    Platform::SuperChip1,
+    Platform::SuperChip,
+    Platform::XoChip,
+];
+
+impl Platform {
+    pub fn id(&self) -> &'static str {
+        return match self {
+            Platform::OriginalChip8 => "originalChip8",
+            Platform::HybridVip => "hybridVIP",
+            Platform::ModernChip8 => "modernChip8",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip1 => "superchip1",
+            Platform::SuperChip => "superchip",
+            Platform::XoChip => "xochip",
+        };
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        return PLATFORMS.iter().copied().find(|p| p.id() == id);
+    }
+
+    pub fn tickrate(&self) -> u32 {
+        return match self {
+            Platform::OriginalChip8 | Platform::HybridVip => 15,
+            Platform::ModernChip8 => 12,
+            Platform::Chip48 | Platform::SuperChip1 | Platform::SuperChip => 30,
+            Platform::XoChip => 100,
+        };
+    }
+
+    // the VIP interpreter only set aside room for 12 return addresses
+    pub fn stack_depth(&self) -> usize {
+        return match self {
+            Platform::OriginalChip8 | Platform::HybridVip => 12,
+            _ => 16,
+        };
+    }
+
+    pub fn font(&self) -> Font {
+        let name = match self {
+            Platform::OriginalChip8 | Platform::HybridVip => "vip",
+            Platform::ModernChip8 | Platform::Chip48 => "modern",
+            Platform::SuperChip1 | Platform::SuperChip | Platform::XoChip => "schip",
+        };
+        return Font::builtin(name).expect("platform fonts are builtin");
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        let vip = Quirks {
+            shift: false,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: false,
+            wrap: false,
+            jump: false,
+            vblank: true,
+            logic: true,
+        };
+        return match self {
+            Platform::OriginalChip8 | Platform::HybridVip => vip,
+            Platform::ModernChip8 => Quirks {
+                vblank: false,
+                logic: false,
+                ..vip
+            },
+            Platform::Chip48 => Quirks {
+                shift: true,
+                memory_increment_by_x: true,
+                jump: true,
+                vblank: false,
+                logic: false,
+                ..vip
+            },
+            Platform::SuperChip1 | Platform::SuperChip => Quirks {
+                shift: true,
+                memory_leave_i_unchanged: true,
+                jump: true,
+                vblank: false,
+                logic: false,
+                ..vip
+            },
+            Platform::XoChip => Quirks {
+                wrap: true,
+                vblank: false,
+                logic: false,
+                ..vip
+            },
+        };
+    }
+}
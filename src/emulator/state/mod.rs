@@ -0,0 +1,268 @@
+mod test;
+
+use super::{
+    cpu::Registers,
+    font::{BIG_SIZE, SMALL_SIZE},
+    memory, Emulator, Font, Quirks, Settings, MAX_STACK_DEPTH,
+};
+use std::fmt;
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateError {
+    NotAState,
+    UnsupportedVersion(u8),
+    Truncated,
+    ProgramTooLarge,
+    // a program counter or stack pointer the machine could never have had
+    InvalidRegisters,
+    // a stack depth or memory map no configuration allows
+    InvalidSettings,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            StateError::NotAState => write!(f, "not a CHIP-8 save state"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save state version {}", version)
+            }
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::ProgramTooLarge => write!(f, "save state program does not fit in memory"),
+            StateError::InvalidRegisters => write!(f, "save state registers are out of range"),
+            StateError::InvalidSettings => write!(f, "save state settings are out of range"),
+        };
+    }
+}
+
+impl std::error::Error for StateError {}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        return Ok(taken);
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.take(2)?;
+        return Ok(u16::from_le_bytes([bytes[0], bytes[1]]));
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.take(4)?;
+        return Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+}
+
+fn quirk_bits(quirks: &Quirks) -> u8 {
+    let flags = [
+        quirks.shift,
+        quirks.memory_increment_by_x,
+        quirks.memory_leave_i_unchanged,
+        quirks.wrap,
+        quirks.jump,
+        quirks.vblank,
+        quirks.logic,
+    ];
+    let mut bits = 0;
+    for (n, flag) in flags.iter().enumerate() {
+        bits |= (*flag as u8) << n;
+    }
+    return bits;
+}
+
+fn quirks_from_bits(bits: u8) -> Quirks {
+    let flag = |n: u8| bits >> n & 1 == 1;
+    return Quirks {
+        shift: flag(0),
+        memory_increment_by_x: flag(1),
+        memory_leave_i_unchanged: flag(2),
+        wrap: flag(3),
+        jump: flag(4),
+        vblank: flag(5),
+        logic: flag(6),
+    };
+}
+
+// Save states hold the whole machine plus the loaded program, so a hard reset
+// after loading one still has something to reload. The layout is little endian
+// and only grows new fields behind a new version number.
+impl Emulator {
+    pub fn save_state(&self) -> Vec<u8> {
+        let registers = self.cpu.registers();
+        let mut state = Vec::with_capacity(self.state_size());
+        state.extend_from_slice(MAGIC);
+        state.push(VERSION);
+
+        state.push(quirk_bits(&self.settings.quirks));
+        state.extend_from_slice(&self.settings.tickrate.to_le_bytes());
+        state.push(self.settings.stack_depth as u8);
+        state.push(self.settings.memory_stack as u8);
+        state.extend_from_slice(&(self.settings.program_start as u16).to_le_bytes());
+        state.extend_from_slice(&(self.settings.font_start as u16).to_le_bytes());
+        state.extend_from_slice(&self.settings.font.small);
+        match self.settings.font.big {
+            Some(big) => {
+                state.push(1);
+                state.extend_from_slice(&big);
+            }
+            None => state.push(0),
+        }
+
+        state.extend_from_slice(&registers.v);
+        state.extend_from_slice(&(registers.program_counter as u16).to_le_bytes());
+        state.extend_from_slice(&(registers.i as u16).to_le_bytes());
+        state.push(registers.stack_pointer as u8);
+        state.push(registers.delay_timer);
+        state.push(registers.sound_timer);
+        state.push(self.halted as u8);
+        state.extend_from_slice(&self.cpu.random_state().to_le_bytes());
+
+        for address in self.memory.stack.iter() {
+            state.extend_from_slice(&address.to_le_bytes());
+        }
+        state.extend_from_slice(&self.memory.memory);
+        // eight pixels to a byte
+        for row in self.display.iter() {
+            for pixels in row.chunks(8) {
+                let mut byte = 0;
+                for pixel in pixels.iter() {
+                    byte = byte << 1 | (*pixel & 1);
+                }
+                state.push(byte);
+            }
+        }
+
+        state.extend_from_slice(&(self.program.len() as u16).to_le_bytes());
+        state.extend_from_slice(&self.program);
+        return state;
+    }
+
+    pub fn state_size(&self) -> usize {
+        return 4
+            + 1
+            + 1
+            + 4
+            + 2
+            + 2
+            + 2
+            + self.settings.font.size()
+            + 1
+            + 16
+            + 2
+            + 2
+            + 4
+            + 4
+            + self.memory.stack.len() * 2
+            + 0x1000
+            + 128 * 64 / 8
+            + 2
+            + self.program.len();
+    }
+
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut reader = Reader { data: state };
+        if reader.take(4)? != MAGIC {
+            return Err(StateError::NotAState);
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let quirks = quirks_from_bits(reader.u8()?);
+        let tickrate = reader.u32()?;
+        let stack_depth = reader.u8()? as usize;
+        let memory_stack = reader.u8()? != 0;
+        let program_start = reader.u16()? as usize;
+        let font_start = reader.u16()? as usize;
+        let mut font =
+            Font::from_bytes(reader.take(SMALL_SIZE)?).expect("the small digits alone make a font");
+        if reader.u8()? != 0 {
+            let mut big = [0; BIG_SIZE];
+            big.copy_from_slice(reader.take(BIG_SIZE)?);
+            font.big = Some(big);
+        }
+        let settings = Settings {
+            quirks,
+            tickrate,
+            stack_depth,
+            memory_stack,
+            program_start,
+            font_start,
+            font,
+        };
+        if !(1..=MAX_STACK_DEPTH).contains(&settings.stack_depth)
+            || settings.program_start >= 0x1000
+            || settings.font_start + settings.font.size() > 0x1000
+        {
+            return Err(StateError::InvalidSettings);
+        }
+        let mut registers = Registers {
+            v: [0; 16],
+            program_counter: 0,
+            i: 0,
+            stack_pointer: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+        };
+        registers.v.copy_from_slice(reader.take(16)?);
+        registers.program_counter = reader.u16()? as usize;
+        registers.i = reader.u16()? as usize;
+        registers.stack_pointer = reader.u8()? as usize;
+        registers.delay_timer = reader.u8()?;
+        registers.sound_timer = reader.u8()?;
+        let halted = reader.u8()? != 0;
+        let random = reader.u32()?;
+        if registers.program_counter >= 0x1000 || registers.stack_pointer > settings.stack_depth {
+            return Err(StateError::InvalidRegisters);
+        }
+
+        let mut stack = vec![0; settings.stack_depth];
+        for address in stack.iter_mut() {
+            *address = reader.u16()?;
+        }
+        let ram = reader.take(0x1000)?;
+        let mut display = [[0; 128]; 64];
+        for row in display.iter_mut() {
+            let bytes = reader.take(128 / 8)?;
+            for (col, pixel) in row.iter_mut().enumerate() {
+                *pixel = bytes[col / 8] >> (7 - col % 8) & 1;
+            }
+        }
+        let program_len = reader.u16()? as usize;
+        let program = reader.take(program_len)?.to_vec();
+
+        // nothing is touched until the whole state has been read
+        let mut memory =
+            memory::RAM::with_layout(settings.program_start, settings.font_start, &settings.font);
+        memory
+            .load_program(&program)
+            .map_err(|_| StateError::ProgramTooLarge)?;
+        memory.memory.copy_from_slice(ram);
+        memory.stack = stack;
+
+        self.memory = memory;
+        self.cpu.set_registers(registers);
+        self.cpu.set_random_state(random);
+        self.configure(settings);
+        self.display = display;
+        self.program = program;
+        self.halted = halted;
+        return Ok(());
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+use super::*;
+#[cfg(test)]
+use crate::emulator::Platform;
+
+#[test]
+fn test_state_round_trip() {
+    let mut emulator = Emulator::new();
+    emulator.configure(Settings::for_platform(Platform::SuperChip));
+    // CLS; LD I, 0; DRW V0, V0, 5; JP 0x206
+    let program = [0x00, 0xE0, 0xA0, 0x00, 0xD0, 0x05, 0x12, 0x06];
+    emulator.load_program(&program).unwrap();
+    emulator.run_frame();
+
+    let state = emulator.save_state();
+    assert_eq!(state.len(), emulator.state_size());
+
+    let mut restored = Emulator::new();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.display(), emulator.display());
+    assert_eq!(restored.snapshot().registers, emulator.snapshot().registers);
+    assert_eq!(restored.settings, emulator.settings);
+    assert_eq!(restored.program, program);
+    assert_eq!(restored.save_state(), state);
+}
+
+#[test]
+fn test_state_errors() {
+    let mut emulator = Emulator::new();
+    let mut state = emulator.save_state();
+    assert_eq!(
+        emulator.load_state(&state[..state.len() - 1]),
+        Err(StateError::Truncated)
+    );
+    state[4] = 99;
+    assert_eq!(
+        emulator.load_state(&state),
+        Err(StateError::UnsupportedVersion(99))
+    );
+    // there is only the one layout
+    state[4] = VERSION + 1;
+    assert!(emulator.load_state(&state).is_err());
+    assert_eq!(emulator.load_state(b"nope"), Err(StateError::NotAState));
+
+    // the stack pointer sits after the magic, version, settings, V0-VF, PC and I
+    let mut state = emulator.save_state();
+    state[117] = 17;
+    assert_eq!(
+        emulator.load_state(&state),
+        Err(StateError::InvalidRegisters)
+    );
+
+    // the stack depth follows the tickrate
+    let mut state = emulator.save_state();
+    state[10] = 0;
+    assert_eq!(
+        emulator.load_state(&state),
+        Err(StateError::InvalidSettings)
+    );
+}
+
+#[test]
+fn test_state_memory_stack() {
+    let mut emulator = Emulator::new();
+    let mut settings = Settings::for_platform(Platform::OriginalChip8);
+    settings.memory_stack = true;
+    emulator.configure(settings);
+    // CALL 0x204; JP 0x202; LD V0, 0x17; LD I, 0xEF5; LD [I], V0; RET
+    // which stores V0 over where V5 lives
+    let program = [
+        0x22, 0x04, 0x12, 0x02, 0x60, 0x17, 0xAE, 0xF5, 0xF0, 0x55, 0x00, 0xEE,
+    ];
+    emulator.load_program(&program).unwrap();
+    emulator.run_frame();
+
+    let snapshot = emulator.snapshot();
+    assert_eq!(snapshot.stack.len(), 12);
+    assert_eq!(snapshot.registers.v[5], 0x17);
+    // the return address sits at the top of the VIP stack
+    assert_eq!(&snapshot.memory[0xECE..0xED0], &[0x02, 0x02]);
+
+    let state = emulator.save_state();
+    assert_eq!(state.len(), emulator.state_size());
+    let mut restored = Emulator::new();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.settings, settings);
+    assert_eq!(restored.save_state(), state);
+}
+
+#[test]
+fn test_state_memory_map() {
+    let mut emulator = Emulator::new();
+    let settings = Settings {
+        program_start: 0x600,
+        font_start: 0x050,
+        font: Font::builtin("dream6800").unwrap(),
+        ..Settings::default()
+    };
+    emulator.configure(settings);
+    // LD V0, 0x0A; LD F, V0; JP 0x604
+    emulator
+        .load_program(&[0x60, 0x0A, 0xF0, 0x29, 0x16, 0x04])
+        .unwrap();
+    emulator.run_frame();
+
+    let snapshot = emulator.snapshot();
+    assert_eq!(snapshot.program, 0x600..0x606);
+    assert_eq!(snapshot.font, 0x050..0x0A0);
+    assert_eq!(snapshot.registers.i, 0x050 + 0xA * 5);
+    assert_eq!(snapshot.memory[0x050 + 5], 0x40);
+
+    let state = emulator.save_state();
+    let mut restored = Emulator::new();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.settings, settings);
+    assert_eq!(restored.snapshot().font, snapshot.font);
+    assert_eq!(restored.save_state(), state);
+}
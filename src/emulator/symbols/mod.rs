@@ -0,0 +1,153 @@
+mod test;
+
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+#[derive(Debug)]
+pub enum SymbolError {
+    Io(io::Error),
+    // the line of the file that could not be read
+    Invalid(usize),
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SymbolError::Io(err) => write!(f, "{}", err),
+            SymbolError::Invalid(line) => {
+                write!(f, "line {}: expected a name and an address", line)
+            }
+        };
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+impl From<io::Error> for SymbolError {
+    fn from(err: io::Error) -> Self {
+        return SymbolError::Io(err);
+    }
+}
+
+// Octo writes numbers as 0x.., 0b.. or plain decimal
+fn parse_address(text: &str) -> Option<usize> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return usize::from_str_radix(hex, 16).ok();
+    }
+    if let Some(binary) = text.strip_prefix("0b") {
+        return usize::from_str_radix(binary, 2).ok();
+    }
+    return text.parse().ok();
+}
+
+// Where a program's labels are and which source lines its instructions came
+// from, so that reports can say "loop+4" or "game.8o:12" instead of 2A4.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Symbols {
+    labels: BTreeMap<usize, String>,
+    lines: BTreeMap<usize, (String, usize)>,
+}
+
+impl Symbols {
+    // One label per line, as Octo's ":const name 0x2A4" symbol output, or
+    // just a name and an address either way round. '#' starts a comment.
+    pub fn parse(text: &str) -> Result<Self, SymbolError> {
+        let mut symbols = Self::default();
+        symbols.add_labels(text)?;
+        return Ok(symbols);
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SymbolError> {
+        return Self::parse(&fs::read_to_string(path)?);
+    }
+
+    pub fn add_labels(&mut self, text: &str) -> Result<(), SymbolError> {
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut words: Vec<&str> = line.split_whitespace().filter(|w| *w != "=").collect();
+            if words.first() == Some(&":const") {
+                words.remove(0);
+            }
+            let label = match words[..] {
+                [] => continue,
+                [name, value] => match (parse_address(value), parse_address(name)) {
+                    (Some(address), None) => (name, address),
+                    (None, Some(address)) => (value, address),
+                    _ => return Err(SymbolError::Invalid(n + 1)),
+                },
+                _ => return Err(SymbolError::Invalid(n + 1)),
+            };
+            self.insert_label(label.0, label.1);
+        }
+        return Ok(());
+    }
+
+    // One "0x2A4 game.8o:12" line per instruction
+    pub fn add_source_map(&mut self, text: &str) -> Result<(), SymbolError> {
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = line
+                .split_once(char::is_whitespace)
+                .and_then(|(address, place)| {
+                    let (file, number) = place.trim().rsplit_once(':')?;
+                    return Some((parse_address(address)?, file, number.parse().ok()?));
+                });
+            let Some((address, file, number)) = entry else {
+                return Err(SymbolError::Invalid(n + 1));
+            };
+            self.insert_line(address, file, number);
+        }
+        return Ok(());
+    }
+
+    pub fn load_source_map(&mut self, path: &Path) -> Result<(), SymbolError> {
+        return self.add_source_map(&fs::read_to_string(path)?);
+    }
+
+    // the first name given to an address is the one shown
+    pub fn insert_label(&mut self, name: &str, address: usize) {
+        self.labels
+            .entry(address)
+            .or_insert_with(|| name.to_string());
+    }
+
+    pub fn insert_line(&mut self, address: usize, file: &str, line: usize) {
+        self.lines.insert(address, (file.to_string(), line));
+    }
+
+    // the other's labels and lines, without renaming any of ours
+    pub fn merge(&mut self, other: &Symbols) {
+        for (address, name) in other.labels.iter() {
+            self.insert_label(name, *address);
+        }
+        for (address, line) in other.lines.iter() {
+            self.lines.entry(*address).or_insert_with(|| line.clone());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.labels.is_empty() && self.lines.is_empty();
+    }
+
+    pub fn label(&self, address: usize) -> Option<&str> {
+        return self.labels.get(&address).map(String::as_str);
+    }
+
+    // The nearest label at or before an address, with the distance past it,
+    // or the bare address when nothing comes before it
+    pub fn name(&self, address: usize) -> String {
+        return match self.labels.range(..=address).next_back() {
+            Some((start, name)) if *start == address => name.clone(),
+            Some((start, name)) => format!("{}+{}", name, address - start),
+            None => format!("{:03X}", address),
+        };
+    }
+
+    // "game.8o:12" for an instruction the source map knows about
+    pub fn line(&self, address: usize) -> Option<String> {
+        let (file, line) = self.lines.get(&address)?;
+        return Some(format!("{}:{}", file, line));
+    }
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_parse_labels() {
+    let symbols = Symbols::parse(
+        "# from octo\n:const main 0x200\n:const loop 0x20A\nsprite = 0x300\n0x310 tiles\n",
+    )
+    .unwrap();
+    assert_eq!(symbols.label(0x200), Some("main"));
+    assert_eq!(symbols.label(0x300), Some("sprite"));
+    assert_eq!(symbols.label(0x310), Some("tiles"));
+    assert_eq!(symbols.name(0x20A), "loop");
+    assert_eq!(symbols.name(0x20E), "loop+4");
+    assert_eq!(symbols.name(0x1FE), "1FE");
+
+    assert!(matches!(
+        Symbols::parse("main 0x200\nnothing here at all\n"),
+        Err(SymbolError::Invalid(2))
+    ));
+}
+
+#[test]
+fn test_source_map() {
+    let mut symbols = Symbols::default();
+    symbols
+        .add_source_map("0x200 game.8o:3\n0x202 C:\\games\\game.8o:4\n")
+        .unwrap();
+    assert_eq!(symbols.line(0x200).as_deref(), Some("game.8o:3"));
+    assert_eq!(symbols.line(0x202).as_deref(), Some("C:\\games\\game.8o:4"));
+    assert_eq!(symbols.line(0x204), None);
+    assert!(matches!(
+        symbols.add_source_map("0x204 game.8o\n"),
+        Err(SymbolError::Invalid(1))
+    ));
+}
+
+#[test]
+fn test_merge() {
+    let mut symbols = Symbols::parse("main 0x200").unwrap();
+    let other = Symbols::parse("start 0x200\nloop 0x204").unwrap();
+    symbols.merge(&other);
+    assert_eq!(symbols.label(0x200), Some("main"));
+    assert_eq!(symbols.label(0x204), Some("loop"));
+}
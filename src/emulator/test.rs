@@ -0,0 +1,91 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_reload_program() {
+    let mut emulator = Emulator::new();
+    // LD V0, 5; CALL 0x206; JP 0x206 (looping one level down the stack)
+    let program = [0x60, 0x05, 0x22, 0x06, 0x00, 0x00, 0x12, 0x06];
+    emulator.load_program(&program).unwrap();
+    emulator.run_frame();
+    emulator.memory_mut()[0x300] = 0xAA;
+    let running = emulator.snapshot();
+
+    // the same build with a different byte at the end
+    let mut rebuilt = program.to_vec();
+    rebuilt.extend_from_slice(&[0x00, 0x01]);
+    emulator.reload_program(&rebuilt, true).unwrap();
+    let reloaded = emulator.snapshot();
+    assert_eq!(reloaded.registers, running.registers);
+    assert_eq!(reloaded.stack, running.stack);
+    assert_eq!(emulator.memory()[0x300], 0);
+    assert_eq!(emulator.memory()[0x208..0x20A], [0x00, 0x01]);
+
+    emulator.reload_program(&program, false).unwrap();
+    let registers = emulator.snapshot().registers;
+    assert_eq!(registers.program_counter, 0x200);
+    assert_eq!(registers.v[0], 0);
+    assert_eq!(registers.stack_pointer, 0);
+}
+
+#[test]
+fn test_reload_program_memory_stack() {
+    let mut emulator = Emulator::new();
+    emulator.configure(Settings {
+        memory_stack: true,
+        ..Settings::default()
+    });
+    // LD V3, 7; JP 0x202
+    let program = [0x63, 0x07, 0x12, 0x02];
+    emulator.load_program(&program).unwrap();
+    emulator.run_frame();
+
+    // the registers are written back into the freshly cleared memory
+    emulator.reload_program(&program, true).unwrap();
+    assert_eq!(emulator.memory()[memory::VARIABLES + 3], 7);
+}
+
+#[test]
+fn test_memory_stack_program_end() {
+    let mut emulator = Emulator::new();
+    emulator.configure(Settings {
+        memory_stack: true,
+        ..Settings::default()
+    });
+    let fits = vec![0; memory::RESERVED_START - memory::PROGRAM_START];
+    assert!(emulator.load_program(&fits).is_ok());
+    assert!(matches!(
+        emulator.load_program(&vec![0; fits.len() + 1]),
+        Err(LoadError::TooLarge { available, .. }) if available == fits.len()
+    ));
+
+    // a deeper stack than the VIP's takes more room
+    emulator.configure(Settings {
+        memory_stack: true,
+        stack_depth: MAX_STACK_DEPTH,
+        ..Settings::default()
+    });
+    assert!(emulator.load_program(&fits).is_err());
+}
+
+#[test]
+fn test_start_rejects_bad_controls() {
+    let (_keyboard_sender, keyboard_receiver) = std::sync::mpsc::channel();
+    let (control_sender, control_receiver) = std::sync::mpsc::channel();
+    control_sender.send(Control::Pause).unwrap();
+    control_sender.send(Control::Load(vec![0; 0x1000])).unwrap();
+    control_sender.send(Control::Speed(0.0)).unwrap();
+    control_sender.send(Control::Speed(f32::NAN)).unwrap();
+    control_sender.send(Control::ShutDown).unwrap();
+
+    let mut emulator = Emulator::new();
+    let mut errors = Vec::new();
+    emulator.start(keyboard_receiver, control_receiver, |event| {
+        if let Event::Error(message) = event {
+            errors.push(message);
+        }
+    });
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("failed to load program"));
+    assert_eq!(emulator.speed, MIN_SPEED);
+}
@@ -0,0 +1,238 @@
+mod test;
+
+use crate::emulator::{Emulator, Reset};
+use std::slice;
+
+/// Bumped whenever a function's signature or behaviour changes.
+pub const CHIP8_API_VERSION: u32 = 1;
+/// Framebuffer width in pixels, also the length of a framebuffer row.
+pub const CHIP8_WIDTH: usize = 128;
+/// Framebuffer height in pixels.
+pub const CHIP8_HEIGHT: usize = 64;
+
+/// An emulator instance. Only ever handled through a pointer.
+pub struct Chip8 {
+    emulator: Emulator,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Result {
+    Ok = 0,
+    NullPointer,
+    RomTooLarge,
+    BufferTooSmall,
+    InvalidState,
+}
+
+/// Returns CHIP8_API_VERSION as compiled into the library.
+#[no_mangle]
+pub extern "C" fn chip8_api_version() -> u32 {
+    return CHIP8_API_VERSION;
+}
+
+/// Creates an emulator with no program loaded. Free it with chip8_free.
+#[no_mangle]
+pub extern "C" fn chip8_new() -> *mut Chip8 {
+    return Box::into_raw(Box::new(Chip8 {
+        emulator: Emulator::new(),
+    }));
+}
+
+/// Destroys an emulator created by chip8_new. Passing NULL does nothing.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a pointer from chip8_new that hasn't been freed yet.
+/// It, and any framebuffer pointer taken from it, is dangling afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Loads a ROM image and resets the machine to run it.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call. data must be NULL or point to len readable
+/// bytes, which are copied and needn't outlive the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    chip8: *mut Chip8,
+    data: *const u8,
+    len: usize,
+) -> Chip8Result {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Result::NullPointer;
+    };
+    if data.is_null() {
+        return Chip8Result::NullPointer;
+    }
+    return match chip8
+        .emulator
+        .load_program(slice::from_raw_parts(data, len))
+    {
+        Ok(()) => Chip8Result::Ok,
+        Err(_) => Chip8Result::RomTooLarge,
+    };
+}
+
+/// Restarts the loaded ROM from a fresh copy of memory.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_reset(chip8: *mut Chip8) -> Chip8Result {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Result::NullPointer;
+    };
+    chip8.emulator.reset(Reset::Hard);
+    return Chip8Result::Ok;
+}
+
+/// Runs the given number of 60 Hz frames as fast as possible.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frames(chip8: *mut Chip8, frames: u32) -> Chip8Result {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Result::NullPointer;
+    };
+    for _ in 0..frames {
+        chip8.emulator.run_frame();
+    }
+    return Chip8Result::Ok;
+}
+
+/// Whether the program has run into a 0000 instruction and stopped.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_is_halted(chip8: *const Chip8) -> bool {
+    return chip8
+        .as_ref()
+        .is_some_and(|chip8| chip8.emulator.is_halted());
+}
+
+/// Returns CHIP8_WIDTH * CHIP8_HEIGHT bytes, one per pixel and row by row,
+/// each 0 or 1. The pointer is valid until the next call that mutates the
+/// emulator.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new. The bytes returned
+/// must not be written to, nor read once chip8 is freed or mutated.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(chip8: *const Chip8) -> *const u8 {
+    return match chip8.as_ref() {
+        Some(chip8) => chip8.emulator.display().as_ptr() as *const u8,
+        None => std::ptr::null(),
+    };
+}
+
+/// Presses or releases one of the sixteen keypad keys (0x0 to 0xF).
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(chip8: *mut Chip8, key: u8, down: bool) -> Chip8Result {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Result::NullPointer;
+    };
+    chip8.emulator.keypad().set_key(key, down);
+    return Chip8Result::Ok;
+}
+
+/// Sets the whole keypad at once, key n being bit 15 - n.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_keys(chip8: *mut Chip8, keys: u16) -> Chip8Result {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Result::NullPointer;
+    };
+    chip8.emulator.keypad().set(keys);
+    return Chip8Result::Ok;
+}
+
+/// The number of bytes chip8_save_state needs for the current ROM.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_state_size(chip8: *const Chip8) -> usize {
+    return chip8
+        .as_ref()
+        .map_or(0, |chip8| chip8.emulator.state_size());
+}
+
+/// Writes a save state into buffer, which must hold chip8_state_size bytes.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call. buffer must be NULL or point to len writable
+/// bytes that don't overlap the emulator.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(
+    chip8: *const Chip8,
+    buffer: *mut u8,
+    len: usize,
+) -> Chip8Result {
+    let Some(chip8) = chip8.as_ref() else {
+        return Chip8Result::NullPointer;
+    };
+    if buffer.is_null() {
+        return Chip8Result::NullPointer;
+    }
+    let state = chip8.emulator.save_state();
+    if len < state.len() {
+        return Chip8Result::BufferTooSmall;
+    }
+    slice::from_raw_parts_mut(buffer, state.len()).copy_from_slice(&state);
+    return Chip8Result::Ok;
+}
+
+/// Restores a state written by chip8_save_state. The emulator is left
+/// untouched if the state is rejected.
+///
+/// # Safety
+///
+/// chip8 must be NULL or a live pointer from chip8_new that no other thread
+/// is using during the call. data must be NULL or point to len readable
+/// bytes, which are copied and needn't outlive the call.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(
+    chip8: *mut Chip8,
+    data: *const u8,
+    len: usize,
+) -> Chip8Result {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Result::NullPointer;
+    };
+    if data.is_null() {
+        return Chip8Result::NullPointer;
+    }
+    return match chip8.emulator.load_state(slice::from_raw_parts(data, len)) {
+        Ok(()) => Chip8Result::Ok,
+        Err(_) => Chip8Result::InvalidState,
+    };
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_ffi_round_trip() {
+    unsafe {
+        let chip8 = chip8_new();
+        // LD V1, 5; LD F, V1; DRW V0, V0, 5; JP 0x206
+        let program: [u8; 8] = [0x61, 0x05, 0xF1, 0x29, 0xD0, 0x05, 0x12, 0x06];
+        assert_eq!(
+            chip8_load_rom(chip8, program.as_ptr(), program.len()),
+            Chip8Result::Ok
+        );
+        assert_eq!(chip8_run_frames(chip8, 2), Chip8Result::Ok);
+        assert!(!chip8_is_halted(chip8));
+
+        let framebuffer =
+            slice::from_raw_parts(chip8_framebuffer(chip8), CHIP8_WIDTH * CHIP8_HEIGHT);
+        // the top row of the digit 5 is four pixels wide
+        assert_eq!(&framebuffer[..5], &[1, 1, 1, 1, 0]);
+        assert_eq!(framebuffer[CHIP8_WIDTH], 1);
+
+        let mut state = vec![0; chip8_state_size(chip8)];
+        assert_eq!(
+            chip8_save_state(chip8, state.as_mut_ptr(), 4),
+            Chip8Result::BufferTooSmall
+        );
+        assert_eq!(
+            chip8_save_state(chip8, state.as_mut_ptr(), state.len()),
+            Chip8Result::Ok
+        );
+
+        let copy = chip8_new();
+        assert_eq!(
+            chip8_load_state(copy, state.as_ptr(), state.len()),
+            Chip8Result::Ok
+        );
+        assert_eq!(*chip8_framebuffer(copy), 1);
+        assert_eq!(
+            chip8_load_state(copy, state.as_ptr(), 3),
+            Chip8Result::InvalidState
+        );
+
+        let too_large = vec![0; 0x1000];
+        assert_eq!(
+            chip8_load_rom(copy, too_large.as_ptr(), too_large.len()),
+            Chip8Result::RomTooLarge
+        );
+        assert_eq!(
+            chip8_set_key(std::ptr::null_mut(), 1, true),
+            Chip8Result::NullPointer
+        );
+
+        chip8_free(chip8);
+        chip8_free(copy);
+    }
+}
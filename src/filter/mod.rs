@@ -0,0 +1,108 @@
+mod test;
+
+use crate::palette::{Color, Palette};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterMode {
+    Off,
+    // keep erased pixels lit, fading out over the given number of frames,
+    // at most 254 of them
+    Decay(u8),
+    // show drawn pixels immediately but only erase them once a frame has ended
+    OrErase,
+}
+
+impl FilterMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        return match mode.trim() {
+            "off" => Some(Self::Off),
+            "decay" => Some(Self::Decay(3)),
+            "or-erase" => Some(Self::OrErase),
+            mode => {
+                let frames = mode.strip_prefix("decay:")?.parse::<u8>().ok()?;
+                // a pixel's age has to be able to pass the number of frames
+                (frames < u8::MAX).then_some(Self::Decay(frames))
+            }
+        };
+    }
+}
+
+// Post-processing applied to what the frontend presents. It only mirrors the
+// emulated display and never writes back to it, so collisions are unaffected.
+pub struct Filter {
+    mode: FilterMode,
+    target: [[u8; 128]; 64],
+    shown: [[u8; 128]; 64],
+    age: [[u8; 128]; 64],
+    dirty: Vec<(usize, usize)>,
+}
+
+impl Filter {
+    pub fn new(mode: FilterMode) -> Self {
+        return Self {
+            mode,
+            target: [[0; 128]; 64],
+            shown: [[0; 128]; 64],
+            age: [[0; 128]; 64],
+            dirty: Vec::new(),
+        };
+    }
+
+    pub fn draw(&mut self, row: usize, col: usize, bit: u8) {
+        self.target[row][col] = bit;
+        if bit != 0 || self.mode == FilterMode::Off {
+            self.shown[row][col] = bit;
+            self.age[row][col] = 0;
+            self.dirty.push((row, col));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for row in 0..64 {
+            for col in 0..128 {
+                self.draw(row, col, 0);
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        for row in 0..64 {
+            for col in 0..128 {
+                if self.shown[row][col] == self.target[row][col] {
+                    continue;
+                }
+                match self.mode {
+                    FilterMode::Off => {}
+                    FilterMode::Decay(frames) => {
+                        self.age[row][col] = self.age[row][col].saturating_add(1);
+                        if self.age[row][col] > frames {
+                            self.shown[row][col] = 0;
+                            self.age[row][col] = 0;
+                        }
+                        self.dirty.push((row, col));
+                    }
+                    FilterMode::OrErase => {
+                        self.shown[row][col] = self.target[row][col];
+                        self.dirty.push((row, col));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn take_dirty(&mut self) -> Vec<(usize, usize)> {
+        return std::mem::take(&mut self.dirty);
+    }
+
+    pub fn color(&self, row: usize, col: usize, palette: &Palette) -> Color {
+        let shown = palette.color(self.shown[row][col]);
+        if let FilterMode::Decay(frames) = self.mode {
+            let age = self.age[row][col];
+            if age > 0 {
+                let amount = 1.0 - age as f32 / (frames as f32 + 1.0);
+                return palette.color(0).blend(shown, amount);
+            }
+        }
+        return shown;
+    }
+}
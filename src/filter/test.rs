@@ -0,0 +1,47 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_filter_decay() {
+    let palette = Palette::classic();
+    let mut filter = Filter::new(FilterMode::Decay(1));
+
+    filter.draw(0, 0, 1);
+    filter.draw(0, 0, 0);
+    assert_eq!(filter.color(0, 0, &palette), palette.color(1));
+
+    filter.end_frame();
+    assert_ne!(filter.color(0, 0, &palette), palette.color(1));
+    assert_ne!(filter.color(0, 0, &palette), palette.color(0));
+
+    filter.end_frame();
+    assert_eq!(filter.color(0, 0, &palette), palette.color(0));
+}
+
+#[test]
+fn test_filter_or_erase() {
+    let palette = Palette::classic();
+    let mut filter = Filter::new(FilterMode::OrErase);
+
+    filter.draw(1, 2, 1);
+    assert_eq!(filter.color(1, 2, &palette), palette.color(1));
+    filter.draw(1, 2, 0);
+    assert_eq!(filter.color(1, 2, &palette), palette.color(1));
+    filter.draw(1, 2, 1);
+    filter.end_frame();
+    assert_eq!(filter.color(1, 2, &palette), palette.color(1));
+
+    filter.draw(1, 2, 0);
+    filter.end_frame();
+    assert_eq!(filter.color(1, 2, &palette), palette.color(0));
+}
+
+#[test]
+fn test_filter_mode_parse() {
+    assert_eq!(FilterMode::parse("off"), Some(FilterMode::Off));
+    assert_eq!(FilterMode::parse("decay:5"), Some(FilterMode::Decay(5)));
+    assert_eq!(FilterMode::parse("or-erase"), Some(FilterMode::OrErase));
+    assert_eq!(FilterMode::parse("decay:254"), Some(FilterMode::Decay(254)));
+    assert_eq!(FilterMode::parse("decay:255"), None);
+    assert_eq!(FilterMode::parse("blur"), None);
+}
@@ -0,0 +1,207 @@
+mod test;
+
+use crate::emulator::{Emulator, LoadError, Reset, Settings};
+use std::fmt;
+
+// comparisons are shared with the cheat search, which is where they live
+pub use crate::cheat::Comparison;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Observation {
+    // one byte per pixel, 128 x 64, row by row
+    Framebuffer,
+    // all 4 KiB of memory
+    Ram,
+}
+
+// A condition on one byte of memory, written as e.g. "0x2F0 >= 3" or
+// "0x2F0 increased".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Predicate {
+    pub address: usize,
+    pub comparison: Comparison,
+    pub value: u8,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PredicateError(String);
+
+impl fmt::Display for PredicateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid predicate '{}'", self.0);
+    }
+}
+
+impl std::error::Error for PredicateError {}
+
+fn parse_number(text: &str) -> Option<usize> {
+    return match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    };
+}
+
+impl Predicate {
+    pub fn parse(text: &str) -> Result<Self, PredicateError> {
+        let error = || PredicateError(text.to_string());
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        let address = parts
+            .first()
+            .and_then(|address| parse_number(address))
+            .filter(|address| *address < 0x1000)
+            .ok_or_else(error)?;
+        let comparison = parts
+            .get(1)
+            .and_then(|comparison| Comparison::parse(comparison))
+            .ok_or_else(error)?;
+        let value = match (comparison.is_relative(), parts.get(2)) {
+            (true, None) => 0,
+            (false, Some(value)) => parse_number(value)
+                .and_then(|value| u8::try_from(value).ok())
+                .ok_or_else(error)?,
+            _ => return Err(error()),
+        };
+        if parts.len() > 3 {
+            return Err(error());
+        }
+        return Ok(Self {
+            address,
+            comparison,
+            value,
+        });
+    }
+
+    pub fn holds(&self, before: &[u8; 0x1000], after: &[u8; 0x1000]) -> bool {
+        return self
+            .comparison
+            .holds(before[self.address], after[self.address], self.value);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub settings: Settings,
+    // frames each action is held for, with rewards summed over them
+    pub frame_skip: u32,
+    pub observation: Observation,
+    // every predicate that holds after a frame adds its reward
+    pub rewards: Vec<(Predicate, f32)>,
+    // the episode ends once any of these holds, or the program halts
+    pub done: Vec<Predicate>,
+    // keypad state for each action, key n being bit 15 - n
+    pub actions: Vec<u16>,
+}
+
+impl Default for Options {
+    // doing nothing, then pressing each key on its own
+    fn default() -> Self {
+        let mut actions = vec![0];
+        actions.extend((0..16).map(|key| 1 << (15 - key)));
+        return Self {
+            settings: Settings::default(),
+            frame_skip: 4,
+            observation: Observation::Framebuffer,
+            rewards: Vec::new(),
+            done: Vec::new(),
+            actions,
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub observation: Vec<u8>,
+    pub reward: f32,
+    pub done: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidAction(pub usize);
+
+impl fmt::Display for InvalidAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "action {} is out of range", self.0);
+    }
+}
+
+impl std::error::Error for InvalidAction {}
+
+// A reset()/step() environment for training agents, driving the emulator
+// directly rather than through the threaded frontend loop.
+pub struct Env {
+    emulator: Emulator,
+    options: Options,
+    done: bool,
+}
+
+impl Env {
+    pub fn new(program: &[u8], options: Options) -> Result<Self, LoadError> {
+        let mut emulator = Emulator::new();
+        emulator.configure(options.settings);
+        emulator.load_program(program)?;
+        return Ok(Self {
+            emulator,
+            options,
+            done: false,
+        });
+    }
+
+    pub fn action_count(&self) -> usize {
+        return self.options.actions.len();
+    }
+
+    pub fn observation_size(&self) -> usize {
+        return match self.options.observation {
+            Observation::Framebuffer => 128 * 64,
+            Observation::Ram => 0x1000,
+        };
+    }
+
+    pub fn reset(&mut self) -> Vec<u8> {
+        self.emulator.reset(Reset::Hard);
+        self.done = false;
+        return self.observe();
+    }
+
+    pub fn step(&mut self, action: usize) -> Result<Step, InvalidAction> {
+        let keys = *self
+            .options
+            .actions
+            .get(action)
+            .ok_or(InvalidAction(action))?;
+        let mut reward = 0.0;
+        for _ in 0..self.options.frame_skip.max(1) {
+            if self.done {
+                break;
+            }
+            let before = *self.emulator.memory();
+            self.emulator.keypad().set(keys);
+            self.emulator.run_frame();
+            let after = self.emulator.memory();
+
+            for (predicate, value) in self.options.rewards.iter() {
+                if predicate.holds(&before, after) {
+                    reward += value;
+                }
+            }
+            self.done = self.emulator.is_halted()
+                || self
+                    .options
+                    .done
+                    .iter()
+                    .any(|predicate| predicate.holds(&before, after));
+        }
+        return Ok(Step {
+            observation: self.observe(),
+            reward,
+            done: self.done,
+        });
+    }
+
+    fn observe(&self) -> Vec<u8> {
+        return match self.options.observation {
+            Observation::Framebuffer => self.emulator.display().concat(),
+            Observation::Ram => self.emulator.memory().to_vec(),
+        };
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+use super::*;
+
+// LD I, 0x300; LD V0, [I]; ADD V0, 1; LD I, 0x300; LD [I], V0; JP 0x200
+#[cfg(test)]
+const COUNTER: [u8; 12] = [
+    0xA3, 0x00, 0xF0, 0x65, 0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00,
+];
+
+#[test]
+fn test_predicate_parse() {
+    assert_eq!(
+        Predicate::parse("0x2F0 >= 3"),
+        Ok(Predicate {
+            address: 0x2F0,
+            comparison: Comparison::GreaterOrEqual,
+            value: 3
+        })
+    );
+    assert_eq!(
+        Predicate::parse("768 increased").unwrap().comparison,
+        Comparison::Increased
+    );
+    assert!(Predicate::parse("0x2F0 increased 3").is_err());
+    assert!(Predicate::parse("0x2F0 == 256").is_err());
+    assert!(Predicate::parse("0x1000 == 1").is_err());
+    assert!(Predicate::parse("0x2F0 ~ 1").is_err());
+}
+
+#[test]
+fn test_env_step() {
+    let options = Options {
+        observation: Observation::Ram,
+        rewards: vec![(Predicate::parse("0x300 increased").unwrap(), 1.0)],
+        done: vec![Predicate::parse("0x300 >= 100").unwrap()],
+        ..Options::default()
+    };
+    let mut env = Env::new(&COUNTER, options).unwrap();
+    assert_eq!(env.action_count(), 17);
+    assert_eq!(env.reset().len(), env.observation_size());
+
+    let step = env.step(0).unwrap();
+    assert_eq!(step.reward, 4.0);
+    assert!(!step.done);
+    assert!(step.observation[0x300] > 0);
+
+    let mut steps = 1;
+    while !env.step(0).unwrap().done {
+        steps += 1;
+        assert!(steps < 100);
+    }
+    assert_eq!(env.step(1).unwrap().reward, 0.0);
+    assert_eq!(env.step(17), Err(InvalidAction(17)));
+
+    assert_eq!(env.reset()[0x300], 0);
+    assert!(!env.step(0).unwrap().done);
+}
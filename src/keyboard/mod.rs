@@ -1,56 +1,57 @@
 use crate::{Application, Message};
+use chip8_emulator::keymap::Keymap;
 use relm4::{
-    gtk::{gdk::Key, prelude::IsA, traits::WidgetExt, EventControllerKey, Inhibit, Widget},
+    gtk::{prelude::IsA, traits::WidgetExt, EventControllerKey, Inhibit, Widget},
     ComponentSender,
 };
-use std::sync::mpsc::Sender;
-
-pub const HEX_KEYS: [char; 16] = [
-    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
-];
+use std::{collections::HashSet, sync::mpsc::Sender};
 
 pub struct Keyboard {
     keys: u16,
+    // physical keys held down, so that a hex key bound to two of them is
+    // only let go once both are; a set as key repeat presses them again
+    held: HashSet<u32>,
+    keymap: Keymap,
     sender: Sender<u16>,
 }
 
 impl Keyboard {
-    pub fn new(sender: Sender<u16>) -> Self {
+    pub fn new(sender: Sender<u16>, keymap: Keymap) -> Self {
         return Self {
             keys: 0,
+            held: HashSet::new(),
+            keymap,
             sender: sender,
         };
     }
 
-    pub fn change_key_state(&mut self, key: Key, pressed: bool) {
-        if let Some(key_val) = key.to_unicode() {
-            match key_val {
-                '0'..='9' | 'a'..='f' => {
-                    if let Some(pos) = HEX_KEYS.iter().position(|&x| x == key_val) {
-                        let bin_pos = 15 - pos;
-
-                        let mut lhs = self.keys >> bin_pos;
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.update();
+    }
 
-                        let mut rhs_mask = 0;
-                        for _ in 0..bin_pos {
-                            rhs_mask = rhs_mask << 1 | 1;
-                        }
-                        let rhs = self.keys & rhs_mask;
+    pub fn change_key_state(&mut self, keycode: u32, pressed: bool) {
+        if self.keymap.key(keycode).is_none() {
+            return;
+        }
+        if pressed {
+            self.held.insert(keycode);
+        } else {
+            self.held.remove(&keycode);
+        }
+        self.update();
+    }
 
-                        if pressed {
-                            lhs = lhs | 1;
-                        } else {
-                            lhs = lhs >> 1 << 1;
-                        }
-                        self.keys = (lhs << bin_pos) | rhs;
-                        if pressed {
-                            self.sender.send(self.keys).unwrap();
-                        }
-                    };
-                }
-                _ => {}
-            }
-        };
+    // Tells the emulator which keys are down, key n being bit 15 - n
+    fn update(&mut self) {
+        self.keys = self
+            .held
+            .iter()
+            .filter_map(|keycode| self.keymap.key(*keycode))
+            .fold(0, |keys, key| keys | 1 << (15 - key));
+        // the emulator thread is gone after a failed load or on shutdown,
+        // and then there's nobody left to tell
+        let _ = self.sender.send(self.keys);
     }
 
     pub fn register_keyboard_controller(
@@ -60,13 +61,13 @@ impl Keyboard {
     ) {
         let controller = EventControllerKey::new();
         let app_sender_ = app_sender.clone();
-        controller.connect_key_pressed(move |_this, key, _code, _modifier| -> Inhibit {
-            app_sender_.input(Message::KeyDown(key.to_lower()));
+        controller.connect_key_pressed(move |_this, key, code, _modifier| -> Inhibit {
+            app_sender_.input(Message::KeyDown(key.to_lower(), code));
             return Inhibit(false);
         });
         let app_sender_ = app_sender.clone();
-        controller.connect_key_released(move |_this, key, _code, _modifier| {
-            app_sender_.input(Message::KeyUp(key));
+        controller.connect_key_released(move |_this, key, code, _modifier| {
+            app_sender_.input(Message::KeyUp(key, code));
         });
         host.add_controller(&controller);
     }
@@ -0,0 +1,105 @@
+mod test;
+
+// Physical key positions on a US layout and their hardware keycodes as
+// reported by GDK on X11 and Wayland (evdev scancode + 8).
+const KEY_NAMES: [(&str, u32); 42] = [
+    ("1", 10),
+    ("2", 11),
+    ("3", 12),
+    ("4", 13),
+    ("5", 14),
+    ("6", 15),
+    ("7", 16),
+    ("8", 17),
+    ("9", 18),
+    ("0", 19),
+    ("q", 24),
+    ("w", 25),
+    ("e", 26),
+    ("r", 27),
+    ("t", 28),
+    ("y", 29),
+    ("u", 30),
+    ("i", 31),
+    ("o", 32),
+    ("p", 33),
+    ("a", 38),
+    ("s", 39),
+    ("d", 40),
+    ("f", 41),
+    ("g", 42),
+    ("h", 43),
+    ("j", 44),
+    ("k", 45),
+    ("l", 46),
+    ("z", 52),
+    ("x", 53),
+    ("c", 54),
+    ("v", 55),
+    ("b", 56),
+    ("n", 57),
+    ("m", 58),
+    ("space", 65),
+    ("up", 111),
+    ("left", 113),
+    ("right", 114),
+    ("down", 116),
+    ("enter", 36),
+];
+
+// COSMAC VIP keypad     keyboard
+//   1 2 3 C             1 2 3 4
+//   4 5 6 D             q w e r
+//   7 8 9 E             a s d f
+//   A 0 B F             z x c v
+pub const COSMAC: &str = "x 1 2 3 q w e a s d z c 4 r f v";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(u32, u8)>,
+}
+
+impl Keymap {
+    pub fn cosmac() -> Self {
+        return Self::parse(COSMAC).unwrap();
+    }
+
+    pub fn keycode(name: &str) -> Option<u32> {
+        let name = name.to_lowercase();
+        if let Some((_, code)) = KEY_NAMES.iter().find(|(n, _)| *n == name) {
+            return Some(*code);
+        }
+        return name.parse::<u32>().ok();
+    }
+
+    // Parses sixteen entries, one for each hex key from 0 to F. An entry is a
+    // key name or a raw keycode; several can be bound to one hex key with '/'.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let entries = spec.split_whitespace().collect::<Vec<&str>>();
+        if entries.len() != 16 {
+            return None;
+        }
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+        for (key, entry) in entries.iter().enumerate() {
+            for name in entry.split('/') {
+                keymap.bind(Self::keycode(name)?, key as u8);
+            }
+        }
+        return Some(keymap);
+    }
+
+    pub fn bind(&mut self, keycode: u32, key: u8) {
+        self.bindings.retain(|(code, _)| *code != keycode);
+        self.bindings.push((keycode, key));
+    }
+
+    pub fn key(&self, keycode: u32) -> Option<u8> {
+        return self
+            .bindings
+            .iter()
+            .find(|(code, _)| *code == keycode)
+            .map(|(_, key)| *key);
+    }
+}
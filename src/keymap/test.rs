@@ -0,0 +1,23 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_cosmac_keymap() {
+    let keymap = Keymap::cosmac();
+    assert_eq!(keymap.key(Keymap::keycode("x").unwrap()), Some(0x0));
+    assert_eq!(keymap.key(Keymap::keycode("1").unwrap()), Some(0x1));
+    assert_eq!(keymap.key(Keymap::keycode("4").unwrap()), Some(0xC));
+    assert_eq!(keymap.key(Keymap::keycode("v").unwrap()), Some(0xF));
+    assert_eq!(keymap.key(Keymap::keycode("b").unwrap()), None);
+}
+
+#[test]
+fn test_parse_keymap() {
+    let keymap = Keymap::parse("x 1 2 3 q w/up e a/left s/down d/right z c 4 r f 55").unwrap();
+    assert_eq!(keymap.key(Keymap::keycode("up").unwrap()), Some(0x5));
+    assert_eq!(keymap.key(Keymap::keycode("w").unwrap()), Some(0x5));
+    assert_eq!(keymap.key(55), Some(0xF));
+
+    assert!(Keymap::parse("x 1 2 3").is_none());
+    assert!(Keymap::parse("x 1 2 3 q w e a s d z c 4 r f nope").is_none());
+}
@@ -0,0 +1,23 @@
+// The emulator core and everything a frontend needs around it. Nothing in
+// here depends on a GUI toolkit, the GTK and terminal frontends are binaries.
+pub mod batch;
+pub mod cartridge;
+pub mod cheat;
+pub mod config;
+pub mod database;
+pub mod emulator;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod gym;
+pub mod keymap;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod octo;
+pub mod palette;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod rom;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod screenshot;
@@ -0,0 +1,502 @@
+mod test;
+
+use crate::{
+    cartridge::Cartridge,
+    cheat::{Cheat, Cheats},
+    emulator::{Emulator, Keypad, Reset, Settings},
+    keymap::COSMAC,
+    palette::Palette,
+};
+use std::{
+    collections::BTreeMap,
+    ffi::{c_char, c_void, CStr},
+    slice,
+    sync::{Mutex, MutexGuard},
+};
+
+// The parts of libretro.h this core uses
+pub const RETRO_API_VERSION: u32 = 1;
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
+const RETRO_PIXEL_FORMAT_XRGB8888: i32 = 1;
+const RETRO_REGION_NTSC: u32 = 0;
+const RETRO_MEMORY_SYSTEM_RAM: u32 = 2;
+const RETRO_DEVICE_JOYPAD: u32 = 1;
+const RETRO_DEVICE_KEYBOARD: u32 = 3;
+const RETRO_DEVICE_ID_JOYPAD_B: u32 = 0;
+const RETRO_DEVICE_ID_JOYPAD_UP: u32 = 4;
+const RETRO_DEVICE_ID_JOYPAD_DOWN: u32 = 5;
+const RETRO_DEVICE_ID_JOYPAD_LEFT: u32 = 6;
+const RETRO_DEVICE_ID_JOYPAD_RIGHT: u32 = 7;
+const RETRO_DEVICE_ID_JOYPAD_A: u32 = 8;
+
+pub type EnvironmentFn = unsafe extern "C" fn(cmd: u32, data: *mut c_void) -> bool;
+pub type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: u32, height: u32, pitch: usize);
+pub type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+pub type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type InputPollFn = unsafe extern "C" fn();
+pub type InputStateFn = unsafe extern "C" fn(port: u32, device: u32, index: u32, id: u32) -> i16;
+
+#[repr(C)]
+pub struct SystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    pub base_width: u32,
+    pub base_height: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    pub geometry: GameGeometry,
+    pub timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 64;
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: usize = 44_100;
+const SAMPLES_PER_FRAME: usize = SAMPLE_RATE / 60;
+const TONE: usize = 440;
+const VOLUME: i16 = 0x1000;
+
+// The pad covers the usual movement keys, everything else is on the keyboard
+// in the COSMAC layout.
+const JOYPAD: [(u32, u8); 6] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x2),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x4),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x6),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x5),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x0),
+];
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+struct Core {
+    emulator: Emulator,
+    palette: Palette,
+    frame: Vec<u32>,
+    audio: Vec<i16>,
+    phase: usize,
+    // the codes the frontend has set, by its index for them
+    cheats: BTreeMap<u32, Vec<Cheat>>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+// A frontend that panicked halfway through a call has already lost the game,
+// so a poisoned lock is still good enough to shut down with.
+fn callbacks() -> MutexGuard<'static, Callbacks> {
+    return CALLBACKS.lock().unwrap_or_else(|err| err.into_inner());
+}
+
+fn core() -> MutexGuard<'static, Option<Core>> {
+    return CORE.lock().unwrap_or_else(|err| err.into_inner());
+}
+
+impl Core {
+    fn new() -> Self {
+        return Self {
+            emulator: Emulator::new(),
+            palette: Palette::classic(),
+            frame: vec![0; WIDTH * HEIGHT],
+            audio: vec![0; SAMPLES_PER_FRAME * 2],
+            phase: 0,
+            cheats: BTreeMap::new(),
+        };
+    }
+
+    fn apply_cheats(&mut self) {
+        let cheats = self.cheats.values().flatten().cloned().collect();
+        self.emulator.set_cheats(Cheats { cheats });
+    }
+
+    fn poll_keypad(&mut self, input_state: InputStateFn) {
+        let mut keypad = Keypad::new();
+        for (id, key) in JOYPAD.iter() {
+            if unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, *id) } != 0 {
+                keypad.set_key(*key, true);
+            }
+        }
+        // RETROK codes for letters and digits are their lowercase ASCII values
+        for (key, name) in COSMAC.split_whitespace().enumerate() {
+            let retrok = name.as_bytes()[0] as u32;
+            if unsafe { input_state(0, RETRO_DEVICE_KEYBOARD, 0, retrok) } != 0 {
+                keypad.set_key(key as u8, true);
+            }
+        }
+        self.emulator.keypad().set(keypad.keys());
+    }
+
+    fn render(&mut self) {
+        let display = self.emulator.display();
+        for (row, pixels) in display.iter().enumerate() {
+            for (col, pixel) in pixels.iter().enumerate() {
+                let color = self.palette.color(*pixel);
+                self.frame[row * WIDTH + col] =
+                    (color.0 as u32) << 16 | (color.1 as u32) << 8 | color.2 as u32;
+            }
+        }
+    }
+
+    // a square wave for as long as the sound timer runs
+    fn mix(&mut self) {
+        let beeping = self.emulator.is_beeping();
+        let period = SAMPLE_RATE / TONE;
+        for frame in self.audio.chunks_mut(2) {
+            let sample = match beeping {
+                true if self.phase < period / 2 => VOLUME,
+                true => -VOLUME,
+                false => 0,
+            };
+            frame[0] = sample;
+            frame[1] = sample;
+            self.phase = (self.phase + 1) % period;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> u32 {
+    return RETRO_API_VERSION;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    callbacks().environment = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    callbacks().video_refresh = Some(callback);
+}
+
+// every sample goes through the batch callback
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    callbacks().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    callbacks().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    callbacks().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {
+    *core() = Some(Core::new());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *core() = None;
+}
+
+/// # Safety
+///
+/// info must be NULL or point to a SystemInfo the frontend owns. The strings
+/// written into it are static.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    let Some(info) = info.as_mut() else {
+        return;
+    };
+    info.library_name = c"chip8_emulator".as_ptr();
+    info.library_version = concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char;
+    info.valid_extensions = c"ch8|c8|chip8|gif".as_ptr();
+    info.need_fullpath = false;
+    info.block_extract = false;
+}
+
+/// # Safety
+///
+/// info must be NULL or point to a SystemAvInfo the frontend owns.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    let Some(info) = info.as_mut() else {
+        return;
+    };
+    info.geometry = GameGeometry {
+        base_width: WIDTH as u32,
+        base_height: HEIGHT as u32,
+        max_width: WIDTH as u32,
+        max_height: HEIGHT as u32,
+        aspect_ratio: 2.0,
+    };
+    info.timing = SystemTiming {
+        fps: FPS,
+        sample_rate: SAMPLE_RATE as f64,
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: u32, _device: u32) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = core().as_mut() {
+        core.emulator.reset(Reset::Hard);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let (video_refresh, audio_sample_batch, input_poll, input_state) = {
+        let callbacks = callbacks();
+        (
+            callbacks.video_refresh,
+            callbacks.audio_sample_batch,
+            callbacks.input_poll,
+            callbacks.input_state,
+        )
+    };
+    let mut core = core();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+
+    if let Some(input_poll) = input_poll {
+        unsafe { input_poll() };
+    }
+    if let Some(input_state) = input_state {
+        core.poll_keypad(input_state);
+    }
+    core.emulator.run_frame();
+
+    core.render();
+    if let Some(video_refresh) = video_refresh {
+        let pitch = WIDTH * std::mem::size_of::<u32>();
+        unsafe {
+            video_refresh(
+                core.frame.as_ptr() as *const c_void,
+                WIDTH as u32,
+                HEIGHT as u32,
+                pitch,
+            )
+        };
+    }
+    core.mix();
+    if let Some(audio_sample_batch) = audio_sample_batch {
+        unsafe { audio_sample_batch(core.audio.as_ptr(), SAMPLES_PER_FRAME) };
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    return match core().as_ref() {
+        Some(core) => core.emulator.state_size(),
+        None => 0,
+    };
+}
+
+/// # Safety
+///
+/// data must be NULL or point to size writable bytes, which
+/// retro_serialize_size says how many are needed.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = core();
+    let Some(core) = core.as_ref() else {
+        return false;
+    };
+    let state = core.emulator.save_state();
+    if data.is_null() || size < state.len() {
+        return false;
+    }
+    slice::from_raw_parts_mut(data as *mut u8, state.len()).copy_from_slice(&state);
+    return true;
+}
+
+/// # Safety
+///
+/// data must be NULL or point to size readable bytes, which are copied and
+/// needn't outlive the call.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = core();
+    let Some(core) = core.as_mut() else {
+        return false;
+    };
+    if data.is_null() {
+        return false;
+    }
+    let state = slice::from_raw_parts(data as *const u8, size);
+    return core.emulator.load_state(state).is_ok();
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {
+    if let Some(core) = core().as_mut() {
+        core.cheats.clear();
+        core.apply_cheats();
+    }
+}
+
+/// Codes are the cheat engine's "2F0 03" or the "2F0:03" frontends tend to
+/// write, several joined with '+'. A code with any part that doesn't parse
+/// is dropped as a whole.
+///
+/// # Safety
+///
+/// code must be NULL or a nul-terminated string, which is copied and needn't
+/// outlive the call.
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(index: u32, enabled: bool, code: *const c_char) {
+    let mut core = core();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+    let code = match code.is_null() {
+        true => String::new(),
+        false => CStr::from_ptr(code).to_string_lossy().into_owned(),
+    };
+    let mut cheats = Vec::new();
+    for part in code.split('+') {
+        match Cheat::parse(&part.replace(':', " ")) {
+            Some(cheat) => cheats.push(cheat),
+            None => {
+                cheats.clear();
+                break;
+            }
+        }
+    }
+    if enabled && !cheats.is_empty() {
+        core.cheats.insert(index, cheats);
+    } else {
+        core.cheats.remove(&index);
+    }
+    core.apply_cheats();
+}
+
+/// # Safety
+///
+/// game must be NULL or point to a GameInfo whose data is NULL or points to
+/// size readable bytes. The ROM is copied and needn't outlive the call.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+    let program = slice::from_raw_parts(game.data as *const u8, game.size);
+
+    if let Some(environment) = callbacks().environment {
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut i32 as *mut c_void,
+        ) {
+            return false;
+        }
+    }
+
+    let mut core = core();
+    let core = core.get_or_insert_with(Core::new);
+    // nothing a previous cartridge asked for carries over to this game
+    core.palette = Palette::classic();
+    if !Cartridge::is_cartridge(program) {
+        core.emulator.configure(Settings::default());
+        return core.emulator.load_program(program).is_ok();
+    }
+    let Ok(cartridge) = Cartridge::parse(program) else {
+        return false;
+    };
+    let mut settings = Settings::default();
+    cartridge.options.apply(&mut settings);
+    core.emulator.configure(settings);
+    if let Some(palette) = cartridge.options.palette("cartridge") {
+        core.palette = palette;
+    }
+    return core.emulator.load_program(&cartridge.program).is_ok();
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: u32,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    return false;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    if let Some(core) = core().as_mut() {
+        core.emulator = Emulator::new();
+        core.palette = Palette::classic();
+        core.cheats.clear();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> u32 {
+    return RETRO_REGION_NTSC;
+}
+
+// Lets the frontend's own cheat search and achievements look at RAM. The
+// pointer stays valid until the game is unloaded.
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: u32) -> *mut c_void {
+    if id != RETRO_MEMORY_SYSTEM_RAM {
+        return std::ptr::null_mut();
+    }
+    return match core().as_mut() {
+        Some(core) => core.emulator.memory_mut().as_mut_ptr() as *mut c_void,
+        None => std::ptr::null_mut(),
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: u32) -> usize {
+    if id != RETRO_MEMORY_SYSTEM_RAM || core().is_none() {
+        return 0;
+    }
+    return 0x1000;
+}
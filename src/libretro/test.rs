@@ -0,0 +1,136 @@
+#[cfg(test)]
+use super::*;
+#[cfg(test)]
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+// A minimal host: it accepts the pixel format, counts frames and samples and
+// holds down whichever pad button the test asks for.
+#[cfg(test)]
+static PIXEL_FORMAT_SET: AtomicBool = AtomicBool::new(false);
+#[cfg(test)]
+static FRAMES: AtomicUsize = AtomicUsize::new(0);
+#[cfg(test)]
+static LIT: AtomicUsize = AtomicUsize::new(0);
+#[cfg(test)]
+static SAMPLES: AtomicUsize = AtomicUsize::new(0);
+#[cfg(test)]
+static HELD: AtomicU32 = AtomicU32::new(u32::MAX);
+
+#[cfg(test)]
+unsafe extern "C" fn environment(cmd: u32, data: *mut c_void) -> bool {
+    if cmd == RETRO_ENVIRONMENT_SET_PIXEL_FORMAT {
+        PIXEL_FORMAT_SET.store(
+            *(data as *const i32) == RETRO_PIXEL_FORMAT_XRGB8888,
+            Ordering::SeqCst,
+        );
+        return true;
+    }
+    return false;
+}
+
+#[cfg(test)]
+unsafe extern "C" fn video_refresh(data: *const c_void, width: u32, height: u32, pitch: usize) {
+    let pixels = slice::from_raw_parts(data as *const u32, pitch / 4 * height as usize);
+    let background = pixels[width as usize * height as usize - 1];
+    let lit = pixels.iter().filter(|pixel| **pixel != background).count();
+    LIT.store(lit, Ordering::SeqCst);
+    FRAMES.fetch_add(1, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+unsafe extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+    SAMPLES.fetch_add(frames, Ordering::SeqCst);
+    return frames;
+}
+
+#[cfg(test)]
+unsafe extern "C" fn input_poll() {}
+
+#[cfg(test)]
+unsafe extern "C" fn input_state(_port: u32, device: u32, _index: u32, id: u32) -> i16 {
+    return (device == RETRO_DEVICE_JOYPAD && id == HELD.load(Ordering::SeqCst)) as i16;
+}
+
+#[test]
+fn test_libretro_host() {
+    assert_eq!(retro_api_version(), RETRO_API_VERSION);
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+
+    // LD V1, K; LD F, V1; DRW V0, V0, 5; LD ST, V1; JP 0x208
+    let program: [u8; 10] = [0xF1, 0x0A, 0xF1, 0x29, 0xD0, 0x05, 0xF1, 0x18, 0x12, 0x08];
+    let game = GameInfo {
+        path: std::ptr::null(),
+        data: program.as_ptr() as *const c_void,
+        size: program.len(),
+        meta: std::ptr::null(),
+    };
+    unsafe {
+        assert!(retro_load_game(&game));
+    }
+    assert!(PIXEL_FORMAT_SET.load(Ordering::SeqCst));
+
+    retro_run();
+    assert_eq!(FRAMES.load(Ordering::SeqCst), 1);
+    assert_eq!(SAMPLES.load(Ordering::SeqCst), SAMPLES_PER_FRAME);
+    assert_eq!(LIT.load(Ordering::SeqCst), 0);
+
+    // A on the pad is keypad 5, whose digit then gets drawn
+    HELD.store(RETRO_DEVICE_ID_JOYPAD_A, Ordering::SeqCst);
+    retro_run();
+    retro_run();
+    assert!(LIT.load(Ordering::SeqCst) > 0);
+    assert_eq!(
+        unsafe { *(retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM) as *const u8).add(0x200) },
+        0xF1
+    );
+
+    let mut state = vec![0u8; retro_serialize_size()];
+    unsafe {
+        assert!(retro_serialize(
+            state.as_mut_ptr() as *mut c_void,
+            state.len()
+        ));
+    }
+    retro_reset();
+    HELD.store(u32::MAX, Ordering::SeqCst);
+    retro_run();
+    assert_eq!(LIT.load(Ordering::SeqCst), 0);
+    unsafe {
+        assert!(retro_unserialize(
+            state.as_ptr() as *const c_void,
+            state.len()
+        ));
+    }
+    retro_run();
+    assert!(LIT.load(Ordering::SeqCst) > 0);
+
+    // cheats poke memory at the start of every frame until they're reset
+    let ram = retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM) as *const u8;
+    unsafe {
+        retro_cheat_set(0, true, c"300:2A+301 07".as_ptr());
+        retro_cheat_set(1, true, c"not a cheat".as_ptr());
+    }
+    retro_run();
+    assert_eq!(unsafe { (*ram.add(0x300), *ram.add(0x301)) }, (0x2A, 0x07));
+    retro_cheat_reset();
+    unsafe { *(ram.add(0x300) as *mut u8) = 0 };
+    retro_run();
+    assert_eq!(unsafe { *ram.add(0x300) }, 0);
+
+    // a cartridge's colours don't outlive it, into the next game or after
+    core().as_mut().unwrap().palette = Palette::amber();
+    unsafe {
+        assert!(retro_load_game(&game));
+    }
+    assert_eq!(core().as_ref().unwrap().palette, Palette::classic());
+    core().as_mut().unwrap().palette = Palette::amber();
+    retro_unload_game();
+    assert_eq!(core().as_ref().unwrap().palette, Palette::classic());
+    retro_deinit();
+    assert_eq!(retro_get_memory_size(RETRO_MEMORY_SYSTEM_RAM), 0);
+}
@@ -1,35 +1,112 @@
-mod emulator;
+mod cheat_editor;
 mod keyboard;
-use emulator::Emulator;
+mod memory_viewer;
+mod menu;
+mod register_inspector;
+use cheat_editor::CheatEditor;
+use chip8_emulator::{
+    cheat::{Cheats, Comparison},
+    config::Config,
+    database::Database,
+    emulator::{self, Control, Emulator, Event, Register, Reset},
+    filter::Filter,
+    palette::Palette,
+    rom::{Rom, Watcher},
+    screenshot,
+};
 use keyboard::Keyboard;
+use memory_viewer::MemoryViewer;
+use register_inspector::RegisterInspector;
 use relm4::{
     gtk::{
         gdk::Key,
-        traits::{BoxExt, GridExt, GtkWindowExt, WidgetExt},
-        Box, Grid, Window,
+        gio::prelude::FileExt,
+        glib::{self, Continue},
+        traits::{BoxExt, DialogExt, FileChooserExt, GridExt, GtkWindowExt, WidgetExt},
+        Box, ButtonsType, FileChooserAction, FileChooserDialog, Grid, Inhibit, MessageDialog,
+        MessageType, Orientation, ResponseType, Window,
     },
-    Component, ComponentParts, RelmApp, RelmIterChildrenExt, RelmWidgetExt,
+    Component, ComponentParts, RelmApp, RelmWidgetExt,
+};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc,
+    thread,
+    time::Duration,
 };
-use std::{sync::mpsc, thread};
 
 pub struct Application {
     display: [[u8; 128]; 64],
     keyboard: Keyboard,
+    config: Config,
+    palette: Palette,
+    filter: Filter,
+    database: Database,
+    control: mpsc::Sender<Control>,
+    emulator: Option<thread::JoinHandle<()>>,
+    memory_viewer: MemoryViewer,
+    register_inspector: RegisterInspector,
+    cheat_editor: CheatEditor,
+    // of the loaded ROM, which its cheats are saved under
+    sha1: Option<String>,
+    paused: bool,
+    slow_motion: bool,
+    fast_forward: bool,
+    // set with --watch, follows whichever ROM is loaded
+    watching: bool,
+    keep_registers: bool,
+    watcher: Option<Watcher>,
+    // given on the command line, so only for the ROM given there
+    symbol_files: (Option<PathBuf>, Option<PathBuf>),
+}
+
+// What the command line asked for
+pub struct Launch {
+    rom: Option<(Rom, PathBuf)>,
+    watch: bool,
+    keep_registers: bool,
+    symbols: Option<PathBuf>,
+    source_map: Option<PathBuf>,
 }
 
 #[derive(Debug)]
 pub enum Message {
     // Display
-    Drw(u8, u8, u8),
-    Clr,
+    Display(std::boxed::Box<emulator::Display>),
+    Emulated,
+    EmulatorError(String),
     // Keyboard
-    KeyDown(Key),
-    KeyUp(Key),
+    KeyDown(Key, u32),
+    KeyUp(Key, u32),
+    // Presentation
+    Frame,
+    // Rom
+    Open,
+    Load(PathBuf),
+    // Emulation
+    Pause,
+    FrameAdvance,
+    Reset(Reset),
+    SlowMotion,
+    FastForward,
+    // Debugging
+    Snapshot(std::boxed::Box<emulator::Snapshot>),
+    ShowMemory,
+    Poke(usize, u8),
+    ShowRegisters,
+    SetRegister(Register, u16),
+    ShowCheats,
+    CheatSearch(Option<(Comparison, u8)>),
+    FreezeCheat(usize),
+    SetCheats(Cheats),
     // Sys
     ShutDown,
 }
 
 pub struct AppWidgets {
+    frame: Box,
     screen: Grid,
 }
 
@@ -38,7 +115,7 @@ impl Component for Application {
 
     type Output = ();
 
-    type Init = ();
+    type Init = Launch;
 
     type Root = Window;
 
@@ -56,67 +133,77 @@ impl Component for Application {
     }
 
     fn init(
-        _init: Self::Init,
+        init: Self::Init,
         root: &Self::Root,
         sender: relm4::ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let program: Vec<u8> = vec![
-
-            // Simple print
-
-            // 0x63, 0x01, 0xF3, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x63, 0x07, 0xF3, 0x29, 0xD0, 0x15,
-            // 0x70, 0x05, 0x63, 0x03, 0xF3, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x63, 0x08, 0xF3, 0x29,
-            // 0xD0,
-            // 0x15,
-
-            // Big multiline text render
-
-            // 0x70, 0x1, 0x70, 0x1, 0x70, 0x1, 0x63, 0x1, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63,
-            // 0x8, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0x4, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5,
-            // 0x63, 0x7, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x70, 0x1, 0x63, 0xa, 0xf3, 0x29, 0xd0,
-            // 0x15, 0x70, 0x5, 0x63, 0xf, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xb, 0xf3, 0x29,
-            // 0xd0, 0x15, 0x70, 0x5, 0x63, 0xb, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x70, 0x1, 0x63,
-            // 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x70, 0x1, 0x63, 0xd, 0xf3, 0x29, 0xd0, 0x15,
-            // 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xa, 0xf3, 0x29, 0xd0,
-            // 0x15, 0x70, 0x5, 0x63, 0xd, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x70, 0x1, 0x63, 0xb,
-            // 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63,
-            // 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xf, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5,
-            // 0x70, 0x1, 0x63, 0xf, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0,
-            // 0x15, 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xb, 0xf3, 0x29,
-            // 0xd0, 0x15, 0x70, 0x5, 0x70, 0x1, 0x63, 0xd, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63,
-            // 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x71, 0x6,
-            // 0x60, 0x0, 0x63, 0xb, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x70, 0x1, 0x63, 0xb, 0xf3,
-            // 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xb,
-            // 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x70, 0x1, 0x63, 0xb, 0xf3, 0x29, 0xd0, 0x15, 0x70,
-            // 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15,
-            // 0x70, 0x5, 0x63, 0xe, 0xf3, 0x29, 0xd0, 0x15, 0x70, 0x5, 0x63, 0xb, 0xf3, 0x29, 0xd0,
-            // 0x15, 0x70, 0x5, 0x63, 0xa, 0xf3, 0x29, 0xd0, 0x15, 0x70,
-            // 0x5, 0x00, 0xE0
-
-            // Type to screen
-
-            // 0xF3, 0x0A, 0xF3, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x12, 0x00,
-        ];
-
         let (keyboard_sender, keyboard_receiver) = mpsc::channel::<u16>();
+        let (control_sender, control_receiver) = mpsc::channel::<Control>();
+
+        let config = Config::load();
+        let palette = config.palette();
+        let filter = Filter::new(config.filter(None));
+        let keymap = config.keymap(None);
+        let path = config.database();
+        let database = match Database::open(path.as_deref()) {
+            Ok(database) => database,
+            Err(err) => {
+                if let Some(path) = path {
+                    eprintln!("failed to load ROM database {}: {}", path.display(), err);
+                }
+                Database::bundled()
+            }
+        };
 
         let mut model = Self {
             display: [[0; 128]; 64],
-            keyboard: Keyboard::new(keyboard_sender),
+            keyboard: Keyboard::new(keyboard_sender, keymap),
+            config,
+            palette,
+            filter,
+            database,
+            control: control_sender,
+            emulator: None,
+            memory_viewer: MemoryViewer::new(root, sender.clone()),
+            register_inspector: RegisterInspector::new(root, sender.clone()),
+            cheat_editor: CheatEditor::new(root, sender.clone()),
+            sha1: None,
+            paused: false,
+            slow_motion: false,
+            fast_forward: false,
+            watching: init.watch,
+            keep_registers: init.keep_registers,
+            watcher: None,
+            symbol_files: (init.symbols, init.source_map),
         };
 
         model
             .keyboard
             .register_keyboard_controller(root, sender.clone());
 
-        thread::spawn(move || {
+        let frame_sender = sender.clone();
+        glib::timeout_add_local(Duration::from_micros(16_667), move || {
+            frame_sender.input(Message::Frame);
+            return Continue(true);
+        });
+
+        let emulator_sender = sender.input_sender().clone();
+        model.emulator = Some(thread::spawn(move || {
             let mut emulator = Emulator::new();
-            emulator.load_program(program);
-            emulator.start(
-                &mut model.display,
-                keyboard_receiver,
-                &sender.input_sender(),
-            );
+            emulator.start(keyboard_receiver, control_receiver, |event| {
+                emulator_sender.emit(match event {
+                    Event::Display(display) => Message::Display(display),
+                    Event::Snapshot(snapshot) => Message::Snapshot(snapshot),
+                    Event::Frame => Message::Emulated,
+                    Event::Error(message) => Message::EmulatorError(message),
+                });
+            });
+        }));
+
+        let close_sender = sender.clone();
+        root.connect_close_request(move |_| {
+            close_sender.input(Message::ShutDown);
+            return Inhibit(true);
         });
 
         let frame = Box::new(relm4::gtk::Orientation::Horizontal, 0);
@@ -124,14 +211,20 @@ impl Component for Application {
         frame.set_width_request(1480);
         frame.set_height_request(840);
         frame.set_margin_all(0);
-        frame.inline_css("background-color: #F4E6D1; border-radius: 50px");
+        frame.inline_css(&format!(
+            "background-color: {}; border-radius: 50px",
+            model.palette.bezel.to_hex()
+        ));
 
         let screen = Grid::builder()
             .width_request(1280)
             .height_request(640)
             .build();
         screen.set_margin_all(100);
-        screen.inline_css("background-color: black; border-radius: 5px; padding: 5px");
+        screen.inline_css(&format!(
+            "{}; border-radius: 5px; padding: 5px",
+            model.palette.css(0)
+        ));
 
         for y in 0..64 {
             for x in 0..128 {
@@ -141,9 +234,16 @@ impl Component for Application {
         }
 
         frame.append(&screen);
-        root.set_child(Some(&frame));
 
-        let widgets = Self::Widgets { screen };
+        let layout = Box::new(Orientation::Vertical, 0);
+        layout.append(&menu::build(root, sender.clone()));
+        layout.append(&frame);
+        root.set_child(Some(&layout));
+
+        let widgets = Self::Widgets { frame, screen };
+        if let Some((rom, path)) = init.rom {
+            model.load_rom(rom, &path, root, &widgets);
+        }
         return ComponentParts { model, widgets };
     }
 
@@ -151,39 +251,335 @@ impl Component for Application {
         &mut self,
         widgets: &mut Self::Widgets,
         message: Self::Input,
-        _sender: relm4::ComponentSender<Self>,
-        _root: &Self::Root,
+        sender: relm4::ComponentSender<Self>,
+        root: &Self::Root,
     ) {
         match message {
-            Message::Clr => {
-                widgets
-                    .screen
-                    .iter_children()
-                    .for_each(|child| child.inline_css("background-color: black"));
+            Message::Display(display) => {
+                for row in 0..64 {
+                    for col in 0..128 {
+                        if display[row][col] != self.display[row][col] {
+                            self.filter.draw(row, col, display[row][col]);
+                        }
+                    }
+                }
+                self.display = *display;
+                self.present(widgets);
             }
-            Message::Drw(row, col, bit) => {
-                let pixel = widgets
-                    .screen
-                    .child_at(col as i32 * 10, row as i32 * 10)
-                    .unwrap();
-                if bit == 1 {
-                    pixel.inline_css("background-color: white");
+            // the filter ages pixels by emulated frames, so pausing or changing
+            // speed doesn't change how long they linger
+            Message::Emulated => {
+                self.filter.end_frame();
+            }
+            Message::EmulatorError(message) => {
+                let dialog = MessageDialog::builder()
+                    .transient_for(root)
+                    .modal(true)
+                    .message_type(MessageType::Error)
+                    .buttons(ButtonsType::Close)
+                    .text("Emulator error")
+                    .secondary_text(&message)
+                    .build();
+                dialog.connect_response(|dialog, _| dialog.close());
+                dialog.show();
+            }
+            Message::Frame => {
+                self.present(widgets);
+                self.reload();
+            }
+            Message::Open => {
+                let dialog = FileChooserDialog::new(
+                    Some("Open ROM"),
+                    Some(root),
+                    FileChooserAction::Open,
+                    &[
+                        ("Cancel", ResponseType::Cancel),
+                        ("Open", ResponseType::Accept),
+                    ],
+                );
+                dialog.connect_response(move |dialog, response| {
+                    if response == ResponseType::Accept {
+                        if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                            sender.input(Message::Load(path));
+                        }
+                    }
+                    dialog.close();
+                });
+                dialog.show();
+            }
+            Message::Load(path) => match Rom::open(&path) {
+                Ok(rom) => {
+                    self.symbol_files = (None, None);
+                    self.load_rom(rom, &path, root, widgets);
+                }
+                Err(err) => {
+                    let dialog = MessageDialog::builder()
+                        .transient_for(root)
+                        .modal(true)
+                        .message_type(MessageType::Error)
+                        .buttons(ButtonsType::Close)
+                        .text(&format!("Could not open {}", path.display()))
+                        .secondary_text(&err.to_string())
+                        .build();
+                    dialog.connect_response(|dialog, _| dialog.close());
+                    dialog.show();
+                }
+            },
+            Message::Pause => {
+                self.paused = !self.paused;
+                if self.paused {
+                    self.send(Control::Pause);
                 } else {
-                    pixel.inline_css("background-color: black");
+                    self.send(Control::Resume);
+                }
+            }
+            Message::FrameAdvance => {
+                if !self.paused {
+                    self.paused = true;
+                    self.send(Control::Pause);
+                }
+                self.send(Control::FrameAdvance);
+            }
+            Message::Reset(reset) => {
+                self.send(Control::Reset(reset));
+            }
+            Message::SlowMotion => {
+                self.slow_motion = !self.slow_motion;
+                self.fast_forward = false;
+                self.send(Control::Speed(self.speed()));
+            }
+            Message::FastForward => {
+                self.fast_forward = !self.fast_forward;
+                self.slow_motion = false;
+                self.send(Control::Speed(self.speed()));
+            }
+            Message::Snapshot(snapshot) => {
+                self.register_inspector.update((*snapshot).clone());
+                self.cheat_editor.update((*snapshot).clone());
+                self.memory_viewer.update(*snapshot);
+            }
+            Message::ShowMemory => {
+                self.memory_viewer.show();
+            }
+            Message::Poke(address, value) => {
+                self.send(Control::Poke(address, value));
+            }
+            Message::ShowRegisters => {
+                self.register_inspector.show();
+            }
+            Message::SetRegister(register, value) => {
+                self.send(Control::SetRegister(register, value));
+            }
+            Message::ShowCheats => {
+                self.cheat_editor.show();
+            }
+            Message::CheatSearch(filter) => {
+                self.cheat_editor.search(filter);
+            }
+            Message::FreezeCheat(address) => {
+                self.cheat_editor.freeze(address);
+            }
+            Message::SetCheats(cheats) => {
+                if let Some(sha1) = &self.sha1 {
+                    if let Err(err) = cheats.save(sha1) {
+                        eprintln!("failed to save cheats: {}", err);
+                    }
+                }
+                self.send(Control::SetCheats(cheats));
+            }
+            Message::ShutDown => {
+                self.send(Control::ShutDown);
+                if let Some(emulator) = self.emulator.take() {
+                    if emulator.join().is_err() {
+                        eprintln!("emulator thread panicked");
+                    }
+                }
+                root.destroy();
+            }
+            Message::KeyDown(Key::F5, _) => sender.input(Message::Pause),
+            Message::KeyDown(Key::F6, _) => sender.input(Message::FrameAdvance),
+            Message::KeyDown(Key::F7, _) => sender.input(Message::Reset(Reset::Soft)),
+            Message::KeyDown(Key::F8, _) => sender.input(Message::Reset(Reset::Hard)),
+            Message::KeyDown(Key::F9, _) => sender.input(Message::SlowMotion),
+            Message::KeyDown(Key::F10, _) => sender.input(Message::FastForward),
+            Message::KeyDown(Key::F3, _) => sender.input(Message::ShowMemory),
+            Message::KeyDown(Key::F4, _) => sender.input(Message::ShowRegisters),
+            Message::KeyDown(Key::F2, _) => {
+                self.cycle_palette(widgets);
+            }
+            Message::KeyDown(Key::F12, _) => {
+                let path = screenshot::default_path();
+                if let Err(err) = screenshot::save(&path, &self.display, &self.palette) {
+                    eprintln!("failed to save screenshot {}: {}", path.display(), err);
                 }
             }
-            Message::KeyDown(key) => {
-                self.keyboard.change_key_state(key, true);
+            Message::KeyDown(_, code) => {
+                self.keyboard.change_key_state(code, true);
             }
-            Message::KeyUp(key) => {
-                self.keyboard.change_key_state(key, false);
+            Message::KeyUp(_, code) => {
+                self.keyboard.change_key_state(code, false);
             }
-            _ => {}
         };
     }
 }
 
+impl Application {
+    fn load_rom(&mut self, rom: Rom, path: &Path, root: &Window, widgets: &AppWidgets) {
+        self.set_symbols(&rom);
+        if self.watching {
+            self.watcher = Some(Watcher::new(path));
+        }
+        let setup = self.config.setup(&rom, Some(&self.database));
+        self.keyboard.set_keymap(setup.keymap);
+        self.set_palette(setup.palette, widgets);
+        self.filter = Filter::new(setup.filter);
+        root.set_title(Some(&format!("CHIP-8 - {}", setup.title)));
+
+        let cheats = Cheats::load(&rom.sha1);
+        self.cheat_editor.set_cheats(&cheats);
+        self.sha1 = Some(rom.sha1);
+
+        self.send(Control::Configure(setup.settings));
+        self.send(Control::Load(rom.program));
+        self.send(Control::SetCheats(cheats));
+        if self.paused {
+            self.paused = false;
+            self.send(Control::Resume);
+        }
+    }
+
+    // Hands a rebuilt ROM to the emulator, keeping the settings it was
+    // loaded with
+    fn reload(&mut self) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        if !watcher.changed() {
+            return;
+        }
+        match Rom::open(watcher.path()) {
+            Ok(rom) => {
+                self.set_symbols(&rom);
+                self.send(Control::Reload(rom.program, self.keep_registers));
+            }
+            Err(err) => eprintln!("could not reload {}: {}", watcher.path().display(), err),
+        }
+    }
+
+    // the ROM's own labels plus any files from the command line
+    fn set_symbols(&mut self, rom: &Rom) {
+        let (symbols, source_map) = &self.symbol_files;
+        let symbols = match rom.symbols_with(symbols.as_deref(), source_map.as_deref()) {
+            Ok(symbols) => symbols,
+            Err(err) => {
+                eprintln!("could not read symbols: {}", err);
+                rom.symbols.clone()
+            }
+        };
+        self.register_inspector.set_symbols(symbols.clone());
+        self.send(Control::SetSymbols(symbols));
+    }
+
+    fn send(&self, control: Control) {
+        // the emulator thread only goes away during shutdown
+        let _ = self.control.send(control);
+    }
+
+    fn speed(&self) -> f32 {
+        if self.slow_motion {
+            return 0.25;
+        }
+        if self.fast_forward {
+            return 4.0;
+        }
+        return 1.0;
+    }
+
+    fn present(&mut self, widgets: &AppWidgets) {
+        for (row, col) in self.filter.take_dirty() {
+            let pixel = widgets
+                .screen
+                .child_at(col as i32 * 10, row as i32 * 10)
+                .unwrap();
+            pixel.inline_css(&self.filter.color(row, col, &self.palette).css());
+        }
+    }
+
+    fn cycle_palette(&mut self, widgets: &AppWidgets) {
+        let palettes = self.config.palettes();
+        let next = match palettes.iter().position(|p| p.name == self.palette.name) {
+            Some(pos) => (pos + 1) % palettes.len(),
+            None => 0,
+        };
+        self.config.set_palette(&palettes[next]);
+        if let Err(err) = self.config.save() {
+            eprintln!("failed to save config: {}", err);
+        }
+        self.set_palette(palettes[next].clone(), widgets);
+    }
+
+    fn set_palette(&mut self, palette: Palette, widgets: &AppWidgets) {
+        self.palette = palette;
+        widgets.frame.inline_css(&format!(
+            "background-color: {}",
+            self.palette.bezel.to_hex()
+        ));
+        widgets.screen.inline_css(&self.palette.css(0));
+        for row in 0..64 {
+            for col in 0..128 {
+                let pixel = widgets
+                    .screen
+                    .child_at(col as i32 * 10, row as i32 * 10)
+                    .unwrap();
+                pixel.inline_css(&self.filter.color(row, col, &self.palette).css());
+            }
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: chip8_emulator [--symbols <labels>] [--source-map <lines>] \
+         [--watch [--keep-registers]] [<rom>]"
+    );
+    process::exit(2);
+}
+
 fn main() {
+    let mut launch = Launch {
+        rom: None,
+        watch: false,
+        keep_registers: false,
+        symbols: None,
+        source_map: None,
+    };
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--symbols" => {
+                launch.symbols = Some(args.next().map(PathBuf::from).unwrap_or_else(|| usage()))
+            }
+            "--source-map" => {
+                launch.source_map = Some(args.next().map(PathBuf::from).unwrap_or_else(|| usage()))
+            }
+            "--watch" => launch.watch = true,
+            "--keep-registers" => launch.keep_registers = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+    if launch.keep_registers && !launch.watch {
+        usage();
+    }
+    launch.rom = path.map(|path| match Rom::open(&path) {
+        Ok(rom) => (rom, path),
+        Err(err) => {
+            eprintln!("could not open {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    });
+
     let app = RelmApp::new("");
-    app.run::<Application>(());
+    app.run::<Application>(launch);
 }
@@ -0,0 +1,211 @@
+use crate::{Application, Message};
+use chip8_emulator::emulator::Snapshot;
+use relm4::{
+    gtk::{
+        prelude::TextBufferExtManual,
+        traits::{
+            BoxExt, EditableExt, EntryExt, GtkWindowExt, TextBufferExt, TextViewExt, WidgetExt,
+        },
+        Box, Entry, Orientation, PolicyType, ScrolledWindow, TextBuffer, TextView, Window,
+    },
+    ComponentSender, RelmWidgetExt,
+};
+use std::ops::Range;
+
+const MEMORY_SIZE: usize = 0x1000;
+const ROW: usize = 16;
+// "0200: " followed by the hex column, a space and the ASCII column
+const HEX_START: usize = 6;
+const ASCII_START: usize = HEX_START + ROW * 3 + 1;
+// frames a written byte stays highlighted
+const WRITE_AGE: u8 = 30;
+// while running, only redraw every few frames to keep the UI responsive
+const REFRESH_INTERVAL: u32 = 6;
+
+pub struct MemoryViewer {
+    window: Window,
+    buffer: TextBuffer,
+    poke: Entry,
+    // what the buffer currently shows
+    shown: [u8; MEMORY_SIZE],
+    // the previous snapshot, used to spot writes
+    last: Option<Snapshot>,
+    writes: [u8; MEMORY_SIZE],
+    frames: u32,
+}
+
+impl MemoryViewer {
+    pub fn new(root: &Window, sender: ComponentSender<Application>) -> Self {
+        let window = Window::builder()
+            .title("Memory")
+            .transient_for(root)
+            .destroy_with_parent(true)
+            .hide_on_close(true)
+            .default_width(620)
+            .default_height(640)
+            .build();
+
+        let buffer = TextBuffer::new(None);
+        let mut text = String::new();
+        for row in 0..MEMORY_SIZE / ROW {
+            text.push_str(&Self::line(row * ROW, &[0; ROW]));
+        }
+        buffer.set_text(&text);
+        buffer.create_tag(Some("program"), &[("background", &"#1e3a5f")]);
+        buffer.create_tag(Some("font"), &[("background", &"#3f3f3f")]);
+        buffer.create_tag(Some("write"), &[("background", &"#7a5c00")]);
+        buffer.create_tag(Some("i"), &[("background", &"#2e7d32")]);
+        buffer.create_tag(Some("pc"), &[("background", &"#b71c1c")]);
+
+        let view = TextView::with_buffer(&buffer);
+        view.set_monospace(true);
+        view.set_editable(false);
+        view.set_cursor_visible(false);
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vexpand(true)
+            .child(&view)
+            .build();
+
+        let poke = Entry::builder()
+            .placeholder_text("address value (hex), editable while paused")
+            .sensitive(false)
+            .build();
+        poke.set_margin_all(5);
+        poke.connect_activate(move |entry| match Self::parse_poke(&entry.text()) {
+            Some((address, value)) => {
+                sender.input(Message::Poke(address, value));
+                entry.set_text("");
+            }
+            None => entry.error_bell(),
+        });
+
+        let layout = Box::new(Orientation::Vertical, 0);
+        layout.append(&scrolled);
+        layout.append(&poke);
+        window.set_child(Some(&layout));
+
+        return Self {
+            window,
+            buffer,
+            poke,
+            shown: [0; MEMORY_SIZE],
+            last: None,
+            writes: [0; MEMORY_SIZE],
+            frames: 0,
+        };
+    }
+
+    pub fn show(&mut self) {
+        self.window.present();
+        if let Some(snapshot) = self.last.take() {
+            self.redraw(&snapshot);
+            self.last = Some(snapshot);
+        }
+    }
+
+    pub fn update(&mut self, snapshot: Snapshot) {
+        for age in self.writes.iter_mut() {
+            *age = age.saturating_sub(1);
+        }
+        if let Some(last) = &self.last {
+            for (address, byte) in snapshot.memory.iter().enumerate() {
+                if last.memory[address] != *byte {
+                    self.writes[address] = WRITE_AGE;
+                }
+            }
+        }
+        self.poke.set_sensitive(snapshot.paused);
+
+        self.frames = self.frames.wrapping_add(1);
+        if self.window.is_visible() && (snapshot.paused || self.frames % REFRESH_INTERVAL == 0) {
+            self.redraw(&snapshot);
+        }
+        self.last = Some(snapshot);
+    }
+
+    fn redraw(&mut self, snapshot: &Snapshot) {
+        for row in 0..MEMORY_SIZE / ROW {
+            let start = row * ROW;
+            let bytes = &snapshot.memory[start..start + ROW];
+            if bytes != &self.shown[start..start + ROW] {
+                let mut line_start = self.buffer.iter_at_line(row as i32).unwrap();
+                let mut line_end = self
+                    .buffer
+                    .iter_at_line_offset(row as i32, ASCII_START as i32 + ROW as i32)
+                    .unwrap();
+                self.buffer.delete(&mut line_start, &mut line_end);
+                let line = Self::line(start, bytes);
+                self.buffer
+                    .insert(&mut line_start, line.trim_end_matches('\n'));
+                self.shown[start..start + ROW].copy_from_slice(bytes);
+            }
+        }
+
+        let (start, end) = self.buffer.bounds();
+        self.buffer.remove_all_tags(&start, &end);
+        self.highlight("program", snapshot.program.clone());
+        self.highlight("font", snapshot.font.clone());
+        for address in 0..MEMORY_SIZE {
+            if self.writes[address] > 0 {
+                self.highlight("write", address..address + 1);
+            }
+        }
+        let registers = &snapshot.registers;
+        self.highlight("i", registers.i..registers.i + 1);
+        // opcodes are two bytes wide
+        self.highlight(
+            "pc",
+            registers.program_counter..registers.program_counter + 2,
+        );
+    }
+
+    fn highlight(&self, tag: &str, range: Range<usize>) {
+        let range = range.start.min(MEMORY_SIZE)..range.end.min(MEMORY_SIZE);
+        let mut address = range.start;
+        while address < range.end {
+            let row = address / ROW;
+            let first = address % ROW;
+            let last = (range.end - row * ROW).min(ROW);
+            let columns = [
+                (HEX_START + first * 3, HEX_START + last * 3 - 1),
+                (ASCII_START + first, ASCII_START + last),
+            ];
+            for (from, to) in columns {
+                let from = self.buffer.iter_at_line_offset(row as i32, from as i32);
+                let to = self.buffer.iter_at_line_offset(row as i32, to as i32);
+                if let (Some(from), Some(to)) = (from, to) {
+                    self.buffer.apply_tag_by_name(tag, &from, &to);
+                }
+            }
+            address = (row + 1) * ROW;
+        }
+    }
+
+    fn line(address: usize, bytes: &[u8]) -> String {
+        let mut line = format!("{:04X}: ", address);
+        for byte in bytes.iter() {
+            line.push_str(&format!("{:02X} ", byte));
+        }
+        line.push(' ');
+        for byte in bytes.iter() {
+            line.push(match byte {
+                0x20..=0x7E => *byte as char,
+                _ => '.',
+            });
+        }
+        line.push('\n');
+        return line;
+    }
+
+    fn parse_poke(text: &str) -> Option<(usize, u8)> {
+        let mut parts = text.split_whitespace();
+        let address = usize::from_str_radix(parts.next()?.trim_start_matches("0x"), 16).ok()?;
+        let value = u8::from_str_radix(parts.next()?.trim_start_matches("0x"), 16).ok()?;
+        if parts.next().is_some() || address >= MEMORY_SIZE {
+            return None;
+        }
+        return Some((address, value));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{Application, Message};
+use chip8_emulator::emulator::Reset;
+use relm4::{
+    actions::{ActionGroupName, ActionName, RelmAction, RelmActionGroup},
+    gtk::{
+        gio,
+        glib::ToVariant,
+        traits::{GtkApplicationExt, WidgetExt},
+        PopoverMenuBar, Window,
+    },
+    ComponentSender,
+};
+
+relm4::new_action_group!(WindowActionGroup, "win");
+relm4::new_stateless_action!(OpenAction, WindowActionGroup, "open");
+relm4::new_stateless_action!(PauseAction, WindowActionGroup, "pause");
+relm4::new_stateless_action!(FrameAdvanceAction, WindowActionGroup, "frame-advance");
+relm4::new_stateless_action!(SoftResetAction, WindowActionGroup, "soft-reset");
+relm4::new_stateless_action!(HardResetAction, WindowActionGroup, "hard-reset");
+relm4::new_stateless_action!(SlowMotionAction, WindowActionGroup, "slow-motion");
+relm4::new_stateless_action!(FastForwardAction, WindowActionGroup, "fast-forward");
+relm4::new_stateless_action!(MemoryViewerAction, WindowActionGroup, "memory-viewer");
+relm4::new_stateless_action!(RegistersAction, WindowActionGroup, "registers");
+relm4::new_stateless_action!(CheatsAction, WindowActionGroup, "cheats");
+
+fn add_action<Name>(
+    group: &RelmActionGroup<WindowActionGroup>,
+    sender: &ComponentSender<Application>,
+    message: fn() -> Message,
+) where
+    Name: ActionName<Group = WindowActionGroup, Target = (), State = ()>,
+{
+    let sender = sender.clone();
+    let action = RelmAction::<Name>::new_stateless(move |_| {
+        sender.input(message());
+    });
+    group.add_action(&action);
+}
+
+// The emulation hotkeys are handled by the keyboard controller, so the menu
+// only displays them instead of registering them as accelerators.
+fn item<Name>(label: &str, hotkey: &str) -> gio::MenuItem
+where
+    Name: ActionName<Target = ()>,
+{
+    let item = RelmAction::<Name>::to_menu_item(label);
+    item.set_attribute_value("accel", Some(&hotkey.to_variant()));
+    return item;
+}
+
+pub fn build(root: &Window, sender: ComponentSender<Application>) -> PopoverMenuBar {
+    let group = RelmActionGroup::<WindowActionGroup>::new();
+    add_action::<OpenAction>(&group, &sender, || Message::Open);
+    add_action::<PauseAction>(&group, &sender, || Message::Pause);
+    add_action::<FrameAdvanceAction>(&group, &sender, || Message::FrameAdvance);
+    add_action::<SoftResetAction>(&group, &sender, || Message::Reset(Reset::Soft));
+    add_action::<HardResetAction>(&group, &sender, || Message::Reset(Reset::Hard));
+    add_action::<SlowMotionAction>(&group, &sender, || Message::SlowMotion);
+    add_action::<FastForwardAction>(&group, &sender, || Message::FastForward);
+    add_action::<MemoryViewerAction>(&group, &sender, || Message::ShowMemory);
+    add_action::<RegistersAction>(&group, &sender, || Message::ShowRegisters);
+    add_action::<CheatsAction>(&group, &sender, || Message::ShowCheats);
+
+    root.insert_action_group(WindowActionGroup::NAME, Some(&group.into_action_group()));
+    relm4::main_application().set_accels_for_action("win.open", &["<Control>o"]);
+
+    let file = gio::Menu::new();
+    file.append_item(&RelmAction::<OpenAction>::to_menu_item("Open…"));
+
+    let emulation = gio::Menu::new();
+    emulation.append_item(&item::<PauseAction>("Pause / Resume", "F5"));
+    emulation.append_item(&item::<FrameAdvanceAction>("Frame Advance", "F6"));
+    emulation.append_item(&item::<SoftResetAction>("Soft Reset", "F7"));
+    emulation.append_item(&item::<HardResetAction>("Hard Reset", "F8"));
+    emulation.append_item(&item::<SlowMotionAction>("Slow Motion", "F9"));
+    emulation.append_item(&item::<FastForwardAction>("Fast Forward", "F10"));
+
+    let debug = gio::Menu::new();
+    debug.append_item(&item::<MemoryViewerAction>("Memory Viewer", "F3"));
+    debug.append_item(&item::<RegistersAction>("Registers", "F4"));
+    debug.append_item(&RelmAction::<CheatsAction>::to_menu_item("Cheats…"));
+
+    let menu = gio::Menu::new();
+    menu.append_submenu(Some("File"), &file);
+    menu.append_submenu(Some("Emulation"), &emulation);
+    menu.append_submenu(Some("Debug"), &debug);
+    return PopoverMenuBar::from_model(Some(&menu));
+}
@@ -0,0 +1,874 @@
+mod test;
+
+use crate::emulator::Symbols;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+// Octo programs are assembled to run from 0x200
+const START: usize = 0x200;
+// XO-CHIP's address space, the most any program can fill
+const MEMORY_SIZE: usize = 0x10000;
+// expanding a macro that expands itself would never end
+const MAX_EXPANSIONS: usize = 10000;
+// :calc parses recursively, so nesting has to stop before the stack does
+const MAX_NESTING: usize = 500;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct OctoError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for OctoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+
+impl std::error::Error for OctoError {}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+// Splits source into whitespace separated tokens, dropping '#' comments and
+// keeping quoted strings whole
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (n, line) in source.lines().enumerate() {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '#' {
+                break;
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+            let mut text = String::from(c);
+            if c == '"' {
+                for c in chars.by_ref() {
+                    text.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+            } else {
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    text.push(*c);
+                    chars.next();
+                }
+            }
+            tokens.push(Token { text, line: n + 1 });
+        }
+    }
+    return tokens;
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    return Some(if negative { -value } else { value });
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+    if digit.len() != 1 {
+        return None;
+    }
+    return u8::from_str_radix(digit, 16).ok();
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Fixup {
+    // the low twelve bits of an instruction
+    Address,
+    // the two instructions :unpack emits, with their high nibble
+    Unpack(u8),
+    // the sixteen bit word after i := long
+    Long,
+}
+
+struct Macro {
+    arguments: Vec<String>,
+    body: Vec<Token>,
+}
+
+// What a condition compiles to: any instructions that work it out into vF,
+// then the skips taken when it holds and when it doesn't.
+struct Condition {
+    prelude: Vec<u16>,
+    skip_if_true: u16,
+    skip_if_false: u16,
+}
+
+struct Assembler {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+    memory: Vec<u8>,
+    here: usize,
+    end: usize,
+    labels: HashMap<String, usize>,
+    // label names in the order they were defined
+    defined: Vec<String>,
+    // the source line of the instruction at each address
+    lines: BTreeMap<usize, usize>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<(usize, String, Fixup, usize)>,
+    // jumps still waiting for the end of an if ... begin block
+    branches: Vec<usize>,
+    // where each open loop starts, and the jumps out of it
+    loops: Vec<(usize, Vec<usize>)>,
+    expansions: usize,
+}
+
+// Assembles Octo source into a program image that loads at 0x200. This
+// covers the CHIP-8, SCHIP and XO-CHIP instructions, labels, constants,
+// aliases, macros and :calc, but not :stringmode.
+pub fn assemble(source: &str) -> Result<Vec<u8>, OctoError> {
+    return assemble_with_symbols(source, "").map(|(program, _)| program);
+}
+
+// The same, along with every label and the line each instruction came from,
+// as if the source had been read from `file`
+pub fn assemble_with_symbols(source: &str, file: &str) -> Result<(Vec<u8>, Symbols), OctoError> {
+    let mut assembler = Assembler {
+        tokens: tokenize(source),
+        pos: 0,
+        line: 1,
+        memory: vec![0; MEMORY_SIZE],
+        here: START,
+        end: START,
+        labels: HashMap::new(),
+        defined: Vec::new(),
+        lines: BTreeMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        macros: HashMap::new(),
+        fixups: Vec::new(),
+        branches: Vec::new(),
+        loops: Vec::new(),
+        expansions: 0,
+    };
+    while assembler.pos < assembler.tokens.len() {
+        assembler.statement()?;
+    }
+    let mut symbols = Symbols::default();
+    for name in assembler.defined.iter() {
+        symbols.insert_label(name, assembler.labels[name]);
+    }
+    for (address, line) in assembler.lines.iter() {
+        symbols.insert_line(*address, file, *line);
+    }
+    return Ok((assembler.finish()?, symbols));
+}
+
+impl Assembler {
+    fn error<T>(&self, message: String) -> Result<T, OctoError> {
+        return Err(OctoError {
+            line: self.line,
+            message,
+        });
+    }
+
+    fn next(&mut self) -> Result<String, OctoError> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return self.error(String::from("unexpected end of program"));
+        };
+        self.line = token.line;
+        self.pos += 1;
+        return Ok(token.text.clone());
+    }
+
+    fn peek(&self) -> Option<&str> {
+        return self.tokens.get(self.pos).map(|token| token.text.as_str());
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), OctoError> {
+        let token = self.next()?;
+        if token != expected {
+            return self.error(format!("expected '{}', found '{}'", expected, token));
+        }
+        return Ok(());
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<(), OctoError> {
+        if self.here >= MEMORY_SIZE {
+            return self.error(String::from("program does not fit in memory"));
+        }
+        self.memory[self.here] = byte;
+        self.here += 1;
+        self.end = self.end.max(self.here);
+        return Ok(());
+    }
+
+    fn emit(&mut self, opcode: u16) -> Result<(), OctoError> {
+        self.lines.insert(self.here, self.line);
+        self.emit_byte((opcode >> 8) as u8)?;
+        return self.emit_byte(opcode as u8);
+    }
+
+    fn register(&mut self) -> Result<u8, OctoError> {
+        let token = self.next()?;
+        return match self.register_named(&token) {
+            Some(register) => Ok(register),
+            None => self.error(format!("expected a register, found '{}'", token)),
+        };
+    }
+
+    fn register_named(&self, token: &str) -> Option<u8> {
+        return parse_register(token).or_else(|| self.aliases.get(token).copied());
+    }
+
+    fn is_register(&self, token: Option<&str>) -> bool {
+        return token.is_some_and(|token| self.register_named(token).is_some());
+    }
+
+    // A number, constant or label already defined
+    fn known(&self, token: &str) -> Option<i64> {
+        if let Some(value) = parse_number(token) {
+            return Some(value);
+        }
+        if let Some(value) = self.constants.get(token) {
+            return Some(value.floor() as i64);
+        }
+        return self.labels.get(token).map(|address| *address as i64);
+    }
+
+    fn value(&mut self, min: i64, max: i64) -> Result<i64, OctoError> {
+        let token = self.next()?;
+        return match self.known(&token) {
+            Some(value) if (min..=max).contains(&value) => Ok(value),
+            Some(value) => self.error(format!("{} is out of range", value)),
+            None => self.error(format!("undefined name '{}'", token)),
+        };
+    }
+
+    fn byte(&mut self) -> Result<u8, OctoError> {
+        return Ok(self.value(-128, 255)? as u8);
+    }
+
+    fn nibble(&mut self) -> Result<u16, OctoError> {
+        return Ok(self.value(0, 15)? as u16);
+    }
+
+    // An address for the instruction at self.here, patched in later if the
+    // label isn't defined yet
+    fn reference(&mut self, fixup: Fixup, max: usize) -> Result<usize, OctoError> {
+        let token = self.next()?;
+        if let Some(value) = self.known(&token) {
+            if value < 0 || value as usize > max {
+                return self.error(format!("address {} is out of range", value));
+            }
+            return Ok(value as usize);
+        }
+        if parse_register(&token).is_some() || token.starts_with(':') {
+            return self.error(format!("expected an address, found '{}'", token));
+        }
+        self.fixups.push((self.here, token, fixup, self.line));
+        return Ok(0);
+    }
+
+    fn patch(&mut self, at: usize, target: usize) -> Result<(), OctoError> {
+        if target > 0xFFF {
+            return self.error(format!("cannot jump to {:X}", target));
+        }
+        self.memory[at] = self.memory[at] & 0xF0 | (target >> 8) as u8;
+        self.memory[at + 1] = target as u8;
+        return Ok(());
+    }
+
+    fn statement(&mut self) -> Result<(), OctoError> {
+        let token = self.next()?;
+        match token.as_str() {
+            ":" => {
+                let name = self.next()?;
+                self.define(name, self.here)?;
+            }
+            ":next" => {
+                let name = self.next()?;
+                self.define(name, self.here + 1)?;
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.value(i64::MIN, i64::MAX)?;
+                self.constants.insert(name, value as f64);
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":org" => {
+                let address = self.value(START as i64, MEMORY_SIZE as i64 - 1)?;
+                self.here = address as usize;
+            }
+            ":byte" => {
+                let byte = match self.peek() {
+                    Some("{") => {
+                        let value = self.calc()?;
+                        if !(-128.0..256.0).contains(&value) {
+                            return self.error(format!("{} is out of range", value));
+                        }
+                        value.floor() as i64 as u8
+                    }
+                    _ => self.byte()?,
+                };
+                self.emit_byte(byte)?;
+            }
+            ":call" => {
+                let address = self.reference(Fixup::Address, 0xFFF)?;
+                self.emit(0x2000 | address as u16)?;
+            }
+            ":unpack" => {
+                let (nibble, fixup, max) = match self.peek() {
+                    Some("long") => {
+                        self.next()?;
+                        (0, Fixup::Unpack(0), 0xFFFF)
+                    }
+                    _ => {
+                        let nibble = self.nibble()? as u8;
+                        (nibble, Fixup::Unpack(nibble), 0xFFF)
+                    }
+                };
+                let address = self.reference(fixup, max)?;
+                self.emit(0x6000 | (nibble as u16) << 4 | (address >> 8) as u16)?;
+                self.emit(0x6100 | (address & 0xFF) as u16)?;
+            }
+            ":macro" => self.define_macro()?,
+            ":calc" => {
+                let name = self.next()?;
+                let value = self.calc()?;
+                self.constants.insert(name, value);
+            }
+            ":assert" => {
+                if self.peek().is_some_and(|token| token.starts_with('"')) {
+                    self.next()?;
+                }
+                if self.calc()? == 0.0 {
+                    return self.error(String::from("assertion failed"));
+                }
+            }
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            ":stringmode" => return self.error(String::from(":stringmode is not supported")),
+            "clear" => self.emit(0x00E0)?,
+            "return" | ";" => self.emit(0x00EE)?,
+            "hires" => self.emit(0x00FF)?,
+            "lores" => self.emit(0x00FE)?,
+            "exit" => self.emit(0x00FD)?,
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n)?;
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(0x00D0 | n)?;
+            }
+            "scroll-right" => self.emit(0x00FB)?,
+            "scroll-left" => self.emit(0x00FC)?,
+            "audio" => self.emit(0xF002)?,
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(0xF001 | n << 8)?;
+            }
+            "jump" => {
+                let address = self.reference(Fixup::Address, 0xFFF)?;
+                self.emit(0x1000 | address as u16)?;
+            }
+            "jump0" => {
+                let address = self.reference(Fixup::Address, 0xFFF)?;
+                self.emit(0xB000 | address as u16)?;
+            }
+            "native" => {
+                let address = self.reference(Fixup::Address, 0xFFF)?;
+                self.emit(address as u16)?;
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble()?;
+                self.emit(0xD000 | x << 8 | y << 4 | n)?;
+            }
+            "bcd" => {
+                let x = self.register()? as u16;
+                self.emit(0xF033 | x << 8)?;
+            }
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                let store = token == "save";
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()? as u16;
+                    self.emit(if store { 0x5002 } else { 0x5003 } | x << 8 | y << 4)?;
+                } else {
+                    self.emit(if store { 0xF055 } else { 0xF065 } | x << 8)?;
+                }
+            }
+            "saveflags" => {
+                let x = self.register()? as u16;
+                self.emit(0xF075 | x << 8)?;
+            }
+            "loadflags" => {
+                let x = self.register()? as u16;
+                self.emit(0xF085 | x << 8)?;
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()? as u16;
+                let low = match token.as_str() {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+                self.emit(0xF000 | x << 8 | low)?;
+            }
+            "i" => self.index()?,
+            "if" => self.conditional()?,
+            "else" => {
+                let Some(branch) = self.branches.pop() else {
+                    return self.error(String::from("'else' without 'if ... begin'"));
+                };
+                let jump = self.here;
+                self.emit(0x1000)?;
+                self.patch(branch, self.here)?;
+                self.branches.push(jump);
+            }
+            "end" => {
+                let Some(branch) = self.branches.pop() else {
+                    return self.error(String::from("'end' without 'if ... begin'"));
+                };
+                self.patch(branch, self.here)?;
+            }
+            "loop" => self.loops.push((self.here, Vec::new())),
+            "while" => {
+                let condition = self.condition()?;
+                let exit = self.here + condition.prelude.len() * 2 + 2;
+                let Some((_, exits)) = self.loops.last_mut() else {
+                    return self.error(String::from("'while' outside of a loop"));
+                };
+                exits.push(exit);
+                for opcode in condition.prelude {
+                    self.emit(opcode)?;
+                }
+                self.emit(condition.skip_if_true)?;
+                self.emit(0x1000)?;
+            }
+            "again" => {
+                let Some((start, exits)) = self.loops.pop() else {
+                    return self.error(String::from("'again' without 'loop'"));
+                };
+                if start > 0xFFF {
+                    return self.error(format!("cannot jump to {:X}", start));
+                }
+                self.emit(0x1000 | start as u16)?;
+                for exit in exits {
+                    self.patch(exit, self.here)?;
+                }
+            }
+            _ if self.is_register(Some(&token)) => {
+                let x = self.register_named(&token).unwrap_or(0);
+                self.assignment(x)?;
+            }
+            _ if self.macros.contains_key(&token) => self.expand(&token)?,
+            _ => match self.known(&token) {
+                // bare numbers and constants are data
+                Some(value) if !self.labels.contains_key(&token) => {
+                    if !(-128..=255).contains(&value) {
+                        return self.error(format!("{} is out of range", value));
+                    }
+                    self.emit_byte(value as u8)?;
+                }
+                // and anything else names a subroutine to call
+                _ => {
+                    self.pos -= 1;
+                    let address = self.reference(Fixup::Address, 0xFFF)?;
+                    self.emit(0x2000 | address as u16)?;
+                }
+            },
+        }
+        return Ok(());
+    }
+
+    fn define(&mut self, name: String, address: usize) -> Result<(), OctoError> {
+        if self.labels.contains_key(&name) {
+            return self.error(format!("'{}' is already defined", name));
+        }
+        if parse_number(&name).is_some() || parse_register(&name).is_some() {
+            return self.error(format!("'{}' cannot be a name", name));
+        }
+        self.defined.push(name.clone());
+        self.labels.insert(name, address);
+        return Ok(());
+    }
+
+    fn index(&mut self) -> Result<(), OctoError> {
+        let op = self.next()?;
+        match op.as_str() {
+            ":=" => match self.peek() {
+                Some("hex") | Some("bighex") => {
+                    let low = if self.next()? == "hex" { 0x29 } else { 0x30 };
+                    let x = self.register()? as u16;
+                    self.emit(0xF000 | x << 8 | low)?;
+                }
+                Some("long") => {
+                    self.next()?;
+                    self.emit(0xF000)?;
+                    let address = self.reference(Fixup::Long, 0xFFFF)?;
+                    self.emit(address as u16)?;
+                }
+                _ => {
+                    let address = self.reference(Fixup::Address, 0xFFF)?;
+                    self.emit(0xA000 | address as u16)?;
+                }
+            },
+            "+=" => {
+                let x = self.register()? as u16;
+                self.emit(0xF01E | x << 8)?;
+            }
+            _ => return self.error(format!("unknown operator 'i {}'", op)),
+        }
+        return Ok(());
+    }
+
+    fn assignment(&mut self, x: u8) -> Result<(), OctoError> {
+        let x = x as u16;
+        let op = self.next()?;
+        let with_register = self.is_register(self.peek());
+        let opcode = match (op.as_str(), with_register) {
+            (":=", true) => 0x8000 | x << 8 | (self.register()? as u16) << 4,
+            ("|=", true) => 0x8001 | x << 8 | (self.register()? as u16) << 4,
+            ("&=", true) => 0x8002 | x << 8 | (self.register()? as u16) << 4,
+            ("^=", true) => 0x8003 | x << 8 | (self.register()? as u16) << 4,
+            ("+=", true) => 0x8004 | x << 8 | (self.register()? as u16) << 4,
+            ("-=", true) => 0x8005 | x << 8 | (self.register()? as u16) << 4,
+            (">>=", true) => 0x8006 | x << 8 | (self.register()? as u16) << 4,
+            ("=-", true) => 0x8007 | x << 8 | (self.register()? as u16) << 4,
+            ("<<=", true) => 0x800E | x << 8 | (self.register()? as u16) << 4,
+            (":=", false) => match self.peek() {
+                Some("random") => {
+                    self.next()?;
+                    0xC000 | x << 8 | self.byte()? as u16
+                }
+                Some("key") => {
+                    self.next()?;
+                    0xF00A | x << 8
+                }
+                Some("delay") => {
+                    self.next()?;
+                    0xF007 | x << 8
+                }
+                _ => 0x6000 | x << 8 | self.byte()? as u16,
+            },
+            ("+=", false) => 0x7000 | x << 8 | self.byte()? as u16,
+            ("-=", false) => 0x7000 | x << 8 | (self.byte()?.wrapping_neg()) as u16,
+            _ => return self.error(format!("unknown operator '{}'", op)),
+        };
+        return self.emit(opcode);
+    }
+
+    fn condition(&mut self) -> Result<Condition, OctoError> {
+        let x = self.register()? as u16;
+        let op = self.next()?;
+        let simple = |skip_if_true: u16, skip_if_false: u16| Condition {
+            prelude: Vec::new(),
+            skip_if_true,
+            skip_if_false,
+        };
+        if op == "key" {
+            return Ok(simple(0xE09E | x << 8, 0xE0A1 | x << 8));
+        }
+        if op == "-key" {
+            return Ok(simple(0xE0A1 | x << 8, 0xE09E | x << 8));
+        }
+        let y = match self.is_register(self.peek()) {
+            true => Some(self.register()? as u16),
+            false => None,
+        };
+        let n = match y {
+            Some(_) => 0,
+            None => self.byte()? as u16,
+        };
+        match (op.as_str(), y) {
+            ("==", Some(y)) => {
+                return Ok(simple(0x5000 | x << 8 | y << 4, 0x9000 | x << 8 | y << 4))
+            }
+            ("!=", Some(y)) => {
+                return Ok(simple(0x9000 | x << 8 | y << 4, 0x5000 | x << 8 | y << 4))
+            }
+            ("==", None) => return Ok(simple(0x3000 | x << 8 | n, 0x4000 | x << 8 | n)),
+            ("!=", None) => return Ok(simple(0x4000 | x << 8 | n, 0x3000 | x << 8 | n)),
+            _ => {}
+        }
+
+        // the rest work out "a >= b" into vF, which is then 1 or 0
+        let (greater_or_equal, swap) = match op.as_str() {
+            ">=" => (true, false),
+            "<" => (false, false),
+            "<=" => (true, true),
+            ">" => (false, true),
+            _ => return self.error(format!("unknown comparison '{}'", op)),
+        };
+        let prelude = match (y, swap) {
+            (Some(y), false) => vec![0x8F00 | x << 4, 0x8F05 | y << 4],
+            (Some(y), true) => vec![0x8F00 | y << 4, 0x8F05 | x << 4],
+            (None, false) => vec![0x6F00 | n, 0x8F07 | x << 4],
+            (None, true) => vec![0x6F00 | n, 0x8F05 | x << 4],
+        };
+        let (skip_if_true, skip_if_false) = match greater_or_equal {
+            true => (0x4F00, 0x3F00),
+            false => (0x3F00, 0x4F00),
+        };
+        return Ok(Condition {
+            prelude,
+            skip_if_true,
+            skip_if_false,
+        });
+    }
+
+    fn conditional(&mut self) -> Result<(), OctoError> {
+        let condition = self.condition()?;
+        for opcode in condition.prelude.iter() {
+            self.emit(*opcode)?;
+        }
+        let token = self.next()?;
+        match token.as_str() {
+            "then" => self.emit(condition.skip_if_false)?,
+            "begin" => {
+                self.emit(condition.skip_if_true)?;
+                self.branches.push(self.here);
+                self.emit(0x1000)?;
+            }
+            _ => return self.error(format!("expected 'then' or 'begin', found '{}'", token)),
+        }
+        return Ok(());
+    }
+
+    // the tokens between a pair of braces, which may nest
+    fn block(&mut self) -> Result<Vec<Token>, OctoError> {
+        self.expect("{")?;
+        let mut depth = 1;
+        let mut body = Vec::new();
+        loop {
+            let token = self.next()?;
+            match token.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(body);
+            }
+            body.push(Token {
+                text: token,
+                line: self.line,
+            });
+        }
+    }
+
+    fn define_macro(&mut self) -> Result<(), OctoError> {
+        let name = self.next()?;
+        let mut arguments = Vec::new();
+        while self.peek().is_some_and(|token| token != "{") {
+            arguments.push(self.next()?);
+        }
+        let body = self.block()?;
+        self.macros.insert(name, Macro { arguments, body });
+        return Ok(());
+    }
+
+    fn expand(&mut self, name: &str) -> Result<(), OctoError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return self.error(format!("macro '{}' expands forever", name));
+        }
+        let count = self.macros[name].arguments.len();
+        let mut values = HashMap::new();
+        for n in 0..count {
+            let value = self.next()?;
+            values.insert(self.macros[name].arguments[n].clone(), value);
+        }
+        let line = self.line;
+        let body: Vec<Token> = self.macros[name]
+            .body
+            .iter()
+            .map(|token| Token {
+                text: values.get(&token.text).unwrap_or(&token.text).clone(),
+                line,
+            })
+            .collect();
+        self.tokens.splice(self.pos..self.pos, body);
+        return Ok(());
+    }
+
+    // A braced :calc expression. Octo evaluates these right to left with no
+    // operator precedence, so "1 + 2 * 3" is 7 but "2 * 3 + 1" is 8.
+    fn calc(&mut self) -> Result<f64, OctoError> {
+        let tokens = self.block()?;
+        let mut pos = 0;
+        let value = self.expression(&tokens, &mut pos, 0)?;
+        if pos < tokens.len() {
+            return self.error(format!("unexpected '{}' in expression", tokens[pos].text));
+        }
+        return Ok(value);
+    }
+
+    fn expression(
+        &self,
+        tokens: &[Token],
+        pos: &mut usize,
+        depth: usize,
+    ) -> Result<f64, OctoError> {
+        if depth > MAX_NESTING {
+            return self.error(String::from("expression nested too deeply"));
+        }
+        let left = self.term(tokens, pos, depth + 1)?;
+        let Some(op) = tokens.get(*pos) else {
+            return Ok(left);
+        };
+        let integer = |f: fn(i64, i64) -> i64, a: f64, b: f64| f(a as i64, b as i64) as f64;
+        let apply: fn(f64, f64) -> f64 = match op.text.as_str() {
+            "+" => |a, b| a + b,
+            "-" => |a, b| a - b,
+            "*" => |a, b| a * b,
+            "/" => |a, b| a / b,
+            "%" => |a, b| a % b,
+            "pow" => f64::powf,
+            "min" => f64::min,
+            "max" => f64::max,
+            "<" => |a, b| (a < b) as i64 as f64,
+            ">" => |a, b| (a > b) as i64 as f64,
+            "<=" => |a, b| (a <= b) as i64 as f64,
+            ">=" => |a, b| (a >= b) as i64 as f64,
+            "==" => |a, b| (a == b) as i64 as f64,
+            "!=" => |a, b| (a != b) as i64 as f64,
+            "&" | "|" | "^" | "<<" | ">>" => {
+                let f: fn(i64, i64) -> i64 = match op.text.as_str() {
+                    "&" => |a, b| a & b,
+                    "|" => |a, b| a | b,
+                    "^" => |a, b| a ^ b,
+                    "<<" => |a, b| a.checked_shl(b as u32).unwrap_or(0),
+                    _ => |a, b| a.checked_shr(b as u32).unwrap_or(0),
+                };
+                *pos += 1;
+                let right = self.expression(tokens, pos, depth + 1)?;
+                return Ok(integer(f, left, right));
+            }
+            _ => return Ok(left),
+        };
+        *pos += 1;
+        let right = self.expression(tokens, pos, depth + 1)?;
+        return Ok(apply(left, right));
+    }
+
+    fn term(&self, tokens: &[Token], pos: &mut usize, depth: usize) -> Result<f64, OctoError> {
+        if depth > MAX_NESTING {
+            return self.error(String::from("expression nested too deeply"));
+        }
+        let Some(token) = tokens.get(*pos) else {
+            return self.error(String::from("expression ends too soon"));
+        };
+        *pos += 1;
+        let unary: Option<fn(f64) -> f64> = match token.text.as_str() {
+            "-" => Some(|a| -a),
+            "~" => Some(|a| !(a as i64) as f64),
+            "!" => Some(|a| (a == 0.0) as i64 as f64),
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "tan" => Some(f64::tan),
+            "exp" => Some(f64::exp),
+            "log" => Some(f64::ln),
+            "abs" => Some(f64::abs),
+            "sqrt" => Some(f64::sqrt),
+            "sign" => Some(f64::signum),
+            "ceil" => Some(f64::ceil),
+            "floor" => Some(f64::floor),
+            _ => None,
+        };
+        if let Some(unary) = unary {
+            return Ok(unary(self.term(tokens, pos, depth + 1)?));
+        }
+        return match token.text.as_str() {
+            "(" => {
+                let value = self.expression(tokens, pos, depth + 1)?;
+                match tokens.get(*pos) {
+                    Some(close) if close.text == ")" => {
+                        *pos += 1;
+                        Ok(value)
+                    }
+                    _ => self.error(String::from("missing ')' in expression")),
+                }
+            }
+            // the byte assembled at an address so far
+            "@" => {
+                let address = self.term(tokens, pos, depth + 1)? as usize;
+                Ok(self.memory.get(address).copied().unwrap_or(0) as f64)
+            }
+            "HERE" => Ok(self.here as f64),
+            "PI" => Ok(std::f64::consts::PI),
+            "E" => Ok(std::f64::consts::E),
+            name => match self.constants.get(name) {
+                Some(value) => Ok(*value),
+                None => match self.known(name) {
+                    Some(value) => Ok(value as f64),
+                    None => self.error(format!("undefined name '{}' in expression", name)),
+                },
+            },
+        };
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, OctoError> {
+        if !self.branches.is_empty() {
+            return self.error(String::from("'if ... begin' without 'end'"));
+        }
+        if !self.loops.is_empty() {
+            return self.error(String::from("'loop' without 'again'"));
+        }
+        for (at, name, fixup, line) in std::mem::take(&mut self.fixups) {
+            self.line = line;
+            let Some(address) = self.labels.get(&name).copied() else {
+                return self.error(format!("undefined name '{}'", name));
+            };
+            match fixup {
+                Fixup::Address => self.patch(at, address)?,
+                Fixup::Unpack(nibble) => {
+                    if address > 0xFFF && nibble != 0 {
+                        return self.error(format!("cannot unpack {:X}", address));
+                    }
+                    self.memory[at + 1] = nibble << 4 | (address >> 8) as u8;
+                    self.memory[at + 3] = address as u8;
+                }
+                Fixup::Long => {
+                    self.memory[at] = (address >> 8) as u8;
+                    self.memory[at + 1] = address as u8;
+                }
+            }
+        }
+        return Ok(self.memory[START..self.end].to_vec());
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(test)]
+use super::*;
+
+#[cfg(test)]
+fn words(program: &[u8]) -> Vec<u16> {
+    return program
+        .chunks(2)
+        .map(|pair| (pair[0] as u16) << 8 | *pair.get(1).unwrap_or(&0) as u16)
+        .collect();
+}
+
+#[test]
+fn test_assemble_instructions() {
+    let source = "
+        : main
+            clear
+            v0 := 5  v1 := v0  v2 += 1  v3 -= 1  v4 += v5  v6 <<= v7
+            i := sprite  sprite v0 v1 5  # draw it
+            i := hex v2  bcd v3  save v4  load v5
+            v9 := random 0xFF  va := key  delay := va
+            draw
+            jump main
+        : draw
+            return
+        : sprite
+            0x3C 0b01111110
+    ";
+    let program = assemble(source).unwrap();
+    assert_eq!(
+        words(&program),
+        vec![
+            0x00E0, 0x6005, 0x8100, 0x7201, 0x73FF, 0x8454, 0x867E, 0xA226, 0xD015, 0xF229, 0xF333,
+            0xF455, 0xF565, 0xC9FF, 0xFA0A, 0xFA15, 0x2224, 0x1200, 0x00EE, 0x3C7E,
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_control_flow() {
+    let source = "
+        loop
+            if v0 == 3 then v1 := 1
+            if v0 != v1 begin
+                v2 := 2
+            else
+                v2 := 3
+            end
+            while v0 key
+            if v0 < 8 then v3 := 4
+        again
+    ";
+    let program = assemble(source).unwrap();
+    assert_eq!(
+        words(&program),
+        vec![
+            0x4003, 0x6101, // if then: skip when false
+            0x9010, 0x120C, 0x6202, 0x120E, 0x6203, // if begin else end
+            0xE09E, 0x121C, // while: out of the loop unless the key is down
+            0x6F08, 0x8F07, 0x4F00, 0x6304, // vF is 1 when v0 >= 8
+            0x1200,
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_directives() {
+    let source = "
+        :const SPEED 3
+        :alias x v4
+        :calc DOUBLE { SPEED * 2 }
+        :macro add-speed register { register += SPEED }
+        x := DOUBLE
+        add-speed x
+        :unpack 0xA data
+        :next target v0 := 0
+        :byte { 1 + 2 * 3 }
+        :org 0x300
+        : data 0xAA
+    ";
+    let program = assemble(source).unwrap();
+    assert_eq!(program.len(), 0x101);
+    assert_eq!(program[0x100], 0xAA);
+    assert_eq!(
+        words(&program[..12]),
+        vec![0x6406, 0x7403, 0x60A3, 0x6100, 0x6000, 0x0700]
+    );
+}
+
+#[test]
+fn test_assemble_errors() {
+    let error = |source: &str| assemble(source).unwrap_err();
+    assert_eq!(
+        error("jump nowhere"),
+        OctoError {
+            line: 1,
+            message: String::from("undefined name 'nowhere'")
+        }
+    );
+    assert_eq!(error("\nv0 := 256").line, 2);
+    assert_eq!(error("loop v0 := 1").message, "'loop' without 'again'");
+    assert_eq!(error(": a : a").message, "'a' is already defined");
+    assert_eq!(
+        error(":macro m { m } m").message,
+        "macro 'm' expands forever"
+    );
+    let nested = format!(
+        ":calc X {{ {}1{} }}",
+        "( ".repeat(10000),
+        " )".repeat(10000)
+    );
+    assert_eq!(error(&nested).message, "expression nested too deeply");
+}
+
+#[test]
+fn test_assemble_with_symbols() {
+    let source = ": main\n  v0 := 1\n  loop\n    sprite v0 v0 1\n  again\n: tile\n  0xFF\n";
+    let (program, symbols) = assemble_with_symbols(source, "game.8o").unwrap();
+    assert_eq!(program.len(), 7);
+    assert_eq!(symbols.label(0x200), Some("main"));
+    assert_eq!(symbols.label(0x206), Some("tile"));
+    assert_eq!(symbols.line(0x200).as_deref(), Some("game.8o:2"));
+    assert_eq!(symbols.line(0x202).as_deref(), Some("game.8o:4"));
+    assert_eq!(symbols.line(0x204).as_deref(), Some("game.8o:5"));
+    // data has no instruction to map
+    assert_eq!(symbols.line(0x206), None);
+}
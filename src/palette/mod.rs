@@ -0,0 +1,159 @@
+mod test;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    pub fn parse(hex: &str) -> Option<Self> {
+        let hex = hex.trim().trim_start_matches('#');
+        if hex.len() != 6 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Self((value >> 16) as u8, (value >> 8) as u8, value as u8));
+    }
+
+    pub fn to_hex(&self) -> String {
+        return format!("#{:02X}{:02X}{:02X}", self.0, self.1, self.2);
+    }
+
+    pub fn css(&self) -> String {
+        return format!("background-color: {}", self.to_hex());
+    }
+
+    pub fn blend(&self, other: Color, amount: f32) -> Color {
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+        return Color(
+            mix(self.0, other.0),
+            mix(self.1, other.1),
+            mix(self.2, other.2),
+        );
+    }
+}
+
+// colors[0] is the background, colors[1] the regular foreground and
+// colors[2] / colors[3] are only reachable with XO-CHIP's second bit plane.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: [Color; 4],
+    pub bezel: Color,
+}
+
+impl Palette {
+    pub fn classic() -> Self {
+        return Self {
+            name: String::from("classic"),
+            colors: [
+                Color(0x00, 0x00, 0x00),
+                Color(0xFF, 0xFF, 0xFF),
+                Color(0xAA, 0xAA, 0xAA),
+                Color(0x55, 0x55, 0x55),
+            ],
+            bezel: Color(0xF4, 0xE6, 0xD1),
+        };
+    }
+
+    pub fn green_phosphor() -> Self {
+        return Self {
+            name: String::from("green"),
+            colors: [
+                Color(0x0A, 0x14, 0x0A),
+                Color(0x33, 0xFF, 0x66),
+                Color(0x1F, 0x99, 0x3D),
+                Color(0x99, 0xFF, 0xB3),
+            ],
+            bezel: Color(0x2B, 0x2B, 0x2B),
+        };
+    }
+
+    pub fn amber() -> Self {
+        return Self {
+            name: String::from("amber"),
+            colors: [
+                Color(0x1A, 0x0F, 0x00),
+                Color(0xFF, 0xB0, 0x00),
+                Color(0x99, 0x66, 0x00),
+                Color(0xFF, 0xD9, 0x80),
+            ],
+            bezel: Color(0x3B, 0x2F, 0x24),
+        };
+    }
+
+    pub fn lcd() -> Self {
+        return Self {
+            name: String::from("lcd"),
+            colors: [
+                Color(0x9B, 0xBC, 0x0F),
+                Color(0x0F, 0x38, 0x0F),
+                Color(0x30, 0x62, 0x30),
+                Color(0x8B, 0xAC, 0x0F),
+            ],
+            bezel: Color(0xC4, 0xCF, 0xA1),
+        };
+    }
+
+    pub fn builtin() -> Vec<Self> {
+        return vec![
+            Self::classic(),
+            Self::green_phosphor(),
+            Self::amber(),
+            Self::lcd(),
+        ];
+    }
+
+    // Parses a user-defined palette such as "#000000 #FFFFFF #AA0000 #00AA00; #F4E6D1".
+    // Two to four pixel colors may be given, optionally followed by the bezel after a ';'.
+    pub fn parse(name: &str, spec: &str) -> Option<Self> {
+        let (pixels, bezel) = match spec.split_once(';') {
+            Some((pixels, bezel)) => (pixels, Some(Color::parse(bezel)?)),
+            None => (spec, None),
+        };
+        let parsed = pixels
+            .split_whitespace()
+            .map(Color::parse)
+            .collect::<Option<Vec<Color>>>()?;
+        if parsed.len() < 2 || parsed.len() > 4 {
+            return None;
+        }
+
+        let mut colors = [parsed[0], parsed[1], parsed[1], parsed[1]];
+        for (i, color) in parsed.iter().enumerate() {
+            colors[i] = *color;
+        }
+        return Some(Self {
+            name: String::from(name),
+            colors,
+            bezel: bezel.unwrap_or(Self::classic().bezel),
+        });
+    }
+
+    pub fn to_spec(&self) -> String {
+        let pixels = self
+            .colors
+            .iter()
+            .map(Color::to_hex)
+            .collect::<Vec<String>>()
+            .join(" ");
+        return format!("{}; {}", pixels, self.bezel.to_hex());
+    }
+
+    pub fn color(&self, pixel: u8) -> Color {
+        return self.colors[(pixel & 0x3) as usize];
+    }
+
+    pub fn css(&self, pixel: u8) -> String {
+        return self.color(pixel).css();
+    }
+
+    pub fn render(&self, display: &[[u8; 128]; 64]) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(128 * 64 * 3);
+        for row in display.iter() {
+            for pixel in row.iter() {
+                let Color(r, g, b) = self.color(*pixel);
+                rgb.extend_from_slice(&[r, g, b]);
+            }
+        }
+        return rgb;
+    }
+}
@@ -0,0 +1,25 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_parse_palette() {
+    let palette = Palette::parse("mine", "#000000 #ff0000; #101010").unwrap();
+    assert_eq!(palette.color(0), Color(0, 0, 0));
+    assert_eq!(palette.color(1), Color(0xFF, 0, 0));
+    assert_eq!(palette.color(3), Color(0xFF, 0, 0));
+    assert_eq!(palette.bezel, Color(0x10, 0x10, 0x10));
+
+    let palette = Palette::parse("xo", "#000000 #111111 #222222 #333333").unwrap();
+    assert_eq!(palette.color(2), Color(0x22, 0x22, 0x22));
+    assert_eq!(palette.bezel, Palette::classic().bezel);
+
+    assert!(Palette::parse("bad", "#000000").is_none());
+    assert!(Palette::parse("bad", "#000000 #GGGGGG").is_none());
+}
+
+#[test]
+fn test_palette_spec_round_trip() {
+    let palette = Palette::amber();
+    let parsed = Palette::parse("amber", &palette.to_spec()).unwrap();
+    assert_eq!(parsed, palette);
+}
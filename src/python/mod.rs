@@ -0,0 +1,99 @@
+use crate::{
+    emulator::{Platform, Settings},
+    gym::{self, Observation, Options, Predicate},
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+fn value_error(err: impl ToString) -> PyErr {
+    return PyValueError::new_err(err.to_string());
+}
+
+// The Python face of gym::Env. Observations come back as bytes so numpy can
+// wrap them without a copy via numpy.frombuffer.
+#[pyclass(name = "Env")]
+struct Env {
+    env: gym::Env,
+    observation: Observation,
+}
+
+#[pymethods]
+impl Env {
+    #[new]
+    #[pyo3(signature = (
+        rom,
+        platform = "modernChip8",
+        frame_skip = 4,
+        observation = "framebuffer",
+        rewards = Vec::new(),
+        done = Vec::new(),
+        actions = None
+    ))]
+    fn new(
+        rom: &[u8],
+        platform: &str,
+        frame_skip: u32,
+        observation: &str,
+        rewards: Vec<(String, f32)>,
+        done: Vec<String>,
+        actions: Option<Vec<u16>>,
+    ) -> PyResult<Self> {
+        let platform = Platform::from_id(platform)
+            .ok_or_else(|| value_error(format!("unknown platform '{}'", platform)))?;
+        let observation = match observation {
+            "framebuffer" => Observation::Framebuffer,
+            "ram" => Observation::Ram,
+            other => return Err(value_error(format!("unknown observation '{}'", other))),
+        };
+        let mut options = Options {
+            settings: Settings::for_platform(platform),
+            frame_skip,
+            observation,
+            ..Options::default()
+        };
+        for (predicate, reward) in rewards {
+            options
+                .rewards
+                .push((Predicate::parse(&predicate).map_err(value_error)?, reward));
+        }
+        for predicate in done {
+            options
+                .done
+                .push(Predicate::parse(&predicate).map_err(value_error)?);
+        }
+        if let Some(actions) = actions {
+            options.actions = actions;
+        }
+        let env = gym::Env::new(rom, options).map_err(value_error)?;
+        return Ok(Self { env, observation });
+    }
+
+    #[getter]
+    fn action_count(&self) -> usize {
+        return self.env.action_count();
+    }
+
+    // (64, 128) for the framebuffer, (4096,) for RAM
+    #[getter]
+    fn observation_shape(&self) -> Vec<usize> {
+        return match self.observation {
+            Observation::Framebuffer => vec![64, 128],
+            Observation::Ram => vec![self.env.observation_size()],
+        };
+    }
+
+    fn reset<'py>(&mut self, py: Python<'py>) -> &'py PyBytes {
+        return PyBytes::new(py, &self.env.reset());
+    }
+
+    // Returns (observation, reward, done).
+    fn step<'py>(&mut self, py: Python<'py>, action: usize) -> PyResult<(&'py PyBytes, f32, bool)> {
+        let step = self.env.step(action).map_err(value_error)?;
+        return Ok((PyBytes::new(py, &step.observation), step.reward, step.done));
+    }
+}
+
+#[pymodule]
+fn chip8_emulator(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<Env>()?;
+    return Ok(());
+}
@@ -0,0 +1,222 @@
+use crate::{Application, Message};
+use chip8_emulator::emulator::{Register, Snapshot, Symbols};
+use relm4::{
+    gtk::{
+        traits::{EditableExt, EntryExt, GridExt, GtkWindowExt, WidgetExt},
+        Align, Entry, Grid, Label, Window,
+    },
+    ComponentSender, RelmWidgetExt,
+};
+
+// registers are refreshed every frame, so highlight them with a class rather
+// than piling up inline providers
+const CSS: &str = "entry.changed { background-color: #7a5c00; }";
+
+// the call stack fills columns of this many entries
+const STACK_ROWS: usize = 16;
+
+struct Field {
+    register: Register,
+    entry: Entry,
+}
+
+impl Field {
+    fn attach(
+        grid: &Grid,
+        sender: &ComponentSender<Application>,
+        name: &str,
+        register: Register,
+        column: i32,
+        row: i32,
+    ) -> Self {
+        let label = Label::builder().label(name).halign(Align::End).build();
+        let entry = Entry::builder()
+            .width_chars(5)
+            .max_width_chars(5)
+            .sensitive(false)
+            .build();
+        let sender = sender.clone();
+        entry.connect_activate(
+            move |entry| match u16::from_str_radix(entry.text().trim(), 16) {
+                Ok(value) if value <= register.max() => {
+                    sender.input(Message::SetRegister(register, value));
+                }
+                _ => entry.error_bell(),
+            },
+        );
+        grid.attach(&label, column * 2, row, 1, 1);
+        grid.attach(&entry, column * 2 + 1, row, 1, 1);
+        return Self { register, entry };
+    }
+}
+
+pub struct RegisterInspector {
+    window: Window,
+    sender: ComponentSender<Application>,
+    fields: Vec<Field>,
+    // the call stack has as many entries as the platform's stack depth, so
+    // it's rebuilt whenever that changes
+    stack_grid: Grid,
+    stack: Vec<Field>,
+    // where the program counter is, by label and source line
+    location: Label,
+    symbols: Symbols,
+    last: Option<Snapshot>,
+}
+
+impl RegisterInspector {
+    pub fn new(root: &Window, sender: ComponentSender<Application>) -> Self {
+        let window = Window::builder()
+            .title("Registers")
+            .transient_for(root)
+            .destroy_with_parent(true)
+            .hide_on_close(true)
+            .resizable(false)
+            .build();
+        relm4::set_global_css(CSS);
+
+        let grid = Grid::builder().row_spacing(2).column_spacing(8).build();
+        grid.set_margin_all(10);
+
+        let registers = [
+            ("PC", Register::ProgramCounter),
+            ("I", Register::I),
+            ("SP", Register::StackPointer),
+            ("DT", Register::DelayTimer),
+            ("ST", Register::SoundTimer),
+        ];
+
+        // registers on the left, the call stack on the right
+        let mut fields = Vec::new();
+        for (row, (name, register)) in registers.iter().enumerate() {
+            fields.push(Field::attach(
+                &grid, &sender, name, *register, 0, row as i32,
+            ));
+        }
+        for n in 0..16 {
+            let name = format!("V{:X}", n);
+            fields.push(Field::attach(
+                &grid,
+                &sender,
+                &name,
+                Register::V(n),
+                1,
+                n as i32,
+            ));
+        }
+        let stack_grid = Grid::builder().row_spacing(2).column_spacing(8).build();
+        grid.attach(&stack_grid, 4, 0, 1, 16);
+
+        let location = Label::builder().halign(Align::Start).build();
+        grid.attach(&location, 0, 16, 5, 1);
+        window.set_child(Some(&grid));
+
+        return Self {
+            window,
+            sender,
+            fields,
+            stack_grid,
+            stack: Vec::new(),
+            location,
+            symbols: Symbols::default(),
+            last: None,
+        };
+    }
+
+    pub fn show(&mut self) {
+        self.window.present();
+        if let Some(snapshot) = self.last.take() {
+            self.refresh(&snapshot, None);
+            self.last = Some(snapshot);
+        }
+    }
+
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    pub fn update(&mut self, snapshot: Snapshot) {
+        let last = self.last.take();
+        if self.window.is_visible() {
+            self.refresh(&snapshot, last.as_ref());
+        }
+        self.last = Some(snapshot);
+    }
+
+    fn resize_stack(&mut self, depth: usize) {
+        while let Some(child) = self.stack_grid.first_child() {
+            self.stack_grid.remove(&child);
+        }
+        self.stack = (0..depth)
+            .map(|n| {
+                let column = (n / STACK_ROWS) as i32;
+                let row = (n % STACK_ROWS) as i32;
+                let name = format!("S{:X}", n);
+                return Field::attach(
+                    &self.stack_grid,
+                    &self.sender,
+                    &name,
+                    Register::Stack(n),
+                    column,
+                    row,
+                );
+            })
+            .collect();
+    }
+
+    fn refresh(&mut self, snapshot: &Snapshot, last: Option<&Snapshot>) {
+        if self.stack.len() != snapshot.stack.len() {
+            self.resize_stack(snapshot.stack.len());
+        }
+
+        let pc = snapshot.registers.program_counter;
+        let location = match (self.symbols.is_empty(), self.symbols.line(pc)) {
+            (true, _) => String::new(),
+            (false, Some(line)) => format!("at {} ({})", self.symbols.name(pc), line),
+            (false, None) => format!("at {}", self.symbols.name(pc)),
+        };
+        if self.location.text() != location {
+            self.location.set_text(&location);
+        }
+
+        for field in self.fields.iter().chain(self.stack.iter()) {
+            let value = Self::value(snapshot, field.register);
+            let text = match field.register {
+                Register::StackPointer => format!("{}", value),
+                Register::V(_) | Register::DelayTimer | Register::SoundTimer => {
+                    format!("{:02X}", value)
+                }
+                _ => format!("{:03X}", value),
+            };
+            if field.entry.text() != text {
+                field.entry.set_text(&text);
+            }
+
+            let changed = last.map_or(false, |last| Self::value(last, field.register) != value);
+            if changed {
+                field.entry.add_css_class("changed");
+            } else {
+                field.entry.remove_css_class("changed");
+            }
+            field.entry.set_sensitive(snapshot.paused);
+            // entries above the stack pointer hold stale return addresses
+            if let Register::Stack(n) = field.register {
+                let live = n < snapshot.registers.stack_pointer;
+                field.entry.set_opacity(if live { 1.0 } else { 0.5 });
+            }
+        }
+    }
+
+    fn value(snapshot: &Snapshot, register: Register) -> u16 {
+        let registers = &snapshot.registers;
+        return match register {
+            Register::V(x) => registers.v[x] as u16,
+            Register::I => registers.i as u16,
+            Register::ProgramCounter => registers.program_counter as u16,
+            Register::StackPointer => registers.stack_pointer as u16,
+            Register::DelayTimer => registers.delay_timer as u16,
+            Register::SoundTimer => registers.sound_timer as u16,
+            Register::Stack(n) => snapshot.stack.get(n).copied().unwrap_or(0),
+        };
+    }
+}
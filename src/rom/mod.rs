@@ -0,0 +1,166 @@
+use crate::{
+    cartridge::{Cartridge, CartridgeError, Options},
+    emulator::{Emulator, LoadError, SymbolError, Symbols},
+};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+// how often a watched ROM file is looked at
+const POLL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Debug)]
+pub struct Rom {
+    pub name: String,
+    pub sha1: String,
+    pub program: Vec<u8>,
+    // the settings an Octo cartridge came with
+    pub cartridge: Option<Options>,
+    // whatever labels and source lines came with the program itself
+    pub symbols: Symbols,
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(io::Error),
+    Load(LoadError),
+    Cartridge(CartridgeError),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RomError::Io(err) => write!(f, "{}", err),
+            RomError::Load(err) => write!(f, "{}", err),
+            RomError::Cartridge(err) => write!(f, "{}", err),
+        };
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<io::Error> for RomError {
+    fn from(err: io::Error) -> Self {
+        return RomError::Io(err);
+    }
+}
+
+impl From<LoadError> for RomError {
+    fn from(err: LoadError) -> Self {
+        return RomError::Load(err);
+    }
+}
+
+impl From<CartridgeError> for RomError {
+    fn from(err: CartridgeError) -> Self {
+        return RomError::Cartridge(err);
+    }
+}
+
+impl Rom {
+    pub fn open(path: &Path) -> Result<Self, RomError> {
+        let mut program = fs::read(path)?;
+        let mut cartridge = None;
+        let mut symbols = Symbols::default();
+        if Cartridge::is_cartridge(&program) {
+            let parsed = Cartridge::parse(&program)?;
+            program = parsed.program;
+            cartridge = Some(parsed.options);
+            symbols = parsed.symbols;
+        }
+        Emulator::check_program(&program)?;
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => path.display().to_string(),
+        };
+        return Ok(Self {
+            name,
+            sha1: Self::hash(&program),
+            program,
+            cartridge,
+            symbols,
+        });
+    }
+
+    // The ROM's own symbols along with those in a symbol file and a source
+    // map, where given. The files win where they name the same address.
+    pub fn symbols_with(
+        &self,
+        symbols: Option<&Path>,
+        source_map: Option<&Path>,
+    ) -> Result<Symbols, SymbolError> {
+        let mut loaded = match symbols {
+            Some(path) => Symbols::load(path)?,
+            None => Symbols::default(),
+        };
+        if let Some(path) = source_map {
+            loaded.load_source_map(path)?;
+        }
+        loaded.merge(&self.symbols);
+        return Ok(loaded);
+    }
+
+    // the SHA-1 the ROM database and per-ROM files are keyed by
+    pub fn hash(program: &[u8]) -> String {
+        return sha1_smol::Sha1::from(program).digest().to_string();
+    }
+}
+
+// Notices when a ROM file is rebuilt, by polling its modification time and
+// size. A change only counts once it has held still for a whole poll, so an
+// assembler that is halfway through writing the file is left to finish.
+pub struct Watcher {
+    path: PathBuf,
+    // what the file looked like when it was last reported, or first seen
+    loaded: Option<(SystemTime, u64)>,
+    pending: Option<(SystemTime, u64)>,
+    polled: Instant,
+}
+
+impl Watcher {
+    pub fn new(path: &Path) -> Self {
+        let mut watcher = Self {
+            path: path.to_path_buf(),
+            loaded: None,
+            pending: None,
+            polled: Instant::now(),
+        };
+        watcher.loaded = watcher.stamp();
+        return watcher;
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    // Whether the file has changed since the last time this said so. Cheap
+    // enough to call every frame, it only touches the file system every POLL.
+    pub fn changed(&mut self) -> bool {
+        if self.polled.elapsed() < POLL {
+            return false;
+        }
+        self.polled = Instant::now();
+        // a file that has gone missing is most likely being rewritten
+        let Some(stamp) = self.stamp() else {
+            return false;
+        };
+        if self.loaded == Some(stamp) {
+            self.pending = None;
+            return false;
+        }
+        if self.pending != Some(stamp) {
+            self.pending = Some(stamp);
+            return false;
+        }
+        self.loaded = Some(stamp);
+        self.pending = None;
+        return true;
+    }
+
+    fn stamp(&self) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(&self.path).ok()?;
+        return Some((metadata.modified().ok()?, metadata.len()));
+    }
+}
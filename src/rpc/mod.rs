@@ -0,0 +1,537 @@
+mod test;
+
+use crate::{
+    cartridge::Cartridge,
+    cheat::{Cheat, Cheats, Comparison, Search},
+    emulator::{Emulator, Platform, Reset, Settings, Symbols},
+    rom::Rom,
+};
+use serde_json::{json, Value};
+use std::path::Path;
+#[cfg(unix)]
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{fs::FileTypeExt, net::UnixListener},
+};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const EMULATOR_ERROR: i64 = -32000;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    return text;
+}
+
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut data = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in text.bytes() {
+        let value = BASE64.iter().position(|b| *b == c)? as u32;
+        bits = bits << 6 | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            data.push((bits >> count) as u8);
+        }
+    }
+    // a lone trailing character can't make up a whole byte
+    if count >= 6 {
+        return None;
+    }
+    return Some(data);
+}
+
+struct Error {
+    code: i64,
+    message: String,
+}
+
+impl Error {
+    fn new(code: i64, message: impl ToString) -> Self {
+        return Self {
+            code,
+            message: message.to_string(),
+        };
+    }
+
+    fn params(message: impl ToString) -> Self {
+        return Self::new(INVALID_PARAMS, message);
+    }
+}
+
+fn param<'a>(params: &'a Value, name: &str) -> Result<&'a Value, Error> {
+    return params
+        .get(name)
+        .ok_or_else(|| Error::params(format!("missing parameter '{}'", name)));
+}
+
+fn uint(params: &Value, name: &str, max: u64) -> Result<u64, Error> {
+    return param(params, name)?
+        .as_u64()
+        .filter(|value| *value <= max)
+        .ok_or_else(|| Error::params(format!("'{}' must be an integer up to {}", name, max)));
+}
+
+fn bytes(params: &Value, name: &str) -> Result<Vec<u8>, Error> {
+    return param(params, name)?
+        .as_str()
+        .and_then(base64_decode)
+        .ok_or_else(|| Error::params(format!("'{}' must be a base64 string", name)));
+}
+
+// One emulator driven entirely by requests, with no clock of its own: time
+// only passes when a client asks for frames.
+pub struct Session {
+    emulator: Emulator,
+    // of the loaded ROM, which its saved cheats are filed under
+    sha1: Option<String>,
+    search: Option<Search>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        return Self {
+            emulator: Emulator::new(),
+            sha1: None,
+            search: None,
+        };
+    }
+
+    // Answers one line of JSON, a request or a batch of them. Notifications
+    // get no response at all, and a batch of nothing else gets none either.
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(err) => {
+                let error = Error::new(PARSE_ERROR, err);
+                return Some(Self::respond(Value::Null, Err(error)).to_string());
+            }
+        };
+        let Value::Array(batch) = request else {
+            return self.answer(&request).map(|response| response.to_string());
+        };
+        if batch.is_empty() {
+            let error = Error::new(INVALID_REQUEST, "empty batch");
+            return Some(Self::respond(Value::Null, Err(error)).to_string());
+        }
+        let responses: Vec<Value> = batch
+            .iter()
+            .filter_map(|request| self.answer(request))
+            .collect();
+        if responses.is_empty() {
+            return None;
+        }
+        return Some(Value::Array(responses).to_string());
+    }
+
+    // Runs a single request. Only a well-formed notification, one without an
+    // id, goes unanswered; anything malformed is answered with whatever id
+    // it had, or null when there's no usable one.
+    fn answer(&mut self, request: &Value) -> Option<Value> {
+        let id = request.get("id");
+        let valid_id = matches!(
+            id,
+            None | Some(Value::Null | Value::String(_) | Value::Number(_))
+        );
+        let method = match request["method"].as_str() {
+            Some(method) if request["jsonrpc"] == "2.0" && valid_id => method,
+            _ => {
+                let id = id.filter(|_| valid_id).cloned().unwrap_or(Value::Null);
+                let error = Error::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request");
+                return Some(Self::respond(id, Err(error)));
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+        let result = self.call(method, &params);
+        return id.map(|id| Self::respond(id.clone(), result));
+    }
+
+    fn respond(id: Value, result: Result<Value, Error>) -> Value {
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        };
+        return response;
+    }
+
+    fn loaded(&self) -> Result<String, Error> {
+        return self
+            .sha1
+            .clone()
+            .ok_or_else(|| Error::new(EMULATOR_ERROR, "no ROM loaded"));
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, Error> {
+        match method {
+            // { "path": "..." } or { "rom": base64 }, plus an optional
+            // "platform" id; without one the default platform is used. An
+            // Octo cartridge's own options apply on top of the platform.
+            "load" => {
+                let (program, options, symbols) = match params.get("path") {
+                    Some(path) => {
+                        let path = path
+                            .as_str()
+                            .ok_or_else(|| Error::params("'path' must be a string"))?;
+                        let rom = Rom::open(Path::new(path))
+                            .map_err(|err| Error::new(EMULATOR_ERROR, err))?;
+                        (rom.program, rom.cartridge, rom.symbols)
+                    }
+                    None => {
+                        let rom = bytes(params, "rom")?;
+                        match Cartridge::is_cartridge(&rom) {
+                            true => {
+                                let cartridge = Cartridge::parse(&rom)
+                                    .map_err(|err| Error::new(EMULATOR_ERROR, err))?;
+                                (
+                                    cartridge.program,
+                                    Some(cartridge.options),
+                                    cartridge.symbols,
+                                )
+                            }
+                            false => (rom, None, Symbols::default()),
+                        }
+                    }
+                };
+                let platform = match params.get("platform").and_then(Value::as_str) {
+                    Some(id) => Platform::from_id(id)
+                        .ok_or_else(|| Error::params(format!("unknown platform '{}'", id)))?,
+                    None => Platform::ModernChip8,
+                };
+                let mut settings = Settings::for_platform(platform);
+                if let Some(options) = options {
+                    options.apply(&mut settings);
+                }
+                self.emulator.configure(settings);
+                self.emulator
+                    .load_program(&program)
+                    .map_err(|err| Error::new(EMULATOR_ERROR, err))?;
+                self.emulator.set_symbols(symbols);
+                self.sha1 = Some(Rom::hash(&program));
+                self.search = None;
+                return Ok(Value::Null);
+            }
+            // { "symbols": "...", "source_map": "..." }, the text of either or
+            // both, in place of whatever labels the ROM came with
+            "load_symbols" => {
+                let text = |name: &str| -> Result<&str, Error> {
+                    return match params.get(name) {
+                        Some(value) => value
+                            .as_str()
+                            .ok_or_else(|| Error::params(format!("'{}' must be a string", name))),
+                        None => Ok(""),
+                    };
+                };
+                let invalid = |err| Error::params(err);
+                let mut symbols = Symbols::parse(text("symbols")?).map_err(invalid)?;
+                symbols
+                    .add_source_map(text("source_map")?)
+                    .map_err(invalid)?;
+                self.emulator.set_symbols(symbols);
+                return Ok(Value::Null);
+            }
+            "reset" => {
+                let hard = params.get("hard").and_then(Value::as_bool).unwrap_or(true);
+                self.emulator
+                    .reset(if hard { Reset::Hard } else { Reset::Soft });
+                return Ok(Value::Null);
+            }
+            "step" => {
+                let frames = match params.get("frames") {
+                    Some(_) => uint(params, "frames", u32::MAX as u64)?,
+                    None => 1,
+                };
+                for _ in 0..frames {
+                    self.emulator.run_frame();
+                }
+                return Ok(json!({ "halted": self.emulator.is_halted() }));
+            }
+            "press" | "release" => {
+                let key = uint(params, "key", 0xF)? as u8;
+                self.emulator.keypad().set_key(key, method == "press");
+                return Ok(Value::Null);
+            }
+            "registers" => {
+                let snapshot = self.emulator.snapshot();
+                let registers = snapshot.registers;
+                let symbols = self.emulator.symbols();
+                return Ok(json!({
+                    "v": registers.v,
+                    "i": registers.i,
+                    "pc": registers.program_counter,
+                    "sp": registers.stack_pointer,
+                    "delay_timer": registers.delay_timer,
+                    "sound_timer": registers.sound_timer,
+                    "stack": snapshot.stack,
+                    // the pc by its label, and the source line it came from
+                    "location": symbols.name(registers.program_counter),
+                    "source": symbols.line(registers.program_counter),
+                }));
+            }
+            // { "address": n, "length": n }, answered with an array of bytes
+            "read_memory" => {
+                let address = uint(params, "address", 0xFFF)? as usize;
+                let length = uint(params, "length", (0x1000 - address) as u64)? as usize;
+                return Ok(json!(self.emulator.memory()[address..address + length]));
+            }
+            // one byte per pixel, row by row
+            "framebuffer" => {
+                return Ok(json!({
+                    "width": 128,
+                    "height": 64,
+                    "data": base64_encode(&self.emulator.display().concat()),
+                }));
+            }
+            "save_state" => {
+                return Ok(json!(base64_encode(&self.emulator.save_state())));
+            }
+            "load_state" => {
+                let state = bytes(params, "state")?;
+                self.emulator
+                    .load_state(&state)
+                    .map_err(|err| Error::new(EMULATOR_ERROR, err))?;
+                return Ok(Value::Null);
+            }
+            // starts over with every address as a candidate
+            "cheat_search_start" => {
+                self.search = Some(Search::new(self.emulator.memory()));
+                return Ok(json!({ "count": 0x1000 }));
+            }
+            // { "comparison": "decreased" } or { "comparison": "==", "value": 3 },
+            // answered with the addresses still in the running
+            "cheat_search" => {
+                let comparison = param(params, "comparison")?
+                    .as_str()
+                    .and_then(Comparison::parse)
+                    .ok_or_else(|| Error::params("unknown comparison"))?;
+                let value = match comparison.is_relative() {
+                    true => 0,
+                    false => uint(params, "value", 0xFF)? as u8,
+                };
+                let search = self
+                    .search
+                    .get_or_insert_with(|| Search::new(self.emulator.memory()));
+                search.filter(self.emulator.memory(), comparison, value);
+                return Ok(json!({
+                    "count": search.candidates().len(),
+                    "candidates": search.candidates(),
+                }));
+            }
+            "cheats" => {
+                let cheats: Vec<Value> = self
+                    .emulator
+                    .cheats()
+                    .cheats
+                    .iter()
+                    .map(|cheat| {
+                        json!({
+                            "address": cheat.address,
+                            "value": cheat.value,
+                            "enabled": cheat.enabled,
+                            "description": cheat.description,
+                        })
+                    })
+                    .collect();
+                return Ok(json!(cheats));
+            }
+            // { "cheats": [{ "address": n, "value": n }, ...] }, replacing the
+            // current list. Leaving out the value freezes the byte as it is,
+            // "enabled" defaults to true and "description" to nothing.
+            "set_cheats" => {
+                let mut cheats = Cheats::default();
+                let list = param(params, "cheats")?
+                    .as_array()
+                    .ok_or_else(|| Error::params("'cheats' must be an array"))?;
+                for entry in list {
+                    let address = uint(entry, "address", 0xFFF)? as usize;
+                    let description = entry["description"].as_str().unwrap_or("");
+                    let mut cheat = match entry.get("value") {
+                        Some(_) => {
+                            Cheat::new(address, uint(entry, "value", 0xFF)? as u8, description)
+                        }
+                        None => Cheat::freeze(address, self.emulator.memory(), description),
+                    };
+                    cheat.enabled = entry["enabled"].as_bool().unwrap_or(true);
+                    cheats.cheats.push(cheat);
+                }
+                self.emulator.set_cheats(cheats);
+                return Ok(Value::Null);
+            }
+            // the list saved for the loaded ROM, which may well be empty
+            "load_cheats" => {
+                let sha1 = self.loaded()?;
+                self.emulator.set_cheats(Cheats::load(&sha1));
+                return Ok(json!(self.emulator.cheats().cheats.len()));
+            }
+            "save_cheats" => {
+                let sha1 = self.loaded()?;
+                self.emulator
+                    .cheats()
+                    .save(&sha1)
+                    .map_err(|err| Error::new(EMULATOR_ERROR, err))?;
+                return Ok(Value::Null);
+            }
+            // { "enabled": true } starts counting from scratch, false stops
+            "profile" => {
+                let enabled = param(params, "enabled")?
+                    .as_bool()
+                    .ok_or_else(|| Error::params("'enabled' must be a boolean"))?;
+                self.emulator.set_profiling(enabled);
+                return Ok(Value::Null);
+            }
+            // the text report, with "top" rows per table, and the call chains
+            // in flamegraph's folded format
+            "profile_report" => {
+                let top = match params.get("top") {
+                    Some(_) => uint(params, "top", 0x1000)? as usize,
+                    None => 20,
+                };
+                let profiler = self
+                    .emulator
+                    .profiler()
+                    .ok_or_else(|| Error::new(EMULATOR_ERROR, "not profiling"))?;
+                return Ok(json!({
+                    "instructions": profiler.total(),
+                    "report": profiler.report(top, self.emulator.symbols()),
+                    "folded": profiler.folded(self.emulator.symbols()),
+                }));
+            }
+            // { "enabled": true } starts tracking the loaded program's bytes
+            "coverage" => {
+                let enabled = param(params, "enabled")?
+                    .as_bool()
+                    .ok_or_else(|| Error::params("'enabled' must be a boolean"))?;
+                self.emulator.set_coverage(enabled);
+                return Ok(Value::Null);
+            }
+            "coverage_report" => {
+                let coverage = self
+                    .emulator
+                    .coverage()
+                    .ok_or_else(|| Error::new(EMULATOR_ERROR, "not tracking coverage"))?;
+                let (executed, read, written, untouched) = coverage.summary();
+                return Ok(json!({
+                    "executed": executed,
+                    "read": read,
+                    "written": written,
+                    "untouched": untouched,
+                    "map": coverage.map(),
+                    "disassembly": coverage.annotate(self.emulator.memory(), self.emulator.symbols()),
+                }));
+            }
+            // { "enabled": true } starts flagging suspicious memory accesses
+            "diagnostics" => {
+                let enabled = param(params, "enabled")?
+                    .as_bool()
+                    .ok_or_else(|| Error::params("'enabled' must be a boolean"))?;
+                self.emulator.set_diagnostics(enabled);
+                return Ok(Value::Null);
+            }
+            "warnings" => {
+                let diagnostics = self
+                    .emulator
+                    .diagnostics()
+                    .ok_or_else(|| Error::new(EMULATOR_ERROR, "diagnostics are off"))?;
+                let warnings: Vec<Value> = diagnostics
+                    .warnings()
+                    .iter()
+                    .map(|warning| {
+                        json!({
+                            "pc": warning.pc,
+                            "count": warning.count,
+                            "message": warning.describe(self.emulator.symbols()),
+                        })
+                    })
+                    .collect();
+                return Ok(json!(warnings));
+            }
+            _ => {
+                return Err(Error::new(
+                    METHOD_NOT_FOUND,
+                    format!("no method '{}'", method),
+                ))
+            }
+        }
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+// Listens on a Unix socket for newline-delimited JSON-RPC, one client at a
+// time. All clients share the same emulator.
+#[cfg(unix)]
+pub fn serve(path: &Path) -> io::Result<()> {
+    // a socket left behind by an earlier run would make bind fail, but
+    // anything else at the path is the user's and stays put
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the path exists and is not a socket",
+            ))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    let listener = UnixListener::bind(path)?;
+    let mut session = Session::new();
+    for stream in listener.incoming() {
+        // one client failing to connect is no reason to stop serving others
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("could not accept a connection: {}", err);
+                continue;
+            }
+        };
+        let Ok(mut writer) = stream.try_clone() else {
+            continue;
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = session.handle(&line) {
+                if writeln!(writer, "{}", response).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    return Ok(());
+}
@@ -0,0 +1,289 @@
+#[cfg(test)]
+use super::*;
+
+#[test]
+fn test_base64() {
+    for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"] {
+        assert_eq!(base64_decode(&base64_encode(data)).unwrap(), data);
+    }
+    assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+    assert_eq!(base64_decode("Zm9vYmFy").unwrap(), b"foobar");
+    assert_eq!(base64_decode("Zm9v!"), None);
+    assert_eq!(base64_decode("Z"), None);
+}
+
+#[cfg(test)]
+fn call(session: &mut Session, request: &str) -> Value {
+    return serde_json::from_str(&session.handle(request).unwrap()).unwrap();
+}
+
+#[test]
+fn test_session() {
+    let mut session = Session::new();
+
+    // LD V0, 7; LD F, V0; LD V1, K; DRW V0, V0, 5; JP 0x208
+    let rom = base64_encode(&[0x60, 0x07, 0xF0, 0x29, 0xF1, 0x0A, 0xD0, 0x05, 0x12, 0x08]);
+    let response = call(
+        &mut session,
+        &format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"load","params":{{"rom":"{}"}}}}"#,
+            rom
+        ),
+    );
+    assert_eq!(response["result"], Value::Null);
+
+    let step = r#"{"jsonrpc":"2.0","id":2,"method":"step","params":{"frames":2}}"#;
+    assert_eq!(call(&mut session, step)["result"]["halted"], false);
+    let registers = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":3,"method":"registers"}"#,
+    );
+    assert_eq!(registers["result"]["v"][0], 7);
+    assert_eq!(registers["result"]["pc"], 0x204);
+
+    // notifications are carried out but never answered
+    assert_eq!(
+        session.handle(r#"{"jsonrpc":"2.0","method":"press","params":{"key":3}}"#),
+        None
+    );
+    call(&mut session, step);
+    let registers = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":4,"method":"registers"}"#,
+    );
+    assert_eq!(registers["result"]["v"][1], 3);
+
+    let framebuffer = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":5,"method":"framebuffer"}"#,
+    );
+    let pixels = base64_decode(framebuffer["result"]["data"].as_str().unwrap()).unwrap();
+    assert_eq!(pixels.len(), 128 * 64);
+    assert!(pixels.contains(&1));
+
+    let memory = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":6,"method":"read_memory","params":{"address":512,"length":2}}"#,
+    );
+    assert_eq!(memory["result"], json!([0x60, 0x07]));
+
+    let state = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":7,"method":"save_state"}"#,
+    );
+    call(&mut session, r#"{"jsonrpc":"2.0","id":8,"method":"reset"}"#);
+    let response = call(
+        &mut session,
+        &format!(
+            r#"{{"jsonrpc":"2.0","id":9,"method":"load_state","params":{{"state":{}}}}}"#,
+            state["result"]
+        ),
+    );
+    assert_eq!(response["result"], Value::Null);
+    let registers = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":10,"method":"registers"}"#,
+    );
+    assert_eq!(registers["result"]["v"][1], 3);
+}
+
+#[test]
+fn test_session_errors() {
+    let mut session = Session::new();
+    assert_eq!(call(&mut session, "{")["error"]["code"], PARSE_ERROR);
+    assert_eq!(
+        call(&mut session, r#"{"id":1,"method":"step"}"#)["error"]["code"],
+        INVALID_REQUEST
+    );
+    assert_eq!(
+        call(&mut session, r#"{"jsonrpc":"2.0","id":1,"method":"fly"}"#)["error"]["code"],
+        METHOD_NOT_FOUND
+    );
+    let press = r#"{"jsonrpc":"2.0","id":1,"method":"press","params":{"key":16}}"#;
+    assert_eq!(call(&mut session, press)["error"]["code"], INVALID_PARAMS);
+    let read =
+        r#"{"jsonrpc":"2.0","id":1,"method":"read_memory","params":{"address":4095,"length":2}}"#;
+    assert_eq!(call(&mut session, read)["error"]["code"], INVALID_PARAMS);
+    let state = r#"{"jsonrpc":"2.0","id":1,"method":"load_state","params":{"state":"AAAA"}}"#;
+    assert_eq!(call(&mut session, state)["error"]["code"], EMULATOR_ERROR);
+
+    // malformed requests are answered even without an id, unlike notifications
+    let malformed = call(&mut session, r#"{"method":"step"}"#);
+    assert_eq!(malformed["error"]["code"], INVALID_REQUEST);
+    assert_eq!(malformed["id"], Value::Null);
+    let malformed = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":[1],"method":"step"}"#,
+    );
+    assert_eq!(malformed["id"], Value::Null);
+    assert_eq!(call(&mut session, "1")["error"]["code"], INVALID_REQUEST);
+    assert_eq!(session.handle(r#"{"jsonrpc":"2.0","method":"fly"}"#), None);
+}
+
+#[test]
+fn test_session_batch() {
+    let mut session = Session::new();
+    let responses = call(
+        &mut session,
+        r#"[{"jsonrpc":"2.0","id":1,"method":"fly"},
+            {"jsonrpc":"2.0","method":"press","params":{"key":3}},
+            {"id":2},
+            {"jsonrpc":"2.0","id":"three","method":"fly"}]"#,
+    );
+    let responses = responses.as_array().unwrap();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[1]["error"]["code"], INVALID_REQUEST);
+    assert_eq!(responses[1]["id"], 2);
+    assert_eq!(responses[2]["id"], "three");
+
+    assert_eq!(call(&mut session, "[]")["error"]["code"], INVALID_REQUEST);
+    assert_eq!(
+        call(&mut session, "[1]")[0]["error"]["code"],
+        INVALID_REQUEST
+    );
+    let notifications = r#"[{"jsonrpc":"2.0","method":"press","params":{"key":3}}]"#;
+    assert_eq!(session.handle(notifications), None);
+}
+
+#[test]
+fn test_session_cheats() {
+    let mut session = Session::new();
+
+    // LD I, 0x300; LD V0, [I]; ADD V0, 1; LD I, 0x300; LD [I], V0; JP 0x200
+    let rom = base64_encode(&[
+        0xA3, 0x00, 0xF0, 0x65, 0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00,
+    ]);
+    call(
+        &mut session,
+        &format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"load","params":{{"rom":"{}"}}}}"#,
+            rom
+        ),
+    );
+    call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":2,"method":"cheat_search_start"}"#,
+    );
+    let step = r#"{"jsonrpc":"2.0","id":3,"method":"step"}"#;
+    let search =
+        r#"{"jsonrpc":"2.0","id":4,"method":"cheat_search","params":{"comparison":"increased"}}"#;
+    call(&mut session, step);
+    call(&mut session, search);
+    call(&mut session, step);
+    let found = call(&mut session, search);
+    assert_eq!(found["result"]["candidates"], json!([0x300]));
+
+    let set = r#"{"jsonrpc":"2.0","id":5,"method":"set_cheats","params":{"cheats":[{"address":768,"value":200}]}}"#;
+    assert_eq!(call(&mut session, set)["result"], Value::Null);
+    call(&mut session, step);
+    let memory = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":6,"method":"read_memory","params":{"address":768,"length":1}}"#,
+    );
+    // the frame starts from the forced value and counts up from there
+    assert!(memory["result"][0].as_u64().unwrap() > 200);
+    let cheats = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":7,"method":"cheats"}"#,
+    );
+    assert_eq!(cheats["result"][0]["value"], 200);
+}
+
+#[test]
+fn test_session_profile() {
+    let mut session = Session::new();
+    // CALL 0x206; JP 0x200; ...; ADD V0, 1; RET
+    let rom = base64_encode(&[0x22, 0x06, 0x12, 0x00, 0x00, 0x00, 0x70, 0x01, 0x00, 0xEE]);
+    call(
+        &mut session,
+        &format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"load","params":{{"rom":"{}"}}}}"#,
+            rom
+        ),
+    );
+    let report = r#"{"jsonrpc":"2.0","id":2,"method":"profile_report"}"#;
+    assert_eq!(call(&mut session, report)["error"]["code"], EMULATOR_ERROR);
+
+    call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":3,"method":"profile","params":{"enabled":true}}"#,
+    );
+    call(&mut session, r#"{"jsonrpc":"2.0","id":4,"method":"step"}"#);
+    let profile = call(&mut session, report);
+    assert_eq!(profile["result"]["instructions"], 12);
+    assert_eq!(
+        profile["result"]["folded"],
+        "main_200 6\nmain_200;sub_206 6\n"
+    );
+
+    call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":5,"method":"load_symbols","params":{"symbols":":const main 0x200\n:const bump 0x206\n","source_map":"0x206 game.8o:9"}}"#,
+    );
+    let profile = call(&mut session, report);
+    assert_eq!(profile["result"]["folded"], "main 6\nmain;bump 6\n");
+    let registers = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":6,"method":"registers"}"#,
+    );
+    assert_eq!(registers["result"]["location"], "main");
+    let invalid = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":7,"method":"load_symbols","params":{"symbols":"main"}}"#,
+    );
+    assert_eq!(invalid["error"]["code"], INVALID_PARAMS);
+}
+
+#[test]
+fn test_session_coverage() {
+    let mut session = Session::new();
+    // JP 0x200, followed by a jump that never runs
+    let rom = base64_encode(&[0x12, 0x00, 0x12, 0x02]);
+    call(
+        &mut session,
+        &format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"load","params":{{"rom":"{}"}}}}"#,
+            rom
+        ),
+    );
+    call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":2,"method":"coverage","params":{"enabled":true}}"#,
+    );
+    call(&mut session, r#"{"jsonrpc":"2.0","id":3,"method":"step"}"#);
+    let report = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":4,"method":"coverage_report"}"#,
+    );
+    assert_eq!(report["result"]["executed"], 2);
+    assert_eq!(report["result"]["untouched"], 2);
+}
+
+#[test]
+fn test_session_diagnostics() {
+    let mut session = Session::new();
+    // LD I, 0x200; LD [I], V0; JP 0x204
+    let rom = base64_encode(&[0xA2, 0x00, 0xF0, 0x55, 0x12, 0x04]);
+    call(
+        &mut session,
+        &format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"load","params":{{"rom":"{}"}}}}"#,
+            rom
+        ),
+    );
+    call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":2,"method":"diagnostics","params":{"enabled":true}}"#,
+    );
+    call(&mut session, r#"{"jsonrpc":"2.0","id":3,"method":"step"}"#);
+    let warnings = call(
+        &mut session,
+        r#"{"jsonrpc":"2.0","id":4,"method":"warnings"}"#,
+    );
+    assert_eq!(warnings["result"][0]["pc"], 0x202);
+    assert_eq!(
+        warnings["result"][0]["message"],
+        "202: wrote to 200, which has run as code"
+    );
+}
@@ -0,0 +1,20 @@
+use crate::palette::Palette;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+pub fn save(path: &Path, display: &[[u8; 128]; 64], palette: &Palette) -> io::Result<()> {
+    let mut ppm = format!("P6\n{} {}\n255\n", display[0].len(), display.len()).into_bytes();
+    ppm.extend(palette.render(display));
+    return fs::write(path, ppm);
+}
+
+pub fn default_path() -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("failed to get time")
+        .as_secs();
+    return PathBuf::from(format!("chip8-{}.ppm", secs));
+}